@@ -0,0 +1,16 @@
+use std::process::Command;
+
+// Embeds the commit the server was built from for GET /version
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output();
+
+    if let Ok(output) = output {
+        if output.status.success() {
+            let hash = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=EGLINE_GIT_HASH={}", hash.trim());
+        }
+    }
+}
@@ -10,6 +10,19 @@ mod m20240428_214720_create_table_bookauthor;
 mod m20240428_222109_create_table_comment;
 mod m20240428_225721_create_table_book_rate;
 mod m20240428_230452_create_table_comment_rate;
+mod m20240512_101530_create_table_review;
+mod m20240512_101545_create_table_review_vote;
+mod m20240519_184210_create_table_reading_progress;
+mod m20240526_143005_create_table_book_follow;
+mod m20240526_143012_create_table_author_follow;
+mod m20240526_143020_create_table_notification;
+mod m20240602_091140_create_table_user_session;
+mod m20240609_120000_add_role_to_user;
+mod m20240616_120000_add_timestamps;
+mod m20240623_120000_add_deleted_at;
+mod m20240630_120000_create_table_audit_log;
+mod m20240707_120000_add_integrity_constraints;
+mod m20240714_120000_add_book_status_enum;
 
 pub struct Migrator;
 
@@ -27,6 +40,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20240428_222109_create_table_comment::Migration),
             Box::new(m20240428_225721_create_table_book_rate::Migration),
             Box::new(m20240428_230452_create_table_comment_rate::Migration),
+            Box::new(m20240512_101530_create_table_review::Migration),
+            Box::new(m20240512_101545_create_table_review_vote::Migration),
+            Box::new(m20240519_184210_create_table_reading_progress::Migration),
+            Box::new(m20240526_143005_create_table_book_follow::Migration),
+            Box::new(m20240526_143012_create_table_author_follow::Migration),
+            Box::new(m20240526_143020_create_table_notification::Migration),
+            Box::new(m20240602_091140_create_table_user_session::Migration),
+            Box::new(m20240609_120000_add_role_to_user::Migration),
+            Box::new(m20240616_120000_add_timestamps::Migration),
+            Box::new(m20240623_120000_add_deleted_at::Migration),
+            Box::new(m20240630_120000_create_table_audit_log::Migration),
+            Box::new(m20240707_120000_add_integrity_constraints::Migration),
+            Box::new(m20240714_120000_add_book_status_enum::Migration),
         ]
     }
 }
@@ -51,7 +51,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum BookGenre {
+pub enum BookGenre {
     Table,
     BookId,
     GenreId
@@ -52,7 +52,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum BookRate {
+pub enum BookRate {
     Table,
     BookId,
     UserId,
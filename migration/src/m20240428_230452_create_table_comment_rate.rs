@@ -52,7 +52,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum CommentRate {
+pub enum CommentRate {
     Table,
     CommentId,
     UserId,
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_222412_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Review::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Review::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Review::UserId).integer().not_null())
+                    .col(ColumnDef::new(Review::BookId).integer().not_null())
+                    .col(ColumnDef::new(Review::Rate).integer().not_null())
+                    .col(ColumnDef::new(Review::Title).string().not_null())
+                    .col(ColumnDef::new(Review::Body).text().not_null())
+                    .col(ColumnDef::new(Review::Spoiler).boolean().not_null().default(false))
+                    .col(ColumnDef::new(Review::Helpful).integer().not_null().default(0))
+                    .col(ColumnDef::new(Review::Unhelpful).integer().not_null().default(0))
+                    .col(ColumnDef::new(Review::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(Review::EditedAt).timestamp())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-review-user_id")
+                            .from(Review::Table, Review::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-review-book_id")
+                            .from(Review::Table, Review::BookId)
+                            .to(Book::Table, Book::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-review-user_id-book_id")
+                            .table(Review::Table)
+                            .col(Review::UserId)
+                            .col(Review::BookId)
+                            .unique()
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Review::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Review {
+    Table,
+    Id,
+    UserId,
+    BookId,
+    Rate,
+    Title,
+    Body,
+    Spoiler,
+    Helpful,
+    Unhelpful,
+    CreatedAt,
+    EditedAt
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_222412_create_table_user::User;
+use super::m20240512_101530_create_table_review::Review;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReviewVote::Table)
+                    .if_not_exists()
+                    .primary_key(
+                        Index::create()
+                            .table(ReviewVote::Table)
+                            .col(ReviewVote::ReviewId)
+                            .col(ReviewVote::UserId)
+                    )
+                    .col(ColumnDef::new(ReviewVote::ReviewId).integer().not_null())
+                    .col(ColumnDef::new(ReviewVote::UserId).integer().not_null())
+                    .col(ColumnDef::new(ReviewVote::Vote).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-review_vote-review_id")
+                            .from(ReviewVote::Table, ReviewVote::ReviewId)
+                            .to(Review::Table, Review::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-review_vote-user_id")
+                            .from(ReviewVote::Table, ReviewVote::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReviewVote::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ReviewVote {
+    Table,
+    ReviewId,
+    UserId,
+    Vote,
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_222412_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReadingProgress::Table)
+                    .if_not_exists()
+                    .primary_key(
+                        Index::create()
+                            .table(ReadingProgress::Table)
+                            .col(ReadingProgress::UserId)
+                            .col(ReadingProgress::BookId)
+                    )
+                    .col(ColumnDef::new(ReadingProgress::UserId).integer().not_null())
+                    .col(ColumnDef::new(ReadingProgress::BookId).integer().not_null())
+                    .col(ColumnDef::new(ReadingProgress::ChapterNumber).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reading_progress-user_id")
+                            .from(ReadingProgress::Table, ReadingProgress::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reading_progress-book_id")
+                            .from(ReadingProgress::Table, ReadingProgress::BookId)
+                            .to(Book::Table, Book::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReadingProgress::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ReadingProgress {
+    Table,
+    UserId,
+    BookId,
+    ChapterNumber,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_222412_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookFollow::Table)
+                    .if_not_exists()
+                    .primary_key(
+                        Index::create()
+                            .table(BookFollow::Table)
+                            .col(BookFollow::UserId)
+                            .col(BookFollow::BookId)
+                    )
+                    .col(ColumnDef::new(BookFollow::UserId).integer().not_null())
+                    .col(ColumnDef::new(BookFollow::BookId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_follow-user_id")
+                            .from(BookFollow::Table, BookFollow::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_follow-book_id")
+                            .from(BookFollow::Table, BookFollow::BookId)
+                            .to(Book::Table, Book::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookFollow::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum BookFollow {
+    Table,
+    UserId,
+    BookId,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_164712_create_table_author::Author;
+use super::m20240427_222412_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuthorFollow::Table)
+                    .if_not_exists()
+                    .primary_key(
+                        Index::create()
+                            .table(AuthorFollow::Table)
+                            .col(AuthorFollow::UserId)
+                            .col(AuthorFollow::AuthorId)
+                    )
+                    .col(ColumnDef::new(AuthorFollow::UserId).integer().not_null())
+                    .col(ColumnDef::new(AuthorFollow::AuthorId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-author_follow-user_id")
+                            .from(AuthorFollow::Table, AuthorFollow::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-author_follow-author_id")
+                            .from(AuthorFollow::Table, AuthorFollow::AuthorId)
+                            .to(Author::Table, Author::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuthorFollow::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum AuthorFollow {
+    Table,
+    UserId,
+    AuthorId,
+}
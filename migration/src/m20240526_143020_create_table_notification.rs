@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_222412_create_table_user::User;
+use super::m20240427_223114_create_table_chapter::Chapter;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notification::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Notification::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Notification::UserId).integer().not_null())
+                    .col(ColumnDef::new(Notification::Kind).string().not_null())
+                    .col(ColumnDef::new(Notification::BookId).integer())
+                    .col(ColumnDef::new(Notification::ChapterId).integer())
+                    .col(ColumnDef::new(Notification::Message).string().not_null())
+                    .col(ColumnDef::new(Notification::IsRead).boolean().not_null().default(false))
+                    .col(ColumnDef::new(Notification::CreatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-notification-user_id")
+                            .from(Notification::Table, Notification::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-notification-book_id")
+                            .from(Notification::Table, Notification::BookId)
+                            .to(Book::Table, Book::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-notification-chapter_id")
+                            .from(Notification::Table, Notification::ChapterId)
+                            .to(Chapter::Table, Chapter::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notification-user_id-is_read")
+                    .table(Notification::Table)
+                    .col(Notification::UserId)
+                    .col(Notification::IsRead)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notification::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Notification {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    BookId,
+    ChapterId,
+    Message,
+    IsRead,
+    CreatedAt,
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_222412_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserSession::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserSession::Token)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserSession::UserId).integer().not_null())
+                    .col(ColumnDef::new(UserSession::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(UserSession::ExpiresAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_session-user_id")
+                            .from(UserSession::Table, UserSession::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserSession::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum UserSession {
+    Table,
+    Token,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+}
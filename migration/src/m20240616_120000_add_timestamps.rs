@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_164602_create_table_genre::Genre;
+use super::m20240427_164712_create_table_author::Author;
+use super::m20240427_222412_create_table_user::User;
+use super::m20240428_222109_create_table_comment::Comment;
+use super::m20240428_225721_create_table_book_rate::BookRate;
+use super::m20240428_230452_create_table_comment_rate::CommentRate;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn tables() -> Vec<DynIden> {
+    vec![
+        Book::Table.into_iden(),
+        Comment::Table.into_iden(),
+        User::Table.into_iden(),
+        BookRate::Table.into_iden(),
+        CommentRate::Table.into_iden(),
+        Author::Table.into_iden(),
+        Genre::Table.into_iden(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let backend = manager.get_database_backend();
+
+        for table in tables() {
+            // SQLite adds one column per statement
+            for column in [Timestamps::CreatedAt, Timestamps::UpdatedAt] {
+                let mut column = ColumnDef::new(column);
+                column.timestamp().not_null();
+
+                // SQLite can not add a column whose default is not a constant,
+                // there the existing rows are stamped by the update below
+                match backend {
+                    DbBackend::Sqlite => column.default("1970-01-01 00:00:00"),
+                    _ => column.default(Expr::current_timestamp())
+                };
+
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(table.clone())
+                            .add_column(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+
+            if backend == DbBackend::Sqlite {
+                manager
+                    .exec_stmt(
+                        Query::update()
+                            .table(table.clone())
+                            .value(Timestamps::CreatedAt, Expr::current_timestamp())
+                            .value(Timestamps::UpdatedAt, Expr::current_timestamp())
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in tables() {
+            for column in [Timestamps::CreatedAt, Timestamps::UpdatedAt] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(table.clone())
+                            .drop_column(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Timestamps {
+    CreatedAt,
+    UpdatedAt,
+}
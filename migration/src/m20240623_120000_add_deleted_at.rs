@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_164712_create_table_author::Author;
+use super::m20240427_222412_create_table_user::User;
+use super::m20240428_222109_create_table_comment::Comment;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn tables() -> Vec<DynIden> {
+    vec![
+        Book::Table.into_iden(),
+        Author::Table.into_iden(),
+        User::Table.into_iden(),
+        Comment::Table.into_iden(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in tables() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(ColumnDef::new(SoftDelete::DeletedAt).timestamp().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in tables() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(SoftDelete::DeletedAt)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SoftDelete {
+    DeletedAt,
+}
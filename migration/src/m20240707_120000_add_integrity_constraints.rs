@@ -0,0 +1,227 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_223114_create_table_chapter::Chapter;
+use super::m20240428_214711_create_table_bookgenre::BookGenre;
+use super::m20240428_214720_create_table_bookauthor::BookAuthor;
+use super::m20240428_222109_create_table_comment::Comment;
+use super::m20240428_225721_create_table_book_rate::BookRate;
+use super::m20240428_230452_create_table_comment_rate::CommentRate;
+use super::m20240512_101530_create_table_review::Review;
+use super::m20240512_101545_create_table_review_vote::ReviewVote;
+use super::m20240519_184210_create_table_reading_progress::ReadingProgress;
+use super::m20240526_143005_create_table_book_follow::BookFollow;
+use super::m20240526_143012_create_table_author_follow::AuthorFollow;
+use super::m20240526_143020_create_table_notification::Notification;
+use super::m20240602_091140_create_table_user_session::UserSession;
+
+const CHAPTER_FOREIGN_KEY: &str = "fk-chapter-book_id";
+const CHAPTER_NUMBER_INDEX: &str = "idx-chapter-book_id-number";
+
+/// Allowed values of the `rate` columns: the table, the constraint name, the condition on `rate`
+/// and the column of the rated row
+const RATE_CHECKS: [(&str, &str, &str, &str); 2] = [
+    ("book_rate", "chk-book_rate-rate", "BETWEEN 1 AND 5", "book_id"),
+    ("comment_rate", "chk-comment_rate-rate", "IN (-1, 1)", "comment_id"),
+];
+
+/// Events the SQLite triggers standing in for the rate checks run on, with their name suffix
+const CHECKED_EVENTS: [(&str, &str); 2] = [("insert", "INSERT"), ("update", r#"UPDATE OF "rate""#)];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Foreign key columns that do not lead an index yet. `chapter.book_id` leads the unique index on
+/// `(book_id, number)`, and the primary keys of the link tables cover their first column.
+fn foreign_key_indexes() -> Vec<(&'static str, DynIden, DynIden)> {
+    vec![
+        ("idx-book_genre-genre_id", BookGenre::Table.into_iden(), BookGenre::GenreId.into_iden()),
+        ("idx-book_author-author_id", BookAuthor::Table.into_iden(), BookAuthor::AuthorId.into_iden()),
+        ("idx-comment-book_id", Comment::Table.into_iden(), Comment::BookId.into_iden()),
+        ("idx-comment-user_id", Comment::Table.into_iden(), Comment::UserId.into_iden()),
+        ("idx-comment-chapter_id", Comment::Table.into_iden(), Comment::ChapterId.into_iden()),
+        ("idx-book_rate-user_id", BookRate::Table.into_iden(), BookRate::UserId.into_iden()),
+        ("idx-comment_rate-user_id", CommentRate::Table.into_iden(), CommentRate::UserId.into_iden()),
+        ("idx-review-book_id", Review::Table.into_iden(), Review::BookId.into_iden()),
+        ("idx-review_vote-user_id", ReviewVote::Table.into_iden(), ReviewVote::UserId.into_iden()),
+        ("idx-reading_progress-book_id", ReadingProgress::Table.into_iden(), ReadingProgress::BookId.into_iden()),
+        ("idx-book_follow-book_id", BookFollow::Table.into_iden(), BookFollow::BookId.into_iden()),
+        ("idx-author_follow-author_id", AuthorFollow::Table.into_iden(), AuthorFollow::AuthorId.into_iden()),
+        ("idx-notification-book_id", Notification::Table.into_iden(), Notification::BookId.into_iden()),
+        ("idx-notification-chapter_id", Notification::Table.into_iden(), Notification::ChapterId.into_iden()),
+        ("idx-user_session-user_id", UserSession::Table.into_iden(), UserSession::UserId.into_iden()),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        // Rates saved before they were validated would break the checks. They are not guessed at,
+        // the rows have to be fixed or removed before migrating
+        for (table, _, condition, rated) in RATE_CHECKS {
+            let invalid = db
+                .query_all(Statement::from_string(
+                    backend,
+                    format!(
+                        r#"SELECT "{1}", "user_id", "rate" FROM "{0}" WHERE NOT ("rate" {2}) ORDER BY "{1}", "user_id""#,
+                        table, rated, condition
+                    ),
+                ))
+                .await?
+                .iter()
+                .map(|row| Ok(format!(
+                    "{} {} by user {} ({})",
+                    rated,
+                    row.try_get::<i32>("", rated)?,
+                    row.try_get::<i32>("", "user_id")?,
+                    row.try_get::<i32>("", "rate")?
+                )))
+                .collect::<Result<Vec<String>, DbErr>>()?;
+
+            if !invalid.is_empty() {
+                return Err(DbErr::Migration(format!(
+                    "Rows of {} with a rate not {}: {}. Fix or remove them first",
+                    table,
+                    condition.to_lowercase(),
+                    invalid.join(", ")
+                )));
+            }
+        }
+
+        // Fails on databases that already have two chapters with the same number in a book,
+        // one of them has to be renumbered or removed first
+        manager
+            .create_index(
+                Index::create()
+                    .name(CHAPTER_NUMBER_INDEX)
+                    .table(Chapter::Table)
+                    .col(Chapter::BookId)
+                    .col(Chapter::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        for (name, table, column) in foreign_key_indexes() {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        match backend {
+            // SQLite can neither change a foreign key nor add a constraint to an existing table,
+            // triggers do the same work there
+            DbBackend::Sqlite => {
+                db.execute_unprepared(&format!(
+                    r#"CREATE TRIGGER "{0}-cascade" BEFORE DELETE ON "book" BEGIN DELETE FROM "chapter" WHERE "book_id" = OLD."id"; END"#,
+                    CHAPTER_FOREIGN_KEY
+                )).await?;
+
+                for (table, name, condition, _) in RATE_CHECKS {
+                    for (suffix, event) in CHECKED_EVENTS {
+                        db.execute_unprepared(&format!(
+                            r#"CREATE TRIGGER "{1}-{2}" BEFORE {3} ON "{0}" WHEN NOT (NEW."rate" {4}) BEGIN SELECT RAISE(ABORT, 'CHECK constraint failed: {1}'); END"#,
+                            table, name, suffix, event, condition
+                        )).await?;
+                    }
+                }
+            },
+            _ => {
+                manager
+                    .drop_foreign_key(
+                        ForeignKey::drop()
+                            .name(CHAPTER_FOREIGN_KEY)
+                            .table(Chapter::Table)
+                            .to_owned(),
+                    )
+                    .await?;
+
+                manager
+                    .create_foreign_key(
+                        ForeignKey::create()
+                            .name(CHAPTER_FOREIGN_KEY)
+                            .from(Chapter::Table, Chapter::BookId)
+                            .to(Book::Table, Book::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .to_owned(),
+                    )
+                    .await?;
+
+                for (table, name, condition, _) in RATE_CHECKS {
+                    db.execute_unprepared(&format!(
+                        r#"ALTER TABLE "{}" ADD CONSTRAINT "{}" CHECK ("rate" {})"#,
+                        table, name, condition
+                    )).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        match manager.get_database_backend() {
+            DbBackend::Sqlite => {
+                db.execute_unprepared(&format!(r#"DROP TRIGGER "{}-cascade""#, CHAPTER_FOREIGN_KEY)).await?;
+
+                for (_, name, _, _) in RATE_CHECKS {
+                    for (suffix, _) in CHECKED_EVENTS {
+                        db.execute_unprepared(&format!(r#"DROP TRIGGER "{}-{}""#, name, suffix)).await?;
+                    }
+                }
+            },
+            _ => {
+                for (table, name, _, _) in RATE_CHECKS {
+                    db.execute_unprepared(&format!(r#"ALTER TABLE "{}" DROP CONSTRAINT "{}""#, table, name)).await?;
+                }
+
+                manager
+                    .drop_foreign_key(
+                        ForeignKey::drop()
+                            .name(CHAPTER_FOREIGN_KEY)
+                            .table(Chapter::Table)
+                            .to_owned(),
+                    )
+                    .await?;
+
+                manager
+                    .create_foreign_key(
+                        ForeignKey::create()
+                            .name(CHAPTER_FOREIGN_KEY)
+                            .from(Chapter::Table, Chapter::BookId)
+                            .to(Book::Table, Book::Id)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        for (name, table, _) in foreign_key_indexes() {
+            manager
+                .drop_index(Index::drop().name(name).table(table).to_owned())
+                .await?;
+        }
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(CHAPTER_NUMBER_INDEX)
+                    .table(Chapter::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
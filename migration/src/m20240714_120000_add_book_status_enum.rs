@@ -0,0 +1,184 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend};
+
+use super::m20240427_083430_create_table_book::Book;
+use super::m20240427_222412_create_table_user::User;
+
+const STATUSES: [BookStatus; 5] = [
+    BookStatus::Ongoing,
+    BookStatus::Completed,
+    BookStatus::Hiatus,
+    BookStatus::Dropped,
+    BookStatus::Announced,
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        // Statuses saved before they were validated can not be cast. They are not guessed at,
+        // the books have to be given one of the statuses before migrating
+        let unknown = db
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .column(Book::Id)
+                        .column(Book::Status)
+                        .from(Book::Table)
+                        .and_where(Expr::col(Book::Status).is_not_in(STATUSES.map(|status| status.to_string())))
+                        .order_by(Book::Id, Order::Asc),
+                ),
+            )
+            .await?
+            .iter()
+            .map(|row| Ok(format!("{} ({})", row.try_get::<i32>("", "id")?, row.try_get::<String>("", "status")?)))
+            .collect::<Result<Vec<String>, DbErr>>()?;
+
+        if !unknown.is_empty() {
+            return Err(DbErr::Migration(format!(
+                "Books with unknown statuses: {}. Set them to one of {} first",
+                unknown.join(", "),
+                STATUSES.map(|status| status.to_string()).join(", ")
+            )));
+        }
+
+        // SQLite has no enum types, the column stays text there
+        if backend == DbBackend::Postgres {
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(BookStatus::Enum)
+                        .values(STATUSES)
+                        .to_owned(),
+                )
+                .await?;
+
+            db.execute_unprepared(
+                r#"ALTER TABLE "book" ALTER COLUMN "status" TYPE "book_status" USING "status"::"book_status""#
+            ).await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookStatusChange::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BookStatusChange::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BookStatusChange::BookId).integer().not_null())
+                    .col(ColumnDef::new(BookStatusChange::FromStatus).enumeration(BookStatus::Enum, STATUSES))
+                    .col(ColumnDef::new(BookStatusChange::ToStatus).enumeration(BookStatus::Enum, STATUSES).not_null())
+                    .col(ColumnDef::new(BookStatusChange::UserId).integer())
+                    .col(ColumnDef::new(BookStatusChange::ChangedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_status_change-book_id")
+                            .from(BookStatusChange::Table, BookStatusChange::BookId)
+                            .to(Book::Table, Book::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_status_change-user_id")
+                            .from(BookStatusChange::Table, BookStatusChange::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-book_status_change-book_id")
+                    .table(BookStatusChange::Table)
+                    .col(BookStatusChange::BookId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-book_status_change-user_id")
+                    .table(BookStatusChange::Table)
+                    .col(BookStatusChange::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Histories of the existing books start with their current status
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(BookStatusChange::Table)
+                    .columns([BookStatusChange::BookId, BookStatusChange::ToStatus, BookStatusChange::ChangedAt])
+                    .select_from(
+                        Query::select()
+                            .column(Book::Id)
+                            .column(Book::Status)
+                            .column(Alias::new("created_at"))
+                            .from(Book::Table)
+                            .to_owned(),
+                    )
+                    .map_err(|err| DbErr::Migration(err.to_string()))?
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookStatusChange::Table).to_owned())
+            .await?;
+
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .get_connection()
+                .execute_unprepared(r#"ALTER TABLE "book" ALTER COLUMN "status" TYPE varchar USING "status"::text"#)
+                .await?;
+
+            manager
+                .drop_type(Type::drop().name(BookStatus::Enum).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum BookStatus {
+    #[sea_orm(iden = "book_status")]
+    Enum,
+    Ongoing,
+    Completed,
+    Hiatus,
+    Dropped,
+    Announced,
+}
+
+#[derive(DeriveIden)]
+enum BookStatusChange {
+    Table,
+    Id,
+    BookId,
+    FromStatus,
+    ToStatus,
+    UserId,
+    ChangedAt,
+}
@@ -0,0 +1,171 @@
+use rocket::data::{Limits, ToByteUnit};
+use rocket::figment::Figment;
+use rocket::response::content::RawHtml;
+use rocket::{Build, Rocket};
+
+use crate::routes::{
+    admin_route, author_route, book_route, chapter_route, comment_rate_route, comment_route, event_route,
+    follow_route, genre_route, notification_route, review_route, status_route, user_route
+};
+use crate::{auth, entities, error, images, retention};
+use crate::events::EventHub;
+use crate::logging::{self, traced, RequestTracing};
+use crate::metrics::Metrics;
+use crate::retention::Retention;
+use crate::views::{ViewCounter, ViewFlush};
+
+use rocket_dyn_templates::Template;
+
+use sea_orm::DatabaseConnection;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+struct SessionTokenAddon;
+
+impl Modify for SessionTokenAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "session_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer))
+            );
+            components.add_security_scheme(
+                "admin_session",
+                SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(auth::ADMIN_COOKIE)))
+            );
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "Egline API"),
+    components(
+        schemas(
+            entities::author::Model,
+            entities::author_follow::Model,
+            entities::book::Model,
+            entities::book_author::Model,
+            entities::book_follow::Model,
+            entities::book_genre::Model,
+            entities::book_rate::Model,
+            entities::book_status_change::Model,
+            entities::chapter::Model,
+            entities::comment::Model,
+            entities::comment_rate::Model,
+            entities::genre::Model,
+            entities::notification::Model,
+            entities::reading_progress::Model,
+            entities::review::Model,
+            entities::review_vote::Model,
+            entities::sea_orm_active_enums::BookStatus,
+            entities::user::Model,
+            error::ApiError,
+            images::ImageUpload
+        )
+    ),
+    tags(
+        (name = "user", description = "Accounts, sessions, saved books and reading progress"),
+        (name = "genre", description = "Book genres"),
+        (name = "author", description = "Authors and their statistics"),
+        (name = "book", description = "Books with their genres, authors and rates"),
+        (name = "chapter", description = "Chapters with text and audio"),
+        (name = "comment", description = "Comments on books and chapters"),
+        (name = "comment-rate", description = "Votes on comments"),
+        (name = "review", description = "Book reviews and helpfulness votes"),
+        (name = "follow", description = "Followed books and authors"),
+        (name = "notification", description = "Notification inbox"),
+        (name = "events", description = "Server-Sent Events push stream"),
+        (name = "status", description = "Schema version, health and readiness probes and metrics")
+    ),
+    modifiers(&SessionTokenAddon)
+)]
+struct ApiDoc;
+
+pub fn api_doc() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+
+    for module_doc in [
+        user_route::UserApi::openapi(),
+        genre_route::GenreApi::openapi(),
+        author_route::AuthorApi::openapi(),
+        book_route::BookApi::openapi(),
+        chapter_route::ChapterApi::openapi(),
+        comment_route::CommentApi::openapi(),
+        comment_rate_route::CommentRateApi::openapi(),
+        review_route::ReviewApi::openapi(),
+        follow_route::FollowApi::openapi(),
+        notification_route::NotificationApi::openapi(),
+        event_route::EventApi::openapi(),
+        status_route::StatusApi::openapi()
+    ] {
+        doc.merge(module_doc);
+    }
+
+    doc
+}
+
+#[get("/")]
+fn index() -> RawHtml<&'static str> {
+    RawHtml("Hello!\nI am Egline server...\n<a href='swagger-ui/'>swagger</a>")
+}
+
+/// Rocket configuration with the server's limits on top of Rocket.toml and the ROCKET_ variables.
+pub fn figment() -> Figment {
+    let limits = Limits::default()
+        .limit("file", images::MAX_UPLOAD_MEBIBYTES.mebibytes())
+        .limit("data-form", (images::MAX_UPLOAD_MEBIBYTES + 1).mebibytes());
+
+    // Rocket's own messages go to the JSON log, where terminal colors would only add escape codes
+    rocket::Config::figment()
+        .merge(("limits", limits))
+        .merge(("cli_colors", false))
+}
+
+/// The whole server on a connected database, used by `main` and by the integration tests.
+pub fn build(figment: Figment, mut db: DatabaseConnection) -> Rocket<Build> {
+    let metrics = Metrics::new();
+    let query_metrics = metrics.clone();
+    db.set_metric_callback(move |info| {
+        query_metrics.observe_query(info);
+        logging::log_query(info);
+    });
+
+    let retention = Retention::from_figment(&figment);
+    let views = ViewCounter::from_figment(&figment);
+
+    rocket
+        ::custom(figment)
+        .manage(db)
+        .manage(EventHub::new())
+        .manage(metrics.clone())
+        .manage(retention)
+        .manage(views)
+        .attach(metrics)
+        .attach(RequestTracing)
+        .attach(Template::fairing())
+        .attach(retention::fairing())
+        .attach(ViewFlush)
+        .register("/", catchers![error::default_catcher])
+        .register("/admin", admin_route::get_all_admin_catchers())
+        .mount("/", traced(routes![index]))
+        .mount("/", traced(status_route::get_all_probe_methods()))
+        .mount(
+            "/",
+            SwaggerUi::new("/swagger-ui/<_..>").url("/api-docs/openapi.json", api_doc())
+        )
+        .mount("/api/v1/user", traced(user_route::get_all_methods()))
+        .mount("/api/v1/genre", traced(genre_route::get_all_methods()))
+        .mount("/api/v1/author", traced(author_route::get_all_methods()))
+        .mount("/api/v1/book", traced(book_route::get_all_methods()))
+        .mount("/api/v1/chapter", traced(chapter_route::get_all_chapter_methods()))
+        .mount("/api/v1/comment", traced(comment_route::get_all_comment_methods()))
+        .mount("/api/v1/comment-rate", traced(comment_rate_route::get_all_comment_rate_methods()))
+        .mount("/api/v1/review", traced(review_route::get_all_review_methods()))
+        .mount("/api/v1/follow", traced(follow_route::get_all_follow_methods()))
+        .mount("/api/v1/notification", traced(notification_route::get_all_notification_methods()))
+        .mount("/api/v1/events", traced(event_route::get_all_event_methods()))
+        .mount("/api/v1/status", traced(status_route::get_all_status_methods()))
+        .mount("/admin", traced(admin_route::get_all_admin_methods()))
+}
@@ -0,0 +1,141 @@
+//! Append-only log of content changes and moderation. Every entry names the acting user, the action,
+//! the changed row and the request it was made in, with the row's fields before and after the change.
+
+use std::convert::Infallible;
+
+use chrono::Utc;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::serde_json::{self, Value};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, DbErr};
+use serde::Serialize;
+
+use crate::entities::audit_log;
+use crate::{auth, logging};
+
+pub const CREATE: &str = "create";
+pub const UPDATE: &str = "update";
+pub const DELETE: &str = "delete";
+pub const RESTORE: &str = "restore";
+pub const ACTIONS: [&str; 4] = [CREATE, UPDATE, DELETE, RESTORE];
+
+/// Values of `entity_type`, the tables of the audited rows
+pub const ENTITY_TYPES: [&str; 7] = ["book", "author", "genre", "chapter", "comment", "review", "user"];
+
+// Image bytes and password hashes have no place in the log, and `updated_at` changes with every update
+const OMITTED_FIELDS: [&str; 4] = ["cover", "avatar", "password", "updated_at"];
+
+/// Who makes a change: the user of the request's session token or admin cookie, `None` for
+/// anonymous requests and the `egline-admin` tasks, and the id of the request.
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+    pub user_id: Option<i32>,
+    pub request_id: Option<String>
+}
+
+impl Actor {
+    /// Changes made outside of a request.
+    pub fn system() -> Actor {
+        Actor::default()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Actor {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = request.headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string())
+            .or_else(|| request.cookies().get(auth::ADMIN_COOKIE).map(|cookie| cookie.value().to_string()));
+
+        // Most changing routes are open, an invalid token only leaves the actor unknown
+        let user_id = match token {
+            Some(token) => auth::session_user_id(request, &token).await.ok(),
+            None => None
+        };
+
+        Outcome::Success(Actor {
+            user_id,
+            request_id: Some(logging::request_id(request))
+        })
+    }
+}
+
+/// Fields of a row as they are logged.
+pub fn snapshot<M: Serialize>(model: &M) -> Value {
+    let mut value = serde_json::to_value(model).unwrap_or(Value::Null);
+
+    if let Value::Object(fields) = &mut value {
+        for field in OMITTED_FIELDS {
+            fields.remove(field);
+        }
+    }
+
+    value
+}
+
+/// Appends an entry. When both `before` and `after` are given only the fields that differ are kept,
+/// and an update that changed nothing is not logged.
+pub async fn record(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    action: &str,
+    entity_type: &str,
+    entity_id: i32,
+    before: Option<Value>,
+    after: Option<Value>
+) -> Result<(), DbErr> {
+    let (before, after) = match (before, after) {
+        (Some(Value::Object(mut before)), Some(Value::Object(mut after))) => {
+            let changed = before.keys()
+                .chain(after.keys())
+                .filter(|field| before.get(*field) != after.get(*field))
+                .cloned()
+                .collect::<Vec<String>>();
+
+            if changed.is_empty() {
+                return Ok(());
+            }
+
+            before.retain(|field, _| changed.contains(field));
+            after.retain(|field, _| changed.contains(field));
+
+            (Some(Value::Object(before)), Some(Value::Object(after)))
+        },
+        other => other
+    };
+
+    audit_log::ActiveModel {
+        actor_id: ActiveValue::set(actor.user_id),
+        action: ActiveValue::set(action.to_string()),
+        entity_type: ActiveValue::set(entity_type.to_string()),
+        entity_id: ActiveValue::set(entity_id),
+        before: ActiveValue::set(before),
+        after: ActiveValue::set(after),
+        request_id: ActiveValue::set(actor.request_id.clone()),
+        created_at: ActiveValue::set(Utc::now().naive_utc()),
+        ..Default::default()
+    }.insert(db).await?;
+
+    Ok(())
+}
+
+/// Entry of a row moved to or out of the trash, the row is logged as it was before a delete
+/// and as it is after a restore.
+pub async fn record_trash<M: Serialize>(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    entity_type: &str,
+    entity_id: i32,
+    deleted: bool,
+    row: Option<M>
+) -> Result<(), DbErr> {
+    let row = row.as_ref().map(snapshot);
+
+    match deleted {
+        true => record(db, actor, DELETE, entity_type, entity_id, row, None).await,
+        false => record(db, actor, RESTORE, entity_type, entity_id, None, row).await
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{Duration, Utc};
+use rand::RngCore;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::entities::prelude::{User, UserSession};
+use crate::entities::{user, user_session};
+
+const SESSION_DAYS: i64 = 30;
+
+pub const ROLE_READER: &str = "reader";
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLES: [&str; 2] = [ROLE_READER, ROLE_ADMIN];
+
+/// Cookie holding the session token of the admin panel
+pub const ADMIN_COOKIE: &str = "admin_session";
+
+/// User authenticated by a session token issued at login.
+/// The token is read from the `Authorization: Bearer <token>` header or,
+/// for clients that can not set headers (e.g. `EventSource`), from the `token` query parameter.
+pub struct AuthUser {
+    pub id: i32,
+    pub token: String
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthUser {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match bearer_token(request) {
+            Some(token) => token,
+            None => match request.query_value::<String>("token") {
+                Some(Ok(token)) => token,
+                _ => return Outcome::Error((Status::Unauthorized, "Missing session token".to_string()))
+            }
+        };
+
+        match session_user_id(request, &token).await {
+            Ok(id) => Outcome::Success(AuthUser { id, token }),
+            Err(error) => Outcome::Error(error)
+        }
+    }
+}
+
+/// User with the admin role, authenticated by the session token in the `admin_session` cookie or,
+/// for API clients, in the `Authorization: Bearer <token>` header. The cookie is set by the admin
+/// login form and is `SameSite=Strict`, so the panel's forms can not be submitted from other sites.
+pub struct AdminUser {
+    pub id: i32,
+    pub display_name: String,
+    pub token: String
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match request.cookies().get(ADMIN_COOKIE) {
+            Some(cookie) => cookie.value().to_string(),
+            None => match bearer_token(request) {
+                Some(token) => token,
+                None => return Outcome::Error((Status::Unauthorized, "Missing session cookie or token".to_string()))
+            }
+        };
+
+        let id = match session_user_id(request, &token).await {
+            Ok(id) => id,
+            Err(error) => return Outcome::Error(error)
+        };
+
+        let db = match request.rocket().state::<DatabaseConnection>() {
+            Some(db) => db,
+            None => return Outcome::Error((Status::InternalServerError, "No database connection".to_string()))
+        };
+
+        let user = User::find_by_id(id)
+            .filter(user::Column::DeletedAt.is_null())
+            .one(db)
+            .await;
+
+        match user {
+            Ok(Some(user)) if user.role == ROLE_ADMIN => Outcome::Success(AdminUser {
+                id,
+                display_name: user.display_name,
+                token
+            }),
+            Ok(_) => Outcome::Error((Status::Forbidden, "Admin role required".to_string())),
+            Err(err) => Outcome::Error((Status::InternalServerError, err.to_string()))
+        }
+    }
+}
+
+/// Token of the `Authorization: Bearer <token>` header.
+fn bearer_token(request: &Request<'_>) -> Option<String> {
+    request.headers()
+        .get_one("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+/// Id of the user owning an unexpired session with the token.
+pub(crate) async fn session_user_id(request: &Request<'_>, token: &str) -> Result<i32, (Status, String)> {
+    let db = match request.rocket().state::<DatabaseConnection>() {
+        Some(db) => db,
+        None => return Err((Status::InternalServerError, "No database connection".to_string()))
+    };
+
+    match UserSession::find_by_id(token.to_string()).one(db).await {
+        Ok(Some(session)) if session.expires_at > Utc::now().naive_utc() => Ok(session.user_id),
+        Ok(_) => Err((Status::Unauthorized, "Invalid or expired session token".to_string())),
+        Err(err) => Err((Status::InternalServerError, err.to_string()))
+    }
+}
+
+pub async fn create_session(db: &DatabaseConnection, user_id: i32) -> Result<String, DbErr> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    let now = Utc::now().naive_utc();
+
+    user_session::ActiveModel {
+        token: ActiveValue::set(token.clone()),
+        user_id: ActiveValue::set(user_id),
+        created_at: ActiveValue::set(now),
+        expires_at: ActiveValue::set(now + Duration::days(SESSION_DAYS)),
+    }.insert(db).await?;
+
+    Ok(token)
+}
+
+pub async fn delete_session(db: &DatabaseConnection, token: String) -> Result<u64, DbErr> {
+    let deleted_session = UserSession::delete_by_id(token).exec(db).await?;
+
+    Ok(deleted_session.rows_affected)
+}
+
+pub async fn delete_user_sessions(db: &DatabaseConnection, user_id: i32) -> Result<u64, DbErr> {
+    let deleted_sessions = UserSession::delete_many()
+        .filter(user_session::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    Ok(deleted_sessions.rows_affected)
+}
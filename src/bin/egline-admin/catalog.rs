@@ -0,0 +1,239 @@
+//! Catalog export and import. The file holds genres, authors, books, their links and chapters;
+//! covers, avatars and chapter files are not part of it.
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait,
+    IntoActiveModel, Statement, TransactionTrait
+};
+
+use egline_server::entities::prelude::{Author, Book, BookAuthor, BookGenre, Chapter, Genre};
+use egline_server::entities::sea_orm_active_enums::BookStatus;
+use egline_server::entities::{author, book, book_author, book_genre, chapter, genre};
+
+/// Rows per `INSERT`, keeps the statements well below the bind parameter limit
+const IMPORT_CHUNK: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Catalog {
+    pub genres: Vec<GenreRecord>,
+    pub authors: Vec<AuthorRecord>,
+    pub books: Vec<BookRecord>,
+    pub book_genres: Vec<book_genre::Model>,
+    pub book_authors: Vec<book_author::Model>,
+    pub chapters: Vec<chapter::Model>
+}
+
+/// Timestamps are missing from files exported by older versions, such rows are stamped with the import time
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenreRecord {
+    pub id: i32,
+    pub title: String,
+    #[serde(default)]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub updated_at: Option<NaiveDateTime>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorRecord {
+    pub id: i32,
+    pub first_name: String,
+    pub last_name: String,
+    pub biography: String,
+    pub rating: f32,
+    #[serde(default)]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub updated_at: Option<NaiveDateTime>,
+    /// Set for authors in the trash
+    #[serde(default)]
+    pub deleted_at: Option<NaiveDateTime>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BookRecord {
+    pub id: i32,
+    pub title: String,
+    pub description: String,
+    pub rating: f32,
+    pub year: i32,
+    pub views: i32,
+    pub status: BookStatus,
+    #[serde(default)]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub updated_at: Option<NaiveDateTime>,
+    /// Set for books in the trash
+    #[serde(default)]
+    pub deleted_at: Option<NaiveDateTime>
+}
+
+impl From<genre::Model> for GenreRecord {
+    fn from(genre: genre::Model) -> GenreRecord {
+        GenreRecord {
+            id: genre.id,
+            title: genre.title,
+            created_at: Some(genre.created_at),
+            updated_at: Some(genre.updated_at)
+        }
+    }
+}
+
+impl From<author::Model> for AuthorRecord {
+    fn from(author: author::Model) -> AuthorRecord {
+        AuthorRecord {
+            id: author.id,
+            first_name: author.first_name,
+            last_name: author.last_name,
+            biography: author.biography,
+            rating: author.rating,
+            created_at: Some(author.created_at),
+            updated_at: Some(author.updated_at),
+            deleted_at: author.deleted_at
+        }
+    }
+}
+
+impl From<book::Model> for BookRecord {
+    fn from(book: book::Model) -> BookRecord {
+        BookRecord {
+            id: book.id,
+            title: book.title,
+            description: book.description,
+            rating: book.rating,
+            year: book.year,
+            views: book.views,
+            status: book.status,
+            created_at: Some(book.created_at),
+            updated_at: Some(book.updated_at),
+            deleted_at: book.deleted_at
+        }
+    }
+}
+
+pub async fn export(db: &DatabaseConnection) -> Result<Catalog, DbErr> {
+    Ok(Catalog {
+        genres: Genre::find().all(db).await?.into_iter().map(GenreRecord::from).collect(),
+        authors: Author::find().all(db).await?.into_iter().map(AuthorRecord::from).collect(),
+        books: Book::find().all(db).await?.into_iter().map(BookRecord::from).collect(),
+        book_genres: BookGenre::find().all(db).await?,
+        book_authors: BookAuthor::find().all(db).await?,
+        chapters: Chapter::find().all(db).await?
+    })
+}
+
+/// Inserts the catalog in one transaction, keeping ids. Rows clashing with existing ones are skipped,
+/// so importing the same file twice changes nothing. Returns the number of inserted rows per table.
+pub async fn import(db: &DatabaseConnection, catalog: Catalog) -> Result<Vec<(&'static str, u64)>, DbErr> {
+    let txn = db.begin().await?;
+    // `insert_many` skips `before_save`, so the timestamps are set here
+    let now = Utc::now().naive_utc();
+
+    let genres = catalog.genres.into_iter().map(|genre| genre::ActiveModel {
+        id: ActiveValue::set(genre.id),
+        title: ActiveValue::set(genre.title),
+        created_at: ActiveValue::set(genre.created_at.unwrap_or(now)),
+        updated_at: ActiveValue::set(genre.updated_at.unwrap_or(now))
+    }).collect();
+
+    let authors = catalog.authors.into_iter().map(|author| author::ActiveModel {
+        id: ActiveValue::set(author.id),
+        first_name: ActiveValue::set(author.first_name),
+        last_name: ActiveValue::set(author.last_name),
+        biography: ActiveValue::set(author.biography),
+        rating: ActiveValue::set(author.rating),
+        avatar: ActiveValue::set(Vec::new()),
+        created_at: ActiveValue::set(author.created_at.unwrap_or(now)),
+        updated_at: ActiveValue::set(author.updated_at.unwrap_or(now)),
+        deleted_at: ActiveValue::set(author.deleted_at)
+    }).collect();
+
+    let books = catalog.books.into_iter().map(|book| book::ActiveModel {
+        id: ActiveValue::set(book.id),
+        title: ActiveValue::set(book.title),
+        description: ActiveValue::set(book.description),
+        cover: ActiveValue::set(Vec::new()),
+        rating: ActiveValue::set(book.rating),
+        year: ActiveValue::set(book.year),
+        views: ActiveValue::set(book.views),
+        status: ActiveValue::set(book.status),
+        created_at: ActiveValue::set(book.created_at.unwrap_or(now)),
+        updated_at: ActiveValue::set(book.updated_at.unwrap_or(now)),
+        deleted_at: ActiveValue::set(book.deleted_at)
+    }).collect();
+
+    let book_genres = catalog.book_genres.into_iter().map(|book_genre| book_genre::ActiveModel {
+        book_id: ActiveValue::set(book_genre.book_id),
+        genre_id: ActiveValue::set(book_genre.genre_id)
+    }).collect();
+
+    let book_authors = catalog.book_authors.into_iter().map(|book_author| book_author::ActiveModel {
+        book_id: ActiveValue::set(book_author.book_id),
+        author_id: ActiveValue::set(book_author.author_id)
+    }).collect();
+
+    let chapters = catalog.chapters.into_iter().map(|chapter| chapter::ActiveModel {
+        id: ActiveValue::set(chapter.id),
+        book_id: ActiveValue::set(chapter.book_id),
+        title: ActiveValue::set(chapter.title),
+        filepath: ActiveValue::set(chapter.filepath),
+        number: ActiveValue::set(chapter.number),
+        date: ActiveValue::set(chapter.date)
+    }).collect();
+
+    let inserted = vec![
+        ("genre", insert_rows(&txn, genres).await?),
+        ("author", insert_rows(&txn, authors).await?),
+        ("book", insert_rows(&txn, books).await?),
+        ("book_genre", insert_rows(&txn, book_genres).await?),
+        ("book_author", insert_rows(&txn, book_authors).await?),
+        ("chapter", insert_rows(&txn, chapters).await?)
+    ];
+
+    reset_sequences(&txn, &["genre", "author", "book", "chapter"]).await?;
+
+    txn.commit().await?;
+
+    Ok(inserted)
+}
+
+pub async fn insert_rows<A, C>(db: &C, rows: Vec<A>) -> Result<u64, DbErr>
+where
+    A: ActiveModelTrait + Clone + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    C: ConnectionTrait
+{
+    let mut inserted = 0;
+
+    for chunk in rows.chunks(IMPORT_CHUNK) {
+        inserted += A::Entity::insert_many(chunk.to_vec())
+            .on_conflict(OnConflict::new().do_nothing().to_owned())
+            .exec_without_returning(db)
+            .await?;
+    }
+
+    Ok(inserted)
+}
+
+/// Moves the id sequences past the imported ids, otherwise the next insert through the API would clash.
+pub async fn reset_sequences<C: ConnectionTrait>(db: &C, tables: &[&str]) -> Result<(), DbErr> {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+
+    for table in tables {
+        db.execute(Statement::from_string(
+            DbBackend::Postgres,
+            format!(
+                r#"SELECT setval(pg_get_serial_sequence('"{0}"', 'id'), COALESCE((SELECT MAX(id) FROM "{0}"), 0) + 1, false)"#,
+                table
+            )
+        )).await?;
+    }
+
+    Ok(())
+}
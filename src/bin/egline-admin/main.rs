@@ -0,0 +1,272 @@
+//! `egline-admin`: administrative tasks run against the server's database.
+//! `egline-admin --help` lists the subcommands.
+
+mod catalog;
+mod seed;
+mod storage;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use egline_server::audit::Actor;
+use egline_server::auth;
+use egline_server::entities::prelude::{Author, Book, Comment, Review, User};
+use egline_server::entities::user;
+use egline_server::error::ApiError;
+use egline_server::retention::{self, Retention};
+use egline_server::routes::{author_route, book_route, comment_rate_route, review_route, user_route};
+use egline_server::routes::user_route::{UserData, UserRoleData};
+use egline_server::setup::set_up_db;
+
+#[derive(Parser)]
+#[command(name = "egline-admin", version, about = "Administrative tasks for the Egline server")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Creates a user with the admin role
+    CreateAdmin {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        display_name: String,
+        #[arg(long, env = "EGLINE_PASSWORD", hide_env_values = true)]
+        password: String
+    },
+    /// Gives an existing user a role, admin by default
+    Promote {
+        #[arg(long)]
+        email: String,
+        #[arg(long, default_value = auth::ROLE_ADMIN)]
+        role: String
+    },
+    /// Sets a new password and ends all sessions of the user
+    ResetPassword {
+        #[arg(long)]
+        email: String,
+        #[arg(long, env = "EGLINE_PASSWORD", hide_env_values = true)]
+        password: String
+    },
+    /// Recomputes book and author ratings and comment and review vote counters
+    Recompute,
+    /// Compares chapter directories with the chapter table, exits with 1 on mismatches
+    VerifyStorage {
+        #[arg(long, default_value = "storage")]
+        root: PathBuf
+    },
+    /// Writes genres, authors, books, their links and chapters as JSON. Images and chapter files are not included
+    Export {
+        /// File to write, standard output when omitted
+        #[arg(long)]
+        output: Option<PathBuf>
+    },
+    /// Loads a file written by export, keeping ids and skipping rows that already exist
+    Import {
+        file: PathBuf
+    },
+    /// Applies pending migrations
+    Migrate,
+    /// Removes trashed books, authors, users and comments for good once they are older than the retention
+    /// window, the books with their chapters and chapter files in `storage`
+    Purge {
+        #[arg(long, default_value_t = retention::DEFAULT_RETENTION_DAYS)]
+        retention_days: i64
+    },
+    /// Fills an empty database with fake users, catalog, rates, reviews and comments, and writes chapter files.
+    /// The same seed and sizes always give the same data
+    Seed {
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+        #[arg(long, default_value_t = 100)]
+        users: usize,
+        #[arg(long, default_value_t = 50)]
+        authors: usize,
+        #[arg(long, default_value_t = 200)]
+        books: usize,
+        /// Most chapters per book
+        #[arg(long, default_value_t = 10)]
+        chapters: usize,
+        /// Most rates per book, one in five comes with a review
+        #[arg(long, default_value_t = 20)]
+        rates: usize,
+        #[arg(long, default_value_t = 1000)]
+        comments: usize,
+        #[arg(long, default_value = "storage")]
+        root: PathBuf
+    }
+}
+
+#[rocket::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let db = match set_up_db().await {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("Can not connect to the database: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&db, cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(db: &DatabaseConnection, command: Command) -> Result<(), String> {
+    match command {
+        Command::CreateAdmin { email, display_name, password } => {
+            let user_data = UserData::new(email, display_name, password);
+            let user = user_route::insert_user(db, &user_data, auth::ROLE_ADMIN).await.map_err(describe)?;
+
+            println!("Created admin {} with id {}", user.display_name, user.id);
+        },
+        Command::Promote { email, role } => {
+            let user = find_user(db, &email).await?;
+            let role_data = UserRoleData::new(role);
+            let user = user_route::set_user_role(db, &Actor::system(), user.id, &role_data).await.map_err(describe)?;
+
+            println!("{} is now {}", user.display_name, user.role);
+        },
+        Command::ResetPassword { email, password } => {
+            let user = find_user(db, &email).await?;
+            user_route::reset_password(db, user.id, &password).await.map_err(describe)?;
+
+            println!("Password of {} was reset and their sessions ended", user.display_name);
+        },
+        Command::Recompute => recompute(db).await.map_err(|err| err.to_string())?,
+        Command::VerifyStorage { root } => {
+            let problems = storage::verify(db, &root).await.map_err(|err| err.to_string())?;
+
+            if !problems.is_empty() {
+                for problem in &problems {
+                    println!("{}", problem);
+                }
+
+                return Err(format!("{} problems found in {}", problems.len(), root.display()));
+            }
+
+            println!("Storage in {} matches the chapter table", root.display());
+        },
+        Command::Export { output } => {
+            let catalog = catalog::export(db).await.map_err(|err| err.to_string())?;
+            let json = serde_json::to_string_pretty(&catalog).map_err(|err| err.to_string())?;
+
+            match output {
+                Some(path) => {
+                    fs::write(&path, json).map_err(|err| format!("Can not write {}: {}", path.display(), err))?;
+                    eprintln!(
+                        "Exported {} genres, {} authors, {} books and {} chapters to {}",
+                        catalog.genres.len(), catalog.authors.len(), catalog.books.len(), catalog.chapters.len(),
+                        path.display()
+                    );
+                },
+                None => println!("{}", json)
+            }
+        },
+        Command::Import { file } => {
+            let json = fs::read_to_string(&file).map_err(|err| format!("Can not read {}: {}", file.display(), err))?;
+            let catalog = serde_json::from_str(&json).map_err(|err| format!("{} is not a catalog: {}", file.display(), err))?;
+
+            for (table, inserted) in catalog::import(db, catalog).await.map_err(|err| err.to_string())? {
+                println!("{}: {} rows inserted", table, inserted);
+            }
+        },
+        Command::Migrate => {
+            let pending = Migrator::get_pending_migrations(db).await.map_err(|err| err.to_string())?;
+
+            if pending.is_empty() {
+                println!("The schema is up to date");
+                return Ok(());
+            }
+
+            for migration in &pending {
+                println!("Applying {}", migration.name());
+            }
+
+            Migrator::up(db, None).await.map_err(|err| err.to_string())?;
+            println!("Applied {} migrations", pending.len());
+        },
+        Command::Purge { retention_days } => {
+            let retention = Retention { days: retention_days };
+            let purged = retention::purge(db, retention.cutoff()).await.map_err(|err| err.to_string())?;
+
+            println!(
+                "Purged {} books, {} authors, {} users and {} comments trashed more than {} days ago",
+                purged.books, purged.authors, purged.users, purged.comments, retention_days
+            );
+        },
+        Command::Seed { seed, users, authors, books, chapters, rates, comments, root } => {
+            let options = seed::SeedOptions { seed, users, authors, books, chapters, rates, comments, root };
+
+            for (table, inserted) in seed::seed(db, &options).await.map_err(|err| err.to_string())? {
+                println!("{}: {} rows inserted", table, inserted);
+            }
+            println!("Every seeded user signs in with the password \"{}\"", seed::PASSWORD);
+        }
+    }
+
+    Ok(())
+}
+
+async fn find_user(db: &DatabaseConnection, email: &str) -> Result<user::Model, String> {
+    let user = User::find()
+        .filter(user::Column::Email.eq(email.to_lowercase()))
+        .one(db)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    user.ok_or(format!("No user with email {}", email))
+}
+
+/// Recounts everything derived from votes and rates. Authors are recomputed on their own too,
+/// as authors without books are not reached through their books.
+async fn recompute(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+    let comments = Comment::find().all(db).await?;
+    for comment in &comments {
+        comment_rate_route::update_comment_votes(db, comment.id).await?;
+    }
+    println!("Recounted votes of {} comments", comments.len());
+
+    let reviews = Review::find().all(db).await?;
+    for review in &reviews {
+        review_route::update_review_votes(db, review.id).await?;
+    }
+    println!("Recounted votes of {} reviews", reviews.len());
+
+    let books = Book::find().all(db).await?;
+    for book in &books {
+        book_route::update_book_rating(db, book.id).await?;
+    }
+    println!("Recomputed ratings of {} books", books.len());
+
+    let authors = Author::find().all(db).await?;
+    for author in &authors {
+        author_route::update_author_rating(db, author.id).await?;
+    }
+    println!("Recomputed ratings of {} authors", authors.len());
+
+    Ok(())
+}
+
+fn describe(err: ApiError) -> String {
+    let mut message = err.message;
+
+    for (field, messages) in err.errors {
+        message.push_str(&format!("\n  {}: {}", field, messages.join(", ")));
+    }
+
+    message
+}
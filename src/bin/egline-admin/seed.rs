@@ -0,0 +1,383 @@
+//! Fake data for demos and load tests. Everything is drawn from one random generator seeded
+//! by `--seed`, so the same seed and sizes always give the same rows and chapter files.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, EntityTrait, Iterable, PaginatorTrait, TransactionTrait};
+use serde_json::json;
+use sha256::digest;
+
+use egline_server::auth;
+use egline_server::entities::prelude::{Author, Book, Genre, User};
+use egline_server::entities::sea_orm_active_enums::BookStatus;
+use egline_server::entities::{
+    author, book, book_author, book_genre, book_rate, book_status_change, chapter, comment, genre, review, user
+};
+
+use crate::catalog::{insert_rows, reset_sequences};
+
+/// Password of every seeded user
+pub const PASSWORD: &str = "password";
+
+/// Comments are generated and inserted this many at a time, so large runs do not hold them all in memory
+const COMMENT_BATCH: usize = 10_000;
+/// One rate in this many comes with a review
+const REVIEW_EVERY: usize = 5;
+/// Creation time of the genres, every other row is created within two years after it
+const START: NaiveDateTime = match NaiveDate::from_ymd_opt(2023, 1, 1) {
+    Some(date) => date.and_time(NaiveTime::MIN),
+    None => panic!("2023-01-01 is a valid date")
+};
+
+const GENRES: [&str; 12] = [
+    "Fantasy", "Science fiction", "Mystery", "Thriller", "Romance", "Horror",
+    "Historical", "Adventure", "Drama", "Comedy", "Poetry", "Biography"
+];
+
+const FIRST_NAMES: [&str; 24] = [
+    "Ada", "Boris", "Clara", "Dmitri", "Elena", "Felix", "Greta", "Hugo", "Irina", "Jonas", "Katya", "Leon",
+    "Mira", "Nikolai", "Olga", "Pavel", "Quinn", "Rosa", "Stefan", "Tanya", "Ulrich", "Vera", "Wanda", "Yuri"
+];
+
+const LAST_NAMES: [&str; 24] = [
+    "Abbott", "Belova", "Carver", "Dolgov", "Ellison", "Fedorova", "Grant", "Hale", "Ivanova", "Jensen",
+    "Kowalski", "Lindqvist", "Morozov", "Novak", "Orlova", "Petrov", "Quill", "Reyes", "Sokolova", "Thorne",
+    "Usova", "Volkov", "Whitlock", "Zaitseva"
+];
+
+const ADJECTIVES: [&str; 24] = [
+    "Silent", "Crimson", "Forgotten", "Last", "Hollow", "Burning", "Frozen", "Hidden", "Broken", "Golden",
+    "Wandering", "Distant", "Secret", "Endless", "Pale", "Restless", "Shattered", "Quiet", "Wild", "Ancient",
+    "Lonely", "Bright", "Fallen", "Sleeping"
+];
+
+const NOUNS: [&str; 24] = [
+    "Kingdom", "River", "Tower", "Garden", "Storm", "Mirror", "Harbor", "Forest", "Crown", "Letter",
+    "Winter", "Bridge", "Shadow", "Island", "Voyage", "Promise", "Station", "Lantern", "Orchard", "Empire",
+    "Compass", "Archive", "Signal", "Valley"
+];
+
+const WORDS: [&str; 48] = [
+    "the", "a", "of", "and", "in", "to", "with", "under", "before", "after", "night", "road", "city", "light",
+    "door", "voice", "sea", "stone", "fire", "rain", "window", "letter", "ship", "mountain", "she", "he",
+    "they", "waited", "remembered", "whispered", "ran", "fell", "found", "lost", "opened", "watched",
+    "slowly", "again", "never", "always", "old", "cold", "dark", "strange", "small", "long", "last", "quiet"
+];
+
+/// Sizes of the generated data. Books get 1 to `chapters` chapters and 0 to `rates` rates.
+pub struct SeedOptions {
+    pub seed: u64,
+    pub users: usize,
+    pub authors: usize,
+    pub books: usize,
+    pub chapters: usize,
+    pub rates: usize,
+    pub comments: usize,
+    pub root: PathBuf
+}
+
+/// Inserts the fake data in one transaction and writes the chapter text files under `root`.
+/// Refuses to run on a database that already has users or a catalog, as the generated ids start at 1.
+/// Returns the number of inserted rows per table.
+pub async fn seed(db: &DatabaseConnection, options: &SeedOptions) -> Result<Vec<(&'static str, u64)>, DbErr> {
+    if User::find().count(db).await? > 0 || Genre::find().count(db).await? > 0
+        || Author::find().count(db).await? > 0 || Book::find().count(db).await? > 0 {
+        return Err(DbErr::Custom(
+            "The database already has data, seed a freshly migrated one".to_string()
+        ));
+    }
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let txn = db.begin().await?;
+    let mut inserted = Vec::new();
+
+    let hashed_password = digest(PASSWORD.to_string());
+    let users = (1..=options.users as i32).map(|id| {
+        let (first_name, last_name) = (FIRST_NAMES.choose(&mut rng).unwrap(), LAST_NAMES.choose(&mut rng).unwrap());
+        let created_at = timestamp(&mut rng);
+
+        user::ActiveModel {
+            id: ActiveValue::set(id),
+            display_name: ActiveValue::set(format!("{}{}{}", first_name, last_name, id)),
+            email: ActiveValue::set(format!("user{}@egline.test", id)),
+            password: ActiveValue::set(hashed_password.clone()),
+            avatar: ActiveValue::set(Vec::new()),
+            saved_books: ActiveValue::set(json!({})),
+            role: ActiveValue::set(auth::ROLE_READER.to_string()),
+            created_at: ActiveValue::set(created_at),
+            updated_at: ActiveValue::set(created_at),
+            deleted_at: ActiveValue::set(None)
+        }
+    }).collect();
+    inserted.push(("user", insert_rows(&txn, users).await?));
+
+    let genres = GENRES.iter().zip(1..).map(|(title, id)| genre::ActiveModel {
+        id: ActiveValue::set(id),
+        title: ActiveValue::set(title.to_string()),
+        created_at: ActiveValue::set(START),
+        updated_at: ActiveValue::set(START)
+    }).collect();
+    inserted.push(("genre", insert_rows(&txn, genres).await?));
+
+    // Ratings are derived from the rates, so the rates are drawn before the books and authors are built
+    let book_ids = 1..=options.books as i32;
+    let book_authors = book_ids.clone()
+        .flat_map(|book_id| {
+            let count = if options.authors > 1 && rng.gen_ratio(1, 10) { 2 } else { 1 };
+
+            (1..=options.authors as i32)
+                .choose_multiple(&mut rng, count)
+                .into_iter()
+                .map(move |author_id| (book_id, author_id))
+        })
+        .collect::<Vec<(i32, i32)>>();
+
+    let mut rates = Vec::new();
+    for book_id in book_ids.clone() {
+        let count = rng.gen_range(0..=options.rates.min(options.users));
+
+        for user_id in (1..=options.users as i32).choose_multiple(&mut rng, count) {
+            // Skewed towards good rates, like real catalogs
+            let rate = *[1, 2, 3, 3, 4, 4, 4, 5, 5, 5].choose(&mut rng).unwrap();
+            rates.push((book_id, user_id, rate));
+        }
+    }
+
+    let mut book_rates: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+    for (book_id, _, rate) in &rates {
+        book_rates.entry(*book_id).or_default().push(*rate);
+    }
+
+    let mut author_rates: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+    for (book_id, author_id) in &book_authors {
+        author_rates.entry(*author_id).or_default().extend(book_rates.get(book_id).into_iter().flatten());
+    }
+
+    let authors = (1..=options.authors as i32).map(|id| {
+        let created_at = timestamp(&mut rng);
+
+        author::ActiveModel {
+            id: ActiveValue::set(id),
+            first_name: ActiveValue::set(FIRST_NAMES.choose(&mut rng).unwrap().to_string()),
+            last_name: ActiveValue::set(LAST_NAMES.choose(&mut rng).unwrap().to_string()),
+            biography: ActiveValue::set(paragraph(&mut rng)),
+            rating: ActiveValue::set(average(author_rates.get(&id))),
+            avatar: ActiveValue::set(Vec::new()),
+            created_at: ActiveValue::set(created_at),
+            updated_at: ActiveValue::set(created_at),
+            deleted_at: ActiveValue::set(None)
+        }
+    }).collect();
+    inserted.push(("author", insert_rows(&txn, authors).await?));
+
+    let statuses = BookStatus::iter().collect::<Vec<BookStatus>>();
+    let mut status_changes = Vec::new();
+    let books = book_ids.clone().map(|id| {
+        let created_at = timestamp(&mut rng);
+        let status = *statuses.choose(&mut rng).unwrap();
+
+        // Histories start with the status the book was created with
+        status_changes.push(book_status_change::ActiveModel {
+            book_id: ActiveValue::set(id),
+            from_status: ActiveValue::set(None),
+            to_status: ActiveValue::set(status),
+            user_id: ActiveValue::set(None),
+            changed_at: ActiveValue::set(created_at),
+            ..Default::default()
+        });
+
+        book::ActiveModel {
+            id: ActiveValue::set(id),
+            title: ActiveValue::set(title(&mut rng)),
+            description: ActiveValue::set(paragraph(&mut rng)),
+            cover: ActiveValue::set(Vec::new()),
+            rating: ActiveValue::set(average(book_rates.get(&id))),
+            year: ActiveValue::set(rng.gen_range(1900..=2024)),
+            views: ActiveValue::set(rng.gen_range(0..100_000)),
+            status: ActiveValue::set(status),
+            created_at: ActiveValue::set(created_at),
+            updated_at: ActiveValue::set(created_at),
+            deleted_at: ActiveValue::set(None)
+        }
+    }).collect();
+    inserted.push(("book", insert_rows(&txn, books).await?));
+    inserted.push(("book_status_change", insert_rows(&txn, status_changes).await?));
+
+    let book_genres = book_ids.clone()
+        .flat_map(|book_id| {
+            let count = rng.gen_range(1..=3);
+
+            (1..=GENRES.len() as i32)
+                .choose_multiple(&mut rng, count)
+                .into_iter()
+                .map(move |genre_id| book_genre::ActiveModel {
+                    book_id: ActiveValue::set(book_id),
+                    genre_id: ActiveValue::set(genre_id)
+                })
+        })
+        .collect();
+    inserted.push(("book_genre", insert_rows(&txn, book_genres).await?));
+
+    let book_authors = book_authors.into_iter().map(|(book_id, author_id)| book_author::ActiveModel {
+        book_id: ActiveValue::set(book_id),
+        author_id: ActiveValue::set(author_id)
+    }).collect();
+    inserted.push(("book_author", insert_rows(&txn, book_authors).await?));
+
+    // (id, book_id) of every chapter, comments pick from them
+    let mut chapter_ids = Vec::new();
+    let mut chapters = Vec::new();
+    for book_id in book_ids.clone() {
+        let mut date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + Duration::days(rng.gen_range(0..1500));
+
+        for number in 1..=rng.gen_range(1..=options.chapters.max(1) as i32) {
+            let id = chapter_ids.len() as i32 + 1;
+            let filepath = format!("/{}/{}/", book_id, number);
+
+            write_chapter_text(&options.root, &filepath, &mut rng)
+                .map_err(|err| DbErr::Custom(format!("Can not write chapter {}: {}", filepath, err)))?;
+
+            chapters.push(chapter::ActiveModel {
+                id: ActiveValue::set(id),
+                book_id: ActiveValue::set(book_id),
+                title: ActiveValue::set(title(&mut rng)),
+                filepath: ActiveValue::set(filepath),
+                number: ActiveValue::set(number),
+                date: ActiveValue::set(date)
+            });
+            chapter_ids.push((id, book_id));
+            date += Duration::days(rng.gen_range(1..30));
+        }
+    }
+    inserted.push(("chapter", insert_rows(&txn, chapters).await?));
+
+    let mut reviews = Vec::new();
+    let book_rates = rates.iter().enumerate().map(|(index, (book_id, user_id, rate))| {
+        let created_at = timestamp(&mut rng);
+
+        if index % REVIEW_EVERY == 0 {
+            reviews.push(review::ActiveModel {
+                id: ActiveValue::set(reviews.len() as i32 + 1),
+                user_id: ActiveValue::set(*user_id),
+                book_id: ActiveValue::set(*book_id),
+                rate: ActiveValue::set(*rate),
+                title: ActiveValue::set(title(&mut rng)),
+                body: ActiveValue::set(paragraph(&mut rng)),
+                spoiler: ActiveValue::set(rng.gen_ratio(1, 10)),
+                helpful: ActiveValue::set(0),
+                unhelpful: ActiveValue::set(0),
+                created_at: ActiveValue::set(created_at),
+                edited_at: ActiveValue::set(None)
+            });
+        }
+
+        book_rate::ActiveModel {
+            book_id: ActiveValue::set(*book_id),
+            user_id: ActiveValue::set(*user_id),
+            rate: ActiveValue::set(*rate),
+            created_at: ActiveValue::set(created_at),
+            updated_at: ActiveValue::set(created_at)
+        }
+    }).collect();
+    inserted.push(("book_rate", insert_rows(&txn, book_rates).await?));
+    inserted.push(("review", insert_rows(&txn, reviews).await?));
+
+    let mut comments_inserted = 0;
+    if options.users > 0 && !chapter_ids.is_empty() {
+        for batch_start in (0..options.comments).step_by(COMMENT_BATCH) {
+            let batch_end = (batch_start + COMMENT_BATCH).min(options.comments);
+
+            let comments = (batch_start..batch_end).map(|index| {
+                let (chapter_id, book_id) = *chapter_ids.choose(&mut rng).unwrap();
+                let created_at = timestamp(&mut rng);
+
+                comment::ActiveModel {
+                    id: ActiveValue::set(index as i32 + 1),
+                    book_id: ActiveValue::set(book_id),
+                    user_id: ActiveValue::set(rng.gen_range(1..=options.users as i32)),
+                    chapter_id: ActiveValue::set(chapter_id),
+                    text: ActiveValue::set(comment_text(&mut rng)),
+                    upvotes: ActiveValue::set(0),
+                    downvotes: ActiveValue::set(0),
+                    created_at: ActiveValue::set(created_at),
+                    updated_at: ActiveValue::set(created_at),
+                    deleted_at: ActiveValue::set(None)
+                }
+            }).collect();
+
+            comments_inserted += insert_rows(&txn, comments).await?;
+        }
+    }
+    inserted.push(("comment", comments_inserted));
+
+    reset_sequences(&txn, &["user", "genre", "author", "book", "chapter", "review", "comment"]).await?;
+
+    txn.commit().await?;
+
+    Ok(inserted)
+}
+
+fn average(rates: Option<&Vec<i32>>) -> f32 {
+    match rates {
+        Some(rates) if !rates.is_empty() => rates.iter().sum::<i32>() as f32 / rates.len() as f32,
+        _ => 0.0
+    }
+}
+
+fn title(rng: &mut StdRng) -> String {
+    let adjective = ADJECTIVES.choose(rng).unwrap();
+    let noun = NOUNS.choose(rng).unwrap();
+
+    match rng.gen_range(0..3) {
+        0 => format!("The {} {}", adjective, noun),
+        1 => format!("{} of the {} {}", noun, adjective, NOUNS.choose(rng).unwrap()),
+        _ => format!("{} {}", adjective, noun)
+    }
+}
+
+fn sentence(rng: &mut StdRng) -> String {
+    let words = (0..rng.gen_range(6..15))
+        .map(|_| *WORDS.choose(rng).unwrap())
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}.", first.to_uppercase(), chars.as_str()),
+        None => String::new()
+    }
+}
+
+fn paragraph(rng: &mut StdRng) -> String {
+    (0..rng.gen_range(3..7)).map(|_| sentence(rng)).collect::<Vec<String>>().join(" ")
+}
+
+/// One or two sentences, sometimes with a `||spoiler||`.
+fn comment_text(rng: &mut StdRng) -> String {
+    let text = sentence(rng);
+
+    match rng.gen_range(0..10) {
+        0 => format!("{} ||{}||", text, sentence(rng)),
+        1..=3 => format!("{} {}", text, sentence(rng)),
+        _ => text
+    }
+}
+
+/// A moment in the two years after `START`
+fn timestamp(rng: &mut StdRng) -> NaiveDateTime {
+    START + Duration::seconds(rng.gen_range(0..2 * 365 * 24 * 60 * 60))
+}
+
+fn write_chapter_text(root: &Path, filepath: &str, rng: &mut StdRng) -> std::io::Result<()> {
+    let dir = root.join(filepath.trim_matches('/'));
+    let text = (0..rng.gen_range(5..20)).map(|_| paragraph(rng)).collect::<Vec<String>>().join("\n\n");
+
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("text.txt"), text)
+}
@@ -0,0 +1,81 @@
+//! Checks the chapter directories under `storage` against the `chapter` table.
+//! Chapters live in `storage/<book_id>/<number>/` with a `text.txt` and an optional `audio.txt`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+
+use egline_server::entities::prelude::Chapter;
+
+/// Problems found, empty when storage and table agree.
+pub async fn verify(db: &DatabaseConnection, root: &Path) -> Result<Vec<String>, DbErr> {
+    let chapters = Chapter::find().all(db).await?;
+    let mut problems = Vec::new();
+
+    for chapter in &chapters {
+        let expected = format!("/{}/{}/", chapter.book_id, chapter.number);
+
+        if chapter.filepath != expected {
+            problems.push(format!(
+                "chapter {}: filepath is {} instead of {}",
+                chapter.id, chapter.filepath, expected
+            ));
+        }
+
+        let dir = root.join(chapter.book_id.to_string()).join(chapter.number.to_string());
+
+        if !dir.is_dir() {
+            problems.push(format!("chapter {}: directory {} is missing", chapter.id, dir.display()));
+        } else if !dir.join("text.txt").is_file() {
+            problems.push(format!("chapter {}: {} is missing", chapter.id, dir.join("text.txt").display()));
+        }
+    }
+
+    let known = chapters.iter()
+        .map(|chapter| (chapter.book_id, chapter.number))
+        .collect::<BTreeSet<(i32, i32)>>();
+
+    for (book_id, number) in chapter_dirs(root) {
+        if !known.contains(&(book_id, number)) {
+            problems.push(format!(
+                "{}: no chapter {} of book {} in the database",
+                root.join(book_id.to_string()).join(number.to_string()).display(), number, book_id
+            ));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// `(book_id, number)` of every `storage/<book_id>/<number>/` directory. Other entries,
+/// like covers and the thumbnail cache, are skipped.
+fn chapter_dirs(root: &Path) -> Vec<(i32, i32)> {
+    let mut dirs = Vec::new();
+
+    for (book_id, book_dir) in numbered_dirs(root) {
+        for (number, _) in numbered_dirs(&book_dir) {
+            dirs.push((book_id, number));
+        }
+    }
+
+    dirs.sort();
+    dirs
+}
+
+fn numbered_dirs(dir: &Path) -> Vec<(i32, std::path::PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new()
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let number = entry.file_name().to_str()?.parse::<i32>().ok()?;
+            Some((number, entry.path()))
+        })
+        .collect()
+}
@@ -2,8 +2,10 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = Author)]
 #[sea_orm(table_name = "author")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -15,14 +17,26 @@ pub struct Model {
     pub rating: f32,
     #[sea_orm(column_type = "Binary(BlobSize::Blob(None))")]
     pub avatar: Vec<u8>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::author_follow::Entity")]
+    AuthorFollow,
     #[sea_orm(has_many = "super::book_author::Entity")]
     BookAuthor,
 }
 
+impl Related<super::author_follow::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuthorFollow.def()
+    }
+}
+
 impl Related<super::book_author::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::BookAuthor.def()
@@ -38,4 +52,19 @@ impl Related<super::book::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now().naive_utc();
+
+        if insert && self.created_at.is_not_set() {
+            self.created_at = sea_orm::ActiveValue::Set(now);
+        }
+        self.updated_at = sea_orm::ActiveValue::Set(now);
+
+        Ok(self)
+    }
+}
@@ -1,10 +1,12 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+use super::sea_orm_active_enums::BookStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = Book)]
 #[sea_orm(table_name = "book")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -17,21 +19,35 @@ pub struct Model {
     pub rating: f32,
     pub year: i32,
     pub views: i32,
-    pub status: String,
+    pub status: BookStatus,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::book_author::Entity")]
     BookAuthor,
+    #[sea_orm(has_many = "super::book_follow::Entity")]
+    BookFollow,
     #[sea_orm(has_many = "super::book_genre::Entity")]
     BookGenre,
     #[sea_orm(has_many = "super::book_rate::Entity")]
     BookRate,
+    #[sea_orm(has_many = "super::book_status_change::Entity")]
+    BookStatusChange,
     #[sea_orm(has_many = "super::chapter::Entity")]
     Chapter,
     #[sea_orm(has_many = "super::comment::Entity")]
     Comment,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
+    #[sea_orm(has_many = "super::reading_progress::Entity")]
+    ReadingProgress,
+    #[sea_orm(has_many = "super::review::Entity")]
+    Review,
 }
 
 impl Related<super::book_author::Entity> for Entity {
@@ -40,6 +56,12 @@ impl Related<super::book_author::Entity> for Entity {
     }
 }
 
+impl Related<super::book_follow::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BookFollow.def()
+    }
+}
+
 impl Related<super::book_genre::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::BookGenre.def()
@@ -52,6 +74,12 @@ impl Related<super::book_rate::Entity> for Entity {
     }
 }
 
+impl Related<super::book_status_change::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BookStatusChange.def()
+    }
+}
+
 impl Related<super::chapter::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Chapter.def()
@@ -64,6 +92,24 @@ impl Related<super::comment::Entity> for Entity {
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
+impl Related<super::reading_progress::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReadingProgress.def()
+    }
+}
+
+impl Related<super::review::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Review.def()
+    }
+}
+
 impl Related<super::author::Entity> for Entity {
     fn to() -> RelationDef {
         super::book_author::Relation::Author.def()
@@ -91,4 +137,19 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now().naive_utc();
+
+        if insert && self.created_at.is_not_set() {
+            self.created_at = sea_orm::ActiveValue::Set(now);
+        }
+        self.updated_at = sea_orm::ActiveValue::Set(now);
+
+        Ok(self)
+    }
+}
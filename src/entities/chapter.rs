@@ -2,8 +2,10 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = Chapter)]
 #[sea_orm(table_name = "chapter")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -27,6 +29,8 @@ pub enum Relation {
     Book,
     #[sea_orm(has_many = "super::comment::Entity")]
     Comment,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
 }
 
 impl Related<super::book::Entity> for Entity {
@@ -41,4 +45,10 @@ impl Related<super::comment::Entity> for Entity {
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
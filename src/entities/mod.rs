@@ -2,13 +2,23 @@
 
 pub mod prelude;
 
+pub mod audit_log;
 pub mod author;
+pub mod author_follow;
 pub mod book;
 pub mod book_author;
+pub mod book_follow;
 pub mod book_genre;
 pub mod book_rate;
+pub mod book_status_change;
 pub mod chapter;
 pub mod comment;
 pub mod comment_rate;
 pub mod genre;
+pub mod notification;
+pub mod reading_progress;
+pub mod review;
+pub mod review_vote;
+pub mod sea_orm_active_enums;
 pub mod user;
+pub mod user_session;
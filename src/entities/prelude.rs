@@ -1,12 +1,21 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::audit_log::Entity as AuditLog;
 pub use super::author::Entity as Author;
+pub use super::author_follow::Entity as AuthorFollow;
 pub use super::book::Entity as Book;
 pub use super::book_author::Entity as BookAuthor;
+pub use super::book_follow::Entity as BookFollow;
 pub use super::book_genre::Entity as BookGenre;
 pub use super::book_rate::Entity as BookRate;
+pub use super::book_status_change::Entity as BookStatusChange;
 pub use super::chapter::Entity as Chapter;
 pub use super::comment::Entity as Comment;
 pub use super::comment_rate::Entity as CommentRate;
 pub use super::genre::Entity as Genre;
+pub use super::notification::Entity as Notification;
+pub use super::reading_progress::Entity as ReadingProgress;
+pub use super::review::Entity as Review;
+pub use super::review_vote::Entity as ReviewVote;
 pub use super::user::Entity as User;
+pub use super::user_session::Entity as UserSession;
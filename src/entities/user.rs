@@ -14,20 +14,55 @@ pub struct Model {
     pub display_name: String,
     #[sea_orm(unique)]
     pub email: String,
+    #[serde(skip_serializing)]
     pub password: String,
     #[sea_orm(column_type = "Binary(BlobSize::Blob(None))")]
     pub avatar: Vec<u8>,
+    #[schema(value_type = Object)]
     pub saved_books: Json,
+    pub role: String,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::author_follow::Entity")]
+    AuthorFollow,
+    #[sea_orm(has_many = "super::book_follow::Entity")]
+    BookFollow,
     #[sea_orm(has_many = "super::book_rate::Entity")]
     BookRate,
+    #[sea_orm(has_many = "super::book_status_change::Entity")]
+    BookStatusChange,
     #[sea_orm(has_many = "super::comment::Entity")]
     Comment,
     #[sea_orm(has_many = "super::comment_rate::Entity")]
     CommentRate,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
+    #[sea_orm(has_many = "super::reading_progress::Entity")]
+    ReadingProgress,
+    #[sea_orm(has_many = "super::review::Entity")]
+    Review,
+    #[sea_orm(has_many = "super::review_vote::Entity")]
+    ReviewVote,
+    #[sea_orm(has_many = "super::user_session::Entity")]
+    UserSession,
+}
+
+impl Related<super::author_follow::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuthorFollow.def()
+    }
+}
+
+impl Related<super::book_follow::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BookFollow.def()
+    }
 }
 
 impl Related<super::book_rate::Entity> for Entity {
@@ -36,12 +71,48 @@ impl Related<super::book_rate::Entity> for Entity {
     }
 }
 
+impl Related<super::book_status_change::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BookStatusChange.def()
+    }
+}
+
 impl Related<super::comment_rate::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::CommentRate.def()
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
+impl Related<super::reading_progress::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReadingProgress.def()
+    }
+}
+
+impl Related<super::review::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Review.def()
+    }
+}
+
+impl Related<super::review_vote::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReviewVote.def()
+    }
+}
+
+impl Related<super::user_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserSession.def()
+    }
+}
+
 impl Related<super::book::Entity> for Entity {
     fn to() -> RelationDef {
         super::book_rate::Relation::Book.def()
@@ -60,4 +131,19 @@ impl Related<super::comment::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now().naive_utc();
+
+        if insert && self.created_at.is_not_set() {
+            self.created_at = sea_orm::ActiveValue::Set(now);
+        }
+        self.updated_at = sea_orm::ActiveValue::Set(now);
+
+        Ok(self)
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, status, Responder};
+use rocket::serde::json::Json;
+use sea_orm::DbErr;
+use serde::Serialize;
+use utoipa::ToSchema;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::logging;
+
+/// JSON body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiError {
+    #[schema(example = 422)]
+    pub code: u16,
+    #[schema(example = "Validation failed")]
+    pub message: String,
+    /// Messages per invalid field, only present on 422 responses
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(example = json!({"email": ["must be a valid email"]}))]
+    pub errors: BTreeMap<String, Vec<String>>,
+    /// Id of the failed request, also sent in the `X-Request-Id` header and written to the server log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "3f2a9c0d4e5b6a7f8091a2b3c4d5e6f7")]
+    pub request_id: Option<String>
+}
+
+impl ApiError {
+    pub fn new(status: Status, message: String) -> ApiError {
+        ApiError {
+            code: status.code,
+            message,
+            errors: BTreeMap::new(),
+            request_id: None
+        }
+    }
+
+    /// Empty 422 to be filled with `add_field`.
+    pub fn validation_failed() -> ApiError {
+        ApiError::new(Status::UnprocessableEntity, "Validation failed".to_string())
+    }
+
+    /// 422 for a single invalid field, for checks done in handlers.
+    pub fn field(field: &str, message: &str) -> ApiError {
+        let mut error = ApiError::validation_failed();
+        error.add_field(field, message);
+
+        error
+    }
+
+    pub fn add_field(&mut self, field: &str, message: &str) {
+        self.errors.entry(field.to_string()).or_default().push(message.to_string());
+    }
+
+    pub fn has_field_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Sets the request id and logs the error, server errors at the error level.
+    pub fn trace(&mut self, request: &Request<'_>) {
+        let request_id = logging::request_id(request);
+
+        if self.code >= 500 {
+            tracing::error!(request_id = %request_id, code = self.code, error = %self.message, "request failed");
+        } else {
+            tracing::info!(request_id = %request_id, code = self.code, error = %self.message, errors = ?self.errors, "request rejected");
+        }
+
+        self.request_id = Some(request_id);
+    }
+}
+
+impl From<DbErr> for ApiError {
+    fn from(err: DbErr) -> ApiError {
+        ApiError::new(Status::InternalServerError, err.to_string())
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(validation_errors: ValidationErrors) -> ApiError {
+        let mut error = ApiError::validation_failed();
+        collect_field_errors(&validation_errors, "", &mut error.errors);
+
+        error
+    }
+}
+
+fn collect_field_errors(validation_errors: &ValidationErrors, prefix: &str, errors: &mut BTreeMap<String, Vec<String>>) {
+    for (field, kind) in validation_errors.errors() {
+        let path = if prefix.is_empty() { field.to_string() } else { format!("{}.{}", prefix, field) };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                let messages = field_errors.iter().map(|field_error| match &field_error.message {
+                    Some(message) => message.to_string(),
+                    None => field_error.code.to_string()
+                });
+
+                errors.entry(path).or_default().extend(messages);
+            },
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, &path, errors),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(mut self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = Status::from_code(self.code).unwrap_or(Status::InternalServerError);
+        self.trace(request);
+
+        status::Custom(status, Json(self)).respond_to(request)
+    }
+}
+
+/// Turns errors raised outside handlers (unparsable JSON, failed guards, unknown routes) into `ApiError` bodies.
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> ApiError {
+    ApiError::new(status, status.reason_lossy().to_string())
+}
@@ -0,0 +1,129 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::Utc;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::serde_json;
+use rocket::tokio::sync::broadcast;
+use serde::Serialize;
+
+pub const NOTIFICATION: &str = "notification";
+pub const COMMENT_VOTE: &str = "comment_vote";
+
+const CHANNEL_CAPACITY: usize = 64;
+// Events kept per user so reconnecting clients can resume with `Last-Event-ID`
+const RECENT_EVENTS: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PushEvent {
+    pub id: u64,
+    pub kind: &'static str,
+    pub data: serde_json::Value
+}
+
+struct UserChannel {
+    // Only kept while the user has a stream open
+    sender: Option<broadcast::Sender<PushEvent>>,
+    recent: VecDeque<PushEvent>
+}
+
+/// Per-user in-process broadcast of push events.
+pub struct EventHub {
+    next_id: AtomicU64,
+    channels: Mutex<HashMap<i32, UserChannel>>
+}
+
+impl EventHub {
+    pub fn new() -> EventHub {
+        // Start from the current time so ids keep growing across restarts
+        let first_id = Utc::now().timestamp_millis() as u64 * 1000;
+
+        EventHub {
+            next_id: AtomicU64::new(first_id),
+            channels: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn publish<T: Serialize>(&self, user_id: i32, kind: &'static str, data: &T) {
+        let event = PushEvent {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            kind,
+            data: serde_json::to_value(data).unwrap_or(serde_json::Value::Null)
+        };
+
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channels.entry(user_id).or_insert_with(UserChannel::new);
+
+        if channel.recent.len() == RECENT_EVENTS {
+            channel.recent.pop_front();
+        }
+        channel.recent.push_back(event.clone());
+
+        // Nobody listening is not an error, the event stays in `recent`. Once the last stream
+        // of the user is gone the sender is dropped, the next stream gets a new one
+        match &channel.sender {
+            Some(sender) if sender.receiver_count() > 0 => {
+                let _ = sender.send(event);
+            },
+            _ => channel.sender = None
+        }
+    }
+
+    /// Returns the events missed since `last_event_id` together with a receiver for new ones.
+    pub fn subscribe(&self, user_id: i32, last_event_id: Option<u64>) -> (Vec<PushEvent>, broadcast::Receiver<PushEvent>) {
+        let mut channels = self.channels.lock().unwrap();
+
+        // Senders of users whose streams have all closed since their last event
+        for channel in channels.values_mut() {
+            if channel.sender.as_ref().is_some_and(|sender| sender.receiver_count() == 0) {
+                channel.sender = None;
+            }
+        }
+
+        let channel = channels.entry(user_id).or_insert_with(UserChannel::new);
+
+        let missed = match last_event_id {
+            Some(last_event_id) => channel.recent.iter()
+                .filter(|event| event.id > last_event_id)
+                .cloned()
+                .collect(),
+            None => Vec::new()
+        };
+
+        let sender = channel.sender.get_or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+        (missed, sender.subscribe())
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> EventHub {
+        EventHub::new()
+    }
+}
+
+impl UserChannel {
+    fn new() -> UserChannel {
+        UserChannel {
+            sender: None,
+            recent: VecDeque::with_capacity(RECENT_EVENTS)
+        }
+    }
+}
+
+/// Value of the `Last-Event-ID` header sent by reconnecting SSE clients.
+pub struct LastEventId(pub Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let last_event_id = request.headers()
+            .get_one("Last-Event-ID")
+            .and_then(|id| id.trim().parse::<u64>().ok());
+
+        Outcome::Success(LastEventId(last_event_id))
+    }
+}
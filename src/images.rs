@@ -0,0 +1,190 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use image::{GenericImageView, ImageFormat, ImageOutputFormat};
+use rocket::data::Capped;
+use rocket::fs::TempFile;
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket::tokio::io::AsyncReadExt;
+use rocket::tokio::{fs, task};
+use sha256::digest;
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+
+const THUMBNAIL_DIR: &str = "storage/thumbnails";
+const CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Largest accepted upload, the `file` limit in `main` is set to the same value
+pub const MAX_UPLOAD_MEBIBYTES: u64 = 5;
+/// Side of the square stored covers are fit in
+pub const COVER_SIDE: u32 = 1024;
+/// Side of the square stored avatars are fit in
+pub const AVATAR_SIDE: u32 = 512;
+
+/// Requested image size, thumbnails keep the aspect ratio and fit in a square of the given side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageSize {
+    Original,
+    Small,
+    Medium,
+    Large
+}
+
+impl ImageSize {
+    pub fn parse(size: Option<&str>) -> Result<ImageSize, ApiError> {
+        match size.unwrap_or("original") {
+            "original" => Ok(ImageSize::Original),
+            "small" => Ok(ImageSize::Small),
+            "medium" => Ok(ImageSize::Medium),
+            "large" => Ok(ImageSize::Large),
+            other => Err(ApiError::new(
+                Status::BadRequest,
+                format!("Unknown size {}, expected one of original, small, medium, large", other)
+            ))
+        }
+    }
+
+    fn side(&self) -> Option<u32> {
+        match self {
+            ImageSize::Original => None,
+            ImageSize::Small => Some(64),
+            ImageSize::Medium => Some(256),
+            ImageSize::Large => Some(512)
+        }
+    }
+}
+
+/// Image bytes with caching headers. Answers 304 when `If-None-Match` matches the ETag.
+pub struct ImageResponse {
+    bytes: Vec<u8>,
+    content_type: ContentType,
+    etag: String
+}
+
+impl<'r> Responder<'r, 'static> for ImageResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let not_modified = request.headers()
+            .get("If-None-Match")
+            .any(|etag| etag == self.etag || etag == "*");
+
+        let mut response = Response::build();
+        response
+            .header(Header::new("ETag", self.etag))
+            .header(Header::new("Cache-Control", CACHE_CONTROL));
+
+        if not_modified {
+            return response.status(Status::NotModified).ok();
+        }
+
+        response
+            .header(self.content_type)
+            .sized_body(self.bytes.len(), Cursor::new(self.bytes))
+            .ok()
+    }
+}
+
+/// `multipart/form-data` body of the upload endpoints.
+#[derive(FromForm, ToSchema)]
+pub struct ImageUpload<'r> {
+    /// PNG, JPEG, GIF or WebP file
+    #[schema(value_type = String, format = Binary)]
+    pub image: Capped<TempFile<'r>>
+}
+
+/// URL of an image endpoint, `None` when there is nothing to serve.
+pub fn image_url(path: String, bytes: &[u8]) -> Option<String> {
+    if bytes.is_empty() { None } else { Some(path) }
+}
+
+/// Serves `bytes` in the requested size. Thumbnails are cached on disk under
+/// `storage/thumbnails`, keyed by the content hash so replaced images never serve stale files.
+pub async fn serve(kind: &str, id: i32, bytes: Vec<u8>, size: ImageSize) -> Result<ImageResponse, ApiError> {
+    if bytes.is_empty() {
+        return Err(ApiError::new(Status::NotFound, format!("No {} image for id {}", kind, id)));
+    }
+
+    let format = match image::guess_format(&bytes) {
+        Ok(format) => format,
+        Err(_) => return Err(ApiError::new(
+            Status::UnprocessableEntity,
+            format!("Stored {} image for id {} is not a supported image", kind, id)
+        ))
+    };
+
+    let hash = digest(bytes.as_slice());
+
+    let side = match size.side() {
+        Some(side) => side,
+        None => return Ok(ImageResponse {
+            bytes,
+            content_type: content_type(format),
+            etag: format!("\"{}\"", hash)
+        })
+    };
+
+    let etag = format!("\"{}-{}\"", hash, side);
+    let path = PathBuf::from(THUMBNAIL_DIR).join(format!("{}-{}-{}-{}.png", kind, id, side, &hash[..16]));
+
+    if let Ok(thumbnail) = fs::read(&path).await {
+        return Ok(ImageResponse { bytes: thumbnail, content_type: ContentType::PNG, etag });
+    }
+
+    let thumbnail = task::spawn_blocking(move || resize(&bytes, side))
+        .await
+        .map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))??;
+
+    // A failed cache write only costs a resize on the next request
+    if fs::create_dir_all(THUMBNAIL_DIR).await.is_ok() {
+        let _ = fs::write(&path, &thumbnail).await;
+    }
+
+    Ok(ImageResponse { bytes: thumbnail, content_type: ContentType::PNG, etag })
+}
+
+/// Checks the uploaded file by its content rather than its declared type and re-encodes it as a PNG
+/// fit in `side`. Re-encoding drops EXIF and any other metadata.
+pub async fn normalize(upload: &ImageUpload<'_>, side: u32) -> Result<Vec<u8>, ApiError> {
+    if !upload.image.is_complete() {
+        return Err(ApiError::new(
+            Status::PayloadTooLarge,
+            format!("Images can be at most {} MiB", MAX_UPLOAD_MEBIBYTES)
+        ));
+    }
+
+    let mut bytes = Vec::with_capacity(upload.image.len() as usize);
+    upload.image.open().await
+        .map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    match image::guess_format(&bytes) {
+        Ok(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP) => {},
+        _ => return Err(ApiError::field("image", "must be a PNG, JPEG, GIF or WebP image"))
+    }
+
+    task::spawn_blocking(move || resize(&bytes, side))
+        .await
+        .map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?
+}
+
+fn resize(bytes: &[u8], side: u32) -> Result<Vec<u8>, ApiError> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|err| ApiError::new(Status::UnprocessableEntity, err.to_string()))?;
+
+    let (width, height) = image.dimensions();
+    let image = if width <= side && height <= side { image } else { image.thumbnail(side, side) };
+
+    let mut thumbnail = Cursor::new(Vec::new());
+    image.write_to(&mut thumbnail, ImageOutputFormat::Png)
+        .map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    Ok(thumbnail.into_inner())
+}
+
+fn content_type(format: ImageFormat) -> ContentType {
+    ContentType::parse_flexible(format.to_mime_type()).unwrap_or(ContentType::Binary)
+}
@@ -0,0 +1,17 @@
+#[macro_use]
+extern crate rocket;
+
+pub mod app;
+pub mod setup;
+pub mod routes;
+pub mod entities;
+pub mod error;
+pub mod images;
+pub mod markup;
+pub mod auth;
+pub mod audit;
+pub mod events;
+pub mod metrics;
+pub mod logging;
+pub mod retention;
+pub mod views;
\ No newline at end of file
@@ -0,0 +1,146 @@
+use std::time::Instant;
+
+use rand::RngCore;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::route::{self, Handler, Route};
+use rocket::{Data, Request, Response};
+use tracing::{Instrument, Span};
+use tracing_subscriber::EnvFilter;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+// Longer or unusual incoming ids are replaced so they can not be used to forge log lines
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+/// Installs the JSON subscriber. `EGLINE_LOG` takes `tracing` filter directives and defaults to `info`.
+/// Records of the `log` crate, which Rocket writes to, end up in the same output.
+pub fn init() {
+    let filter = EnvFilter::try_from_env("EGLINE_LOG").unwrap_or_else(|_| EnvFilter::new("info"));
+
+    // Fails only when a subscriber or logger is already installed, which then keeps receiving the records
+    let _ = tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(filter)
+        .with_current_span(true)
+        .with_span_list(true)
+        .try_init();
+}
+
+/// Correlation id of a request and the span its handler and queries are logged in.
+struct RequestTrace {
+    id: String,
+    span: Span,
+    start: Instant
+}
+
+impl RequestTrace {
+    fn new(request: &Request<'_>) -> RequestTrace {
+        let id = match request.headers().get_one(REQUEST_ID_HEADER) {
+            Some(id) if is_valid_request_id(id) => id.to_string(),
+            _ => new_request_id()
+        };
+        let span = tracing::info_span!(
+            "request",
+            request_id = %id,
+            method = %request.method(),
+            uri = %request.uri()
+        );
+
+        RequestTrace { id, span, start: Instant::now() }
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn new_request_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Requests that did not pass the fairing, like local test clients without it, still get an id
+fn request_trace<'r>(request: &'r Request<'_>) -> &'r RequestTrace {
+    request.local_cache(|| RequestTrace::new(request))
+}
+
+/// Id of the request, the one sent in `X-Request-Id` or a generated one.
+pub fn request_id(request: &Request<'_>) -> String {
+    request_trace(request).id.clone()
+}
+
+/// Assigns the request id, echoes it in the `X-Request-Id` response header and logs every response.
+pub struct RequestTracing;
+
+#[rocket::async_trait]
+impl Fairing for RequestTracing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request tracing",
+            kind: Kind::Request | Kind::Response
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request_trace(request);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let trace = request_trace(request);
+        let status = response.status().code;
+        let elapsed_ms = trace.start.elapsed().as_secs_f64() * 1000.0;
+        let route = request.route().map(|route| route.uri.to_string());
+
+        tracing::info!(
+            parent: &trace.span,
+            status,
+            elapsed_ms,
+            route = route.as_deref().unwrap_or(""),
+            "response"
+        );
+
+        response.set_header(Header::new(REQUEST_ID_HEADER, trace.id.clone()));
+    }
+}
+
+/// Runs a route's handler inside the request span, so events logged by the handler and its queries carry the request id.
+#[derive(Clone)]
+struct Traced(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Traced {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let trace = request_trace(request);
+        let name = request.route().and_then(|route| route.name.as_deref()).unwrap_or("");
+        let span = tracing::info_span!(parent: &trace.span, "handler", name);
+
+        self.0.handle(request, data).instrument(span).await
+    }
+}
+
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Traced(route.handler));
+            route
+        })
+        .collect()
+}
+
+/// Logs a finished SeaORM query. SeaORM reports queries after they ran, so they are events
+/// in the span of the handler that issued them rather than spans of their own.
+pub fn log_query(info: &sea_orm::metric::Info<'_>) {
+    let elapsed_ms = info.elapsed.as_secs_f64() * 1000.0;
+
+    if info.failed {
+        tracing::warn!(target: "egline::db", elapsed_ms, sql = %info.statement.sql, "query failed");
+    } else {
+        tracing::debug!(target: "egline::db", elapsed_ms, sql = %info.statement.sql, "query");
+    }
+}
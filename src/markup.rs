@@ -0,0 +1,52 @@
+//! Inline markup used in comments and reviews.
+//! Spoilers are written as `||hidden text||` and rendered as `<span class="spoiler">hidden text</span>`,
+//! everything else is HTML-escaped.
+
+const SPOILER_DELIMITER: &str = "||";
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' => escaped.push_str("<br>"),
+            _ => escaped.push(ch)
+        }
+    }
+
+    escaped
+}
+
+pub fn render(text: &str) -> String {
+    let parts = text.split(SPOILER_DELIMITER).collect::<Vec<&str>>();
+    // An odd number of parts means every delimiter has a closing pair
+    let closed_parts = if parts.len() % 2 == 0 { parts.len() - 1 } else { parts.len() };
+    let mut html = String::with_capacity(text.len());
+
+    for (index, part) in parts.iter().enumerate() {
+        if index >= closed_parts {
+            html.push_str(SPOILER_DELIMITER);
+            html.push_str(&escape_html(part));
+        } else if index % 2 == 1 {
+            html.push_str(&spoiler(&escape_html(part)));
+        } else {
+            html.push_str(&escape_html(part));
+        }
+    }
+
+    html
+}
+
+/// Renders the text and hides all of it behind a single spoiler.
+pub fn render_hidden(text: &str) -> String {
+    spoiler(&render(text))
+}
+
+fn spoiler(html: &str) -> String {
+    format!("<span class=\"spoiler\">{}</span>", html)
+}
@@ -0,0 +1,185 @@
+use std::time::Instant;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Data, Request, Response};
+use sea_orm::DatabaseConnection;
+
+pub const LOGIN_API: &str = "api";
+pub const LOGIN_ADMIN: &str = "admin";
+pub const VOTE_COMMENT: &str = "comment";
+pub const VOTE_REVIEW: &str = "review";
+pub const CHAPTER_TEXT: &str = "text";
+pub const CHAPTER_AUDIO: &str = "audio";
+
+// Requests that did not match a route share one label so unknown paths can not grow the series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Prometheus collectors of the server. Clones share the same collectors, so one copy is managed
+/// for handlers, one is attached as the request fairing and one is moved into the SeaORM metric callback.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    db_queries: HistogramVec,
+    db_connections: IntGaugeVec,
+    chapter_bytes: IntCounterVec,
+    logins: IntCounterVec,
+    comments: IntCounter,
+    votes: IntCounterVec
+}
+
+struct RequestStart(Option<Instant>);
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let registry = Registry::new_custom(Some("egline".to_string()), None).unwrap();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Handled requests by route, method and status"),
+            &["route", "method", "status"]
+        ).unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Time to produce a response by route and method"),
+            &["route", "method"]
+        ).unwrap();
+        let db_queries = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "SeaORM query time by statement kind and outcome")
+                .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0]),
+            &["statement", "failed"]
+        ).unwrap();
+        let db_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Open database connections by state"),
+            &["state"]
+        ).unwrap();
+        let chapter_bytes = IntCounterVec::new(
+            Opts::new("chapter_bytes_served_total", "Bytes of chapter text and audio read from storage"),
+            &["kind"]
+        ).unwrap();
+        let logins = IntCounterVec::new(
+            Opts::new("logins_total", "Successful logins by client"),
+            &["client"]
+        ).unwrap();
+        let comments = IntCounter::new("comments_created_total", "Created comments").unwrap();
+        let votes = IntCounterVec::new(
+            Opts::new("votes_total", "Votes cast or changed on comments and reviews"),
+            &["target"]
+        ).unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry.register(Box::new(db_queries.clone())).unwrap();
+        registry.register(Box::new(db_connections.clone())).unwrap();
+        registry.register(Box::new(chapter_bytes.clone())).unwrap();
+        registry.register(Box::new(logins.clone())).unwrap();
+        registry.register(Box::new(comments.clone())).unwrap();
+        registry.register(Box::new(votes.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_duration,
+            db_queries,
+            db_connections,
+            chapter_bytes,
+            logins,
+            comments,
+            votes
+        }
+    }
+
+    /// Records a query reported by SeaORM's metric callback, labelled by its first SQL keyword
+    pub fn observe_query(&self, info: &sea_orm::metric::Info<'_>) {
+        let statement = info.statement.sql
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        self.db_queries
+            .with_label_values(&[&statement, if info.failed { "true" } else { "false" }])
+            .observe(info.elapsed.as_secs_f64());
+    }
+
+    pub fn chapter_served(&self, kind: &str, bytes: usize) {
+        self.chapter_bytes.with_label_values(&[kind]).inc_by(bytes as u64);
+    }
+
+    pub fn login(&self, client: &str) {
+        self.logins.with_label_values(&[client]).inc();
+    }
+
+    pub fn comment_created(&self) {
+        self.comments.inc();
+    }
+
+    pub fn vote(&self, target: &str) {
+        self.votes.with_label_values(&[target]).inc();
+    }
+
+    /// All collectors in the Prometheus text format, with the pool gauges read at call time
+    pub fn render(&self, db: &DatabaseConnection) -> String {
+        let pool_usage = match db {
+            DatabaseConnection::SqlxPostgresPoolConnection(_) => {
+                let pool = db.get_postgres_connection_pool();
+                Some((pool.size(), pool.num_idle()))
+            },
+            #[cfg(feature = "sqlite")]
+            DatabaseConnection::SqlxSqlitePoolConnection(_) => {
+                let pool = db.get_sqlite_connection_pool();
+                Some((pool.size(), pool.num_idle()))
+            },
+            _ => None
+        };
+
+        if let Some((size, idle)) = pool_usage {
+            self.db_connections.with_label_values(&["idle"]).set(idle as i64);
+            self.db_connections.with_label_values(&["in_use"]).set(size as i64 - idle as i64);
+        }
+
+        let mut buffer = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Metrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus metrics",
+            kind: Kind::Request | Kind::Response
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let route = match request.route() {
+            Some(route) => route.uri.to_string(),
+            None => UNMATCHED_ROUTE.to_string()
+        };
+        let method = request.method().as_str();
+        let status = response.status().code.to_string();
+
+        self.http_requests.with_label_values(&[&route, method, &status]).inc();
+
+        if let RequestStart(Some(start)) = request.local_cache(|| RequestStart(None)) {
+            self.http_duration
+                .with_label_values(&[&route, method])
+                .observe(start.elapsed().as_secs_f64());
+        }
+    }
+}
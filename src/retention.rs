@@ -0,0 +1,195 @@
+//! Purge of trashed books, authors, users and comments. Deleting one of them only sets its `deleted_at`,
+//! rows stay restorable until they are older than the retention window and the purge removes them for good.
+
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
+use rocket::tokio::{self, fs, time};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use crate::entities::prelude::{Author, Book, BookAuthor, BookRate, Comment, CommentRate, Review, ReviewVote, User};
+use crate::entities::{author, book, book_author, book_rate, comment, comment_rate, review, review_vote, user};
+use crate::routes::{author_route, book_route, comment_rate_route, review_route};
+
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long trashed rows are kept, `retention_days` in Rocket.toml or `ROCKET_RETENTION_DAYS`.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub days: i64
+}
+
+impl Retention {
+    pub fn from_figment(figment: &Figment) -> Retention {
+        Retention {
+            days: figment.extract_inner("retention_days").unwrap_or(DEFAULT_RETENTION_DAYS)
+        }
+    }
+
+    /// Rows trashed before this moment are due for the purge.
+    pub fn cutoff(&self) -> NaiveDateTime {
+        Utc::now().naive_utc() - chrono::Duration::days(self.days)
+    }
+
+    /// When a row trashed at `deleted_at` is purged at the earliest.
+    pub fn purge_at(&self, deleted_at: NaiveDateTime) -> NaiveDateTime {
+        deleted_at + chrono::Duration::days(self.days)
+    }
+}
+
+/// Rows removed by one purge.
+#[derive(Debug)]
+pub struct Purged {
+    pub books: u64,
+    pub authors: u64,
+    pub users: u64,
+    pub comments: u64
+}
+
+/// Removes the rows trashed before `cutoff`. Books go with their chapters and storage directories, and
+/// their authors' ratings are recomputed; votes and rates of purged users leave the counters of what
+/// they voted on, so those are recomputed too.
+pub async fn purge(db: &DatabaseConnection, cutoff: NaiveDateTime) -> Result<Purged, DbErr> {
+    let comments = Comment::delete_many()
+        .filter(comment::Column::DeletedAt.lt(cutoff))
+        .exec(db)
+        .await?
+        .rows_affected;
+
+    let book_ids = Book::find()
+        .filter(book::Column::DeletedAt.lt(cutoff))
+        .all(db)
+        .await?
+        .iter()
+        .map(|book| book.id)
+        .collect::<Vec<i32>>();
+
+    // Links to the authors go with the books, so their authors are collected first
+    let author_ids = BookAuthor::find()
+        .filter(book_author::Column::BookId.is_in(book_ids.clone()))
+        .all(db)
+        .await?
+        .iter()
+        .map(|book_author| book_author.author_id)
+        .collect::<Vec<i32>>();
+
+    let mut books = 0;
+    if !book_ids.is_empty() {
+        // Chapters and their comments go with the book
+        books = Book::delete_many()
+            .filter(book::Column::Id.is_in(book_ids.clone()))
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        for book_id in &book_ids {
+            if let Err(err) = fs::remove_dir_all(format!("storage/{}", book_id)).await {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(DbErr::Custom(format!("Can not remove storage of book {}: {}", book_id, err)));
+                }
+            }
+        }
+    }
+
+    let authors = Author::delete_many()
+        .filter(author::Column::DeletedAt.lt(cutoff))
+        .exec(db)
+        .await?
+        .rows_affected;
+
+    // Authors purged along with their books have nothing left to rate
+    for author in Author::find().filter(author::Column::Id.is_in(author_ids)).all(db).await? {
+        author_route::update_author_rating(db, author.id).await?;
+    }
+
+    let user_ids = User::find()
+        .filter(user::Column::DeletedAt.lt(cutoff))
+        .all(db)
+        .await?
+        .iter()
+        .map(|user| user.id)
+        .collect::<Vec<i32>>();
+
+    let mut users = 0;
+    if !user_ids.is_empty() {
+        let rated_books = BookRate::find()
+            .filter(book_rate::Column::UserId.is_in(user_ids.clone()))
+            .all(db)
+            .await?
+            .iter()
+            .map(|book_rate| book_rate.book_id)
+            .collect::<Vec<i32>>();
+        let voted_comments = CommentRate::find()
+            .filter(comment_rate::Column::UserId.is_in(user_ids.clone()))
+            .all(db)
+            .await?
+            .iter()
+            .map(|comment_rate| comment_rate.comment_id)
+            .collect::<Vec<i32>>();
+        let voted_reviews = ReviewVote::find()
+            .filter(review_vote::Column::UserId.is_in(user_ids.clone()))
+            .all(db)
+            .await?
+            .iter()
+            .map(|review_vote| review_vote.review_id)
+            .collect::<Vec<i32>>();
+
+        users = User::delete_many()
+            .filter(user::Column::Id.is_in(user_ids))
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        // Comments and reviews written by the purged users are gone with them
+        for book in Book::find().filter(book::Column::Id.is_in(rated_books)).all(db).await? {
+            book_route::update_book_rating(db, book.id).await?;
+        }
+        for comment in Comment::find().filter(comment::Column::Id.is_in(voted_comments)).all(db).await? {
+            comment_rate_route::update_comment_votes(db, comment.id).await?;
+        }
+        for review in Review::find().filter(review::Column::Id.is_in(voted_reviews)).all(db).await? {
+            review_route::update_review_votes(db, review.id).await?;
+        }
+    }
+
+    Ok(Purged { books, authors, users, comments })
+}
+
+/// Runs the purge once the server is up and then every hour, until shutdown.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Retention purge", |rocket| Box::pin(async move {
+        let (Some(db), Some(retention)) = (
+            rocket.state::<DatabaseConnection>().cloned(),
+            rocket.state::<Retention>().copied()
+        ) else {
+            return;
+        };
+        let mut shutdown = rocket.shutdown();
+
+        tokio::spawn(async move {
+            let mut interval = time::interval(PURGE_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = &mut shutdown => break
+                }
+
+                match purge(&db, retention.cutoff()).await {
+                    Ok(purged) => tracing::info!(
+                        books = purged.books,
+                        authors = purged.authors,
+                        users = purged.users,
+                        comments = purged.comments,
+                        "purged trashed rows"
+                    ),
+                    Err(err) => tracing::error!(error = %err, "retention purge failed")
+                }
+            }
+        });
+    }))
+}
@@ -0,0 +1,991 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use rocket::form::Form;
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::request::Request;
+use rocket::response::{self, status, Redirect, Responder};
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+use serde::Serialize;
+
+use sea_orm::{
+    prelude::{DateTime, DbErr}, ActiveEnum, ColumnTrait, Condition, DatabaseConnection, EntityTrait, Iterable, PaginatorTrait, QueryFilter,
+    QueryOrder, Select
+};
+
+use crate::audit::{self, Actor};
+use crate::auth::{self, AdminUser};
+use crate::error::ApiError;
+use crate::images;
+use crate::metrics::{self, Metrics};
+use crate::retention::Retention;
+use crate::entities::prelude::{AuditLog, Author, Book, Chapter, Comment, Genre, Review, User};
+use crate::entities::{audit_log, author, book, chapter, comment, genre, review, user};
+use crate::entities::sea_orm_active_enums::BookStatus;
+use crate::routes::{author_route, book_route, chapter_route, comment_route, genre_route, review_route, user_route};
+use crate::routes::author_route::{AuthorData, AuthorWithAvatarUrl};
+use crate::routes::book_route::BookData;
+use crate::routes::chapter_route::ChapterTitleData;
+use crate::routes::genre_route::GenreData;
+use crate::routes::user_route::{UserAuthModel, UserRoleData, UserWithoutPassword};
+
+const PER_PAGE: u64 = 25;
+
+/// Admin page, or a redirect after a successful form submission.
+#[derive(Responder)]
+enum AdminPage {
+    #[response(status = 401)]
+    Unauthorized(Template),
+    /// Form rendered again with the field errors
+    #[response(status = 422)]
+    Invalid(Template),
+    Redirect(Box<Redirect>)
+}
+
+/// `ApiError` rendered as an admin page with the same status.
+struct AdminError(ApiError);
+
+impl From<ApiError> for AdminError {
+    fn from(err: ApiError) -> AdminError {
+        AdminError(err)
+    }
+}
+
+impl From<DbErr> for AdminError {
+    fn from(err: DbErr) -> AdminError {
+        AdminError(err.into())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for AdminError {
+    fn respond_to(mut self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = Status::from_code(self.0.code).unwrap_or(Status::InternalServerError);
+        self.0.trace(request);
+
+        let page = Template::render("admin/error", context! {
+            code: self.0.code,
+            message: self.0.message,
+            errors: self.0.errors,
+            request_id: self.0.request_id
+        });
+
+        status::Custom(status, page).respond_to(request)
+    }
+}
+
+/// One page of a list view, `q` is the search the items were filtered by.
+#[derive(Serialize)]
+struct Listing<T> {
+    items: Vec<T>,
+    q: String,
+    page: u64,
+    pages: u64,
+    total: u64
+}
+
+/// Book without the cover bytes, which have no place in a template context.
+#[derive(Serialize)]
+struct BookRow {
+    id: i32,
+    title: String,
+    description: String,
+    rating: f32,
+    year: i32,
+    views: i32,
+    status: BookStatus,
+    cover_url: Option<String>
+}
+
+impl From<book::Model> for BookRow {
+    fn from(book: book::Model) -> BookRow {
+        BookRow {
+            cover_url: images::image_url(format!("/api/v1/book/{}/cover", book.id), &book.cover),
+            id: book.id,
+            title: book.title,
+            description: book.description,
+            rating: book.rating,
+            year: book.year,
+            views: book.views,
+            status: book.status
+        }
+    }
+}
+
+async fn fetch_page<E, T>(
+    db: &DatabaseConnection,
+    query: Select<E>,
+    q: Option<String>,
+    page: Option<u64>
+) -> Result<Listing<T>, AdminError>
+where
+    E: EntityTrait,
+    E::Model: Sync,
+    T: From<E::Model>
+{
+    let page = page.unwrap_or(1).max(1);
+    let paginator = query.paginate(db, PER_PAGE);
+
+    let counts = paginator.num_items_and_pages().await?;
+    let items = paginator.fetch_page(page - 1).await?;
+
+    Ok(Listing {
+        items: items.into_iter().map(T::from).collect(),
+        q: q.unwrap_or_default(),
+        page,
+        pages: counts.number_of_pages.max(1),
+        total: counts.number_of_items
+    })
+}
+
+/// Search term, `None` when empty.
+fn search_term(q: &Option<String>) -> Option<String> {
+    q.as_ref().map(|q| q.trim().to_string()).filter(|q| !q.is_empty())
+}
+
+#[get("/login")]
+fn login_page() -> Template {
+    Template::render("admin/login", context! {})
+}
+
+#[post("/login", data = "<login_data>")]
+async fn login(
+    db: &State<DatabaseConnection>,
+    cookies: &CookieJar<'_>,
+    metrics: &State<Metrics>,
+    login_data: Form<UserAuthModel>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let user = match user_route::find_by_credentials(db, &login_data).await {
+        Ok(user) => user,
+        Err(err) if err.has_field_errors() => None,
+        Err(err) => return Err(err.into())
+    };
+
+    match user {
+        Some(user) if user.role == auth::ROLE_ADMIN => {
+            let token = auth::create_session(db, user.id).await?;
+
+            cookies.add(
+                // Sent to the API too, so the panel's uploads of covers and avatars are signed in
+                Cookie::build((auth::ADMIN_COOKIE, token))
+                    .path("/")
+                    .http_only(true)
+                    .same_site(SameSite::Strict)
+            );
+            metrics.login(metrics::LOGIN_ADMIN);
+
+            Ok(AdminPage::Redirect(Box::new(Redirect::to("/admin"))))
+        },
+        Some(_) => Ok(AdminPage::Unauthorized(Template::render("admin/login", context! {
+            email: &login_data.email,
+            error: "This account does not have the admin role"
+        }))),
+        None => Ok(AdminPage::Unauthorized(Template::render("admin/login", context! {
+            email: &login_data.email,
+            error: "Email or password are not valid"
+        })))
+    }
+}
+
+#[post("/logout")]
+async fn logout(
+    db: &State<DatabaseConnection>,
+    cookies: &CookieJar<'_>,
+    admin: AdminUser
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    auth::delete_session(db, admin.token).await?;
+    cookies.remove(Cookie::build(auth::ADMIN_COOKIE).path("/"));
+
+    Ok(Redirect::to("/admin/login"))
+}
+
+#[get("/")]
+async fn dashboard(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    Ok(Template::render("admin/dashboard", context! {
+        admin: admin.display_name,
+        books: Book::find().filter(book::Column::DeletedAt.is_null()).count(db).await?,
+        authors: Author::find().filter(author::Column::DeletedAt.is_null()).count(db).await?,
+        genres: Genre::find().count(db).await?,
+        chapters: Chapter::find().count(db).await?,
+        users: User::find().filter(user::Column::DeletedAt.is_null()).count(db).await?,
+        comments: Comment::find().filter(comment::Column::DeletedAt.is_null()).count(db).await?,
+        reviews: Review::find().count(db).await?,
+        trashed: trashed_count(db).await?
+    }))
+}
+
+#[get("/books?<q>&<page>")]
+async fn list_books(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    q: Option<String>,
+    page: Option<u64>
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let mut query = Book::find()
+        .filter(book::Column::DeletedAt.is_null())
+        .order_by_asc(book::Column::Title);
+
+    if let Some(term) = search_term(&q) {
+        query = query.filter(book::Column::Title.contains(term));
+    }
+
+    let books: Listing<BookRow> = fetch_page(db, query, q, page).await?;
+
+    Ok(Template::render("admin/books", context! {
+        admin: admin.display_name,
+        books
+    }))
+}
+
+#[get("/books/new")]
+fn new_book(admin: AdminUser) -> Template {
+    Template::render("admin/book_form", context! {
+        admin: admin.display_name,
+        statuses: BookStatus::names()
+    })
+}
+
+#[get("/books/<id>")]
+async fn edit_book(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    id: i32
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let book = match Book::find_by_id(id).filter(book::Column::DeletedAt.is_null()).one(db).await? {
+        Some(book) => BookRow::from(book),
+        None => return Err(ApiError::new(Status::NotFound, format!("No book with id {}", id)).into())
+    };
+
+    book_form(db, admin, Some(id), &book, BTreeMap::new()).await
+}
+
+#[post("/books", data = "<book_data>")]
+async fn create_book(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    book_data: Form<BookData>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match book_route::save_book(db, &actor, None, &book_data).await {
+        Ok(book) => Ok(AdminPage::Redirect(Box::new(Redirect::to(format!("/admin/books/{}", book.id))))),
+        Err(err) if err.has_field_errors() => {
+            Ok(AdminPage::Invalid(book_form(db, admin, None, &*book_data, err.errors).await?))
+        },
+        Err(err) => Err(err.into())
+    }
+}
+
+#[post("/books/<id>", data = "<book_data>")]
+async fn update_book(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    id: i32,
+    book_data: Form<BookData>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match book_route::save_book(db, &actor, Some(id), &book_data).await {
+        Ok(book) => Ok(AdminPage::Redirect(Box::new(Redirect::to(format!("/admin/books/{}", book.id))))),
+        Err(err) if err.has_field_errors() => {
+            Ok(AdminPage::Invalid(book_form(db, admin, Some(id), &*book_data, err.errors).await?))
+        },
+        Err(err) => Err(err.into())
+    }
+}
+
+#[post("/books/<id>/delete")]
+async fn delete_book(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    book_route::remove_book(db, &actor, id).await?;
+
+    Ok(Redirect::to("/admin/books"))
+}
+
+/// Book form, with the book's chapters when editing an existing book.
+async fn book_form<B: Serialize>(
+    db: &DatabaseConnection,
+    admin: AdminUser,
+    id: Option<i32>,
+    book: &B,
+    errors: BTreeMap<String, Vec<String>>
+) -> Result<Template, AdminError> {
+    let chapters = match id {
+        Some(id) => Chapter::find()
+            .filter(chapter::Column::BookId.eq(id))
+            .order_by_asc(chapter::Column::Number)
+            .all(db)
+            .await?,
+        None => Vec::new()
+    };
+
+    // An existing book only offers the statuses it can move to
+    let statuses = match id {
+        Some(id) => match Book::find_by_id(id).one(db).await? {
+            Some(saved) => BookStatus::iter()
+                .filter(|status| saved.status.can_become(*status))
+                .map(|status| status.to_value())
+                .collect(),
+            None => BookStatus::names()
+        },
+        None => BookStatus::names()
+    };
+
+    Ok(Template::render("admin/book_form", context! {
+        admin: admin.display_name,
+        id,
+        book,
+        chapters,
+        errors,
+        statuses
+    }))
+}
+
+#[post("/chapters/<id>", data = "<title_data>")]
+async fn rename_chapter(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32,
+    title_data: Form<ChapterTitleData>
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let chapter = chapter_route::rename_chapter(db, &actor, id, &title_data).await?;
+
+    Ok(Redirect::to(format!("/admin/books/{}", chapter.book_id)))
+}
+
+#[post("/chapters/<id>/delete")]
+async fn delete_chapter(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let book_id = match Chapter::find_by_id(id).one(db).await? {
+        Some(chapter) => chapter.book_id,
+        None => return Err(ApiError::new(Status::NotFound, format!("No chapter with id {}", id)).into())
+    };
+
+    chapter_route::remove_chapter(db, &actor, id).await?;
+
+    Ok(Redirect::to(format!("/admin/books/{}", book_id)))
+}
+
+#[get("/authors?<q>&<page>")]
+async fn list_authors(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    q: Option<String>,
+    page: Option<u64>
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let mut query = Author::find()
+        .filter(author::Column::DeletedAt.is_null())
+        .order_by_asc(author::Column::LastName)
+        .order_by_asc(author::Column::FirstName);
+
+    if let Some(term) = search_term(&q) {
+        query = query.filter(
+            Condition::any()
+                .add(author::Column::FirstName.contains(term.clone()))
+                .add(author::Column::LastName.contains(term))
+        );
+    }
+
+    let authors: Listing<AuthorWithAvatarUrl> = fetch_page(db, query, q, page).await?;
+
+    Ok(Template::render("admin/authors", context! {
+        admin: admin.display_name,
+        authors
+    }))
+}
+
+#[get("/authors/new")]
+fn new_author(admin: AdminUser) -> Template {
+    Template::render("admin/author_form", context! {
+        admin: admin.display_name
+    })
+}
+
+#[get("/authors/<id>")]
+async fn edit_author(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    id: i32
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let author = match Author::find_by_id(id).filter(author::Column::DeletedAt.is_null()).one(db).await? {
+        Some(author) => AuthorWithAvatarUrl::from(author),
+        None => return Err(ApiError::new(Status::NotFound, format!("No author with id {}", id)).into())
+    };
+
+    Ok(Template::render("admin/author_form", context! {
+        admin: admin.display_name,
+        id,
+        author
+    }))
+}
+
+#[post("/authors", data = "<author_data>")]
+async fn create_author(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    author_data: Form<AuthorData>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match author_route::save_author(db, &actor, None, &author_data).await {
+        Ok(author) => Ok(AdminPage::Redirect(Box::new(Redirect::to(format!("/admin/authors/{}", author.id))))),
+        Err(err) if err.has_field_errors() => Ok(AdminPage::Invalid(Template::render("admin/author_form", context! {
+            admin: admin.display_name,
+            author: &*author_data,
+            errors: err.errors
+        }))),
+        Err(err) => Err(err.into())
+    }
+}
+
+#[post("/authors/<id>", data = "<author_data>")]
+async fn update_author(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    id: i32,
+    author_data: Form<AuthorData>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match author_route::save_author(db, &actor, Some(id), &author_data).await {
+        Ok(author) => Ok(AdminPage::Redirect(Box::new(Redirect::to(format!("/admin/authors/{}", author.id))))),
+        Err(err) if err.has_field_errors() => Ok(AdminPage::Invalid(Template::render("admin/author_form", context! {
+            admin: admin.display_name,
+            id,
+            author: &*author_data,
+            errors: err.errors
+        }))),
+        Err(err) => Err(err.into())
+    }
+}
+
+#[post("/authors/<id>/delete")]
+async fn delete_author(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    author_route::remove_author(db, &actor, id).await?;
+
+    Ok(Redirect::to("/admin/authors"))
+}
+
+#[get("/genres")]
+async fn list_genres(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    genres_page(db, admin, BTreeMap::new()).await
+}
+
+#[post("/genres", data = "<genre_data>")]
+async fn create_genre(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    genre_data: Form<GenreData>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match genre_route::save_genre(db, &actor, None, &genre_data).await {
+        Ok(_) => Ok(AdminPage::Redirect(Box::new(Redirect::to("/admin/genres")))),
+        Err(err) if err.has_field_errors() => Ok(AdminPage::Invalid(genres_page(db, admin, err.errors).await?)),
+        Err(err) => Err(err.into())
+    }
+}
+
+#[post("/genres/<id>", data = "<genre_data>")]
+async fn rename_genre(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    id: i32,
+    genre_data: Form<GenreData>
+) -> Result<AdminPage, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match genre_route::save_genre(db, &actor, Some(id), &genre_data).await {
+        Ok(_) => Ok(AdminPage::Redirect(Box::new(Redirect::to("/admin/genres")))),
+        Err(err) if err.has_field_errors() => Ok(AdminPage::Invalid(genres_page(db, admin, err.errors).await?)),
+        Err(err) => Err(err.into())
+    }
+}
+
+#[post("/genres/<id>/delete")]
+async fn delete_genre(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    genre_route::remove_genre(db, &actor, id).await?;
+
+    Ok(Redirect::to("/admin/genres"))
+}
+
+/// Genres are few, so the page lists all of them with inline create and rename forms.
+async fn genres_page(
+    db: &DatabaseConnection,
+    admin: AdminUser,
+    errors: BTreeMap<String, Vec<String>>
+) -> Result<Template, AdminError> {
+    let genres = Genre::find().order_by_asc(genre::Column::Title).all(db).await?;
+
+    Ok(Template::render("admin/genres", context! {
+        admin: admin.display_name,
+        genres,
+        errors
+    }))
+}
+
+#[get("/users?<q>&<page>")]
+async fn list_users(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    q: Option<String>,
+    page: Option<u64>
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let mut query = User::find()
+        .filter(user::Column::DeletedAt.is_null())
+        .order_by_asc(user::Column::DisplayName);
+
+    if let Some(term) = search_term(&q) {
+        query = query.filter(
+            Condition::any()
+                .add(user::Column::DisplayName.contains(term.clone()))
+                .add(user::Column::Email.contains(term.to_lowercase()))
+        );
+    }
+
+    let users: Listing<UserWithoutPassword> = fetch_page(db, query, q, page).await?;
+
+    Ok(Template::render("admin/users", context! {
+        admin_id: admin.id,
+        admin: admin.display_name,
+        users,
+        roles: auth::ROLES
+    }))
+}
+
+#[post("/users/<id>/role", data = "<role_data>")]
+async fn set_user_role(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    id: i32,
+    role_data: Form<UserRoleData>
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    // Keeps at least the current admin able to sign in
+    if id == admin.id {
+        return Err(ApiError::new(Status::BadRequest, "Admins can not change their own role".to_string()).into());
+    }
+
+    user_route::set_user_role(db, &actor, id, &role_data).await?;
+
+    Ok(Redirect::to("/admin/users"))
+}
+
+#[post("/users/<id>/delete")]
+async fn delete_user(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    if id == admin.id {
+        return Err(ApiError::new(Status::BadRequest, "Admins can not delete themselves".to_string()).into());
+    }
+
+    user_route::remove_user(db, &actor, id).await?;
+
+    Ok(Redirect::to("/admin/users"))
+}
+
+#[get("/comments?<q>&<page>")]
+async fn list_comments(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    q: Option<String>,
+    page: Option<u64>
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let mut query = Comment::find()
+        .filter(comment::Column::DeletedAt.is_null())
+        .order_by_desc(comment::Column::Id);
+
+    if let Some(term) = search_term(&q) {
+        query = query.filter(comment::Column::Text.contains(term));
+    }
+
+    let comments: Listing<comment::Model> = fetch_page(db, query, q, page).await?;
+
+    Ok(Template::render("admin/comments", context! {
+        admin: admin.display_name,
+        comments
+    }))
+}
+
+#[post("/comments/<id>/delete")]
+async fn delete_comment(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    comment_route::remove_comment(db, &actor, id).await?;
+
+    Ok(Redirect::to("/admin/comments"))
+}
+
+#[get("/reviews?<q>&<page>")]
+async fn list_reviews(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    q: Option<String>,
+    page: Option<u64>
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let mut query = Review::find()
+        .order_by_desc(review::Column::CreatedAt)
+        .order_by_desc(review::Column::Id);
+
+    if let Some(term) = search_term(&q) {
+        query = query.filter(
+            Condition::any()
+                .add(review::Column::Title.contains(term.clone()))
+                .add(review::Column::Body.contains(term))
+        );
+    }
+
+    let reviews: Listing<review::Model> = fetch_page(db, query, q, page).await?;
+
+    Ok(Template::render("admin/reviews", context! {
+        admin: admin.display_name,
+        reviews
+    }))
+}
+
+#[post("/reviews/<id>/delete")]
+async fn delete_review(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    review_route::remove_review(db, &actor, id).await?;
+
+    Ok(Redirect::to("/admin/reviews"))
+}
+
+/// Trashed row of any kind, `kind` is the path segment of its list and restore form.
+#[derive(Serialize)]
+struct TrashRow {
+    kind: &'static str,
+    id: i32,
+    label: String,
+    deleted_at: DateTime,
+    purge_at: DateTime
+}
+
+impl TrashRow {
+    fn new(kind: &'static str, id: i32, label: String, deleted_at: Option<DateTime>, retention: &Retention) -> TrashRow {
+        let deleted_at = deleted_at.unwrap_or_default();
+
+        TrashRow { kind, id, label, deleted_at, purge_at: retention.purge_at(deleted_at) }
+    }
+}
+
+async fn trashed_count(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    Ok(
+        Book::find().filter(book::Column::DeletedAt.is_not_null()).count(db).await?
+            + Author::find().filter(author::Column::DeletedAt.is_not_null()).count(db).await?
+            + User::find().filter(user::Column::DeletedAt.is_not_null()).count(db).await?
+            + Comment::find().filter(comment::Column::DeletedAt.is_not_null()).count(db).await?
+    )
+}
+
+/// Everything in the trash, most recently deleted first, with the date the purge removes it.
+#[get("/trash")]
+async fn trash(
+    db: &State<DatabaseConnection>,
+    retention: &State<Retention>,
+    admin: AdminUser
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+    let mut rows = Vec::new();
+
+    for book in Book::find().filter(book::Column::DeletedAt.is_not_null()).all(db).await? {
+        rows.push(TrashRow::new("books", book.id, book.title, book.deleted_at, retention));
+    }
+    for author in Author::find().filter(author::Column::DeletedAt.is_not_null()).all(db).await? {
+        let name = format!("{} {}", author.first_name, author.last_name);
+        rows.push(TrashRow::new("authors", author.id, name, author.deleted_at, retention));
+    }
+    for user in User::find().filter(user::Column::DeletedAt.is_not_null()).all(db).await? {
+        let name = format!("{} <{}>", user.display_name, user.email);
+        rows.push(TrashRow::new("users", user.id, name, user.deleted_at, retention));
+    }
+    for comment in Comment::find().filter(comment::Column::DeletedAt.is_not_null()).all(db).await? {
+        let text = comment.text.chars().take(80).collect();
+        rows.push(TrashRow::new("comments", comment.id, text, comment.deleted_at, retention));
+    }
+
+    rows.sort_by_key(|row| Reverse(row.deleted_at));
+
+    Ok(Template::render("admin/trash", context! {
+        admin: admin.display_name,
+        rows,
+        retention_days: retention.days
+    }))
+}
+
+#[post("/books/<id>/restore")]
+async fn restore_book(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match book_route::restore_book(db, &actor, id).await? {
+        0 => Err(ApiError::new(Status::NotFound, format!("No trashed book with id {}", id)).into()),
+        _ => Ok(Redirect::to("/admin/trash"))
+    }
+}
+
+#[post("/authors/<id>/restore")]
+async fn restore_author(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match author_route::restore_author(db, &actor, id).await? {
+        0 => Err(ApiError::new(Status::NotFound, format!("No trashed author with id {}", id)).into()),
+        _ => Ok(Redirect::to("/admin/trash"))
+    }
+}
+
+#[post("/users/<id>/restore")]
+async fn restore_user(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match user_route::restore_user(db, &actor, id).await? {
+        0 => Err(ApiError::new(Status::NotFound, format!("No trashed user with id {}", id)).into()),
+        _ => Ok(Redirect::to("/admin/trash"))
+    }
+}
+
+#[post("/comments/<id>/restore")]
+async fn restore_comment(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    _admin: AdminUser,
+    id: i32
+) -> Result<Redirect, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match comment_route::restore_comment(db, &actor, id).await? {
+        0 => Err(ApiError::new(Status::NotFound, format!("No trashed comment with id {}", id)).into()),
+        _ => Ok(Redirect::to("/admin/trash"))
+    }
+}
+
+/// Audit entry with the display name of its actor.
+#[derive(Serialize)]
+struct AuditRow {
+    #[serde(flatten)]
+    entry: audit_log::Model,
+    actor: Option<String>
+}
+
+/// Filters of the audit page, kept as entered so the form and the page links can repeat them.
+#[derive(Serialize, Default)]
+struct AuditFilters {
+    entity_type: String,
+    entity_id: String,
+    actor_id: String,
+    action: String
+}
+
+/// Audit log, newest first, filtered by any of the entity, the actor and the action.
+#[get("/audit?<entity_type>&<entity_id>&<actor_id>&<action>&<page>")]
+async fn list_audit_log(
+    db: &State<DatabaseConnection>,
+    admin: AdminUser,
+    entity_type: Option<String>,
+    entity_id: Option<i32>,
+    actor_id: Option<i32>,
+    action: Option<String>,
+    page: Option<u64>
+) -> Result<Template, AdminError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let mut query = AuditLog::find().order_by_desc(audit_log::Column::Id);
+    let mut filters = AuditFilters::default();
+
+    if let Some(entity_type) = search_term(&entity_type) {
+        query = query.filter(audit_log::Column::EntityType.eq(entity_type.clone()));
+        filters.entity_type = entity_type;
+    }
+    if let Some(entity_id) = entity_id {
+        query = query.filter(audit_log::Column::EntityId.eq(entity_id));
+        filters.entity_id = entity_id.to_string();
+    }
+    if let Some(actor_id) = actor_id {
+        query = query.filter(audit_log::Column::ActorId.eq(actor_id));
+        filters.actor_id = actor_id.to_string();
+    }
+    if let Some(action) = search_term(&action) {
+        query = query.filter(audit_log::Column::Action.eq(action.clone()));
+        filters.action = action;
+    }
+
+    let entries: Listing<audit_log::Model> = fetch_page(db, query, None, page).await?;
+
+    let actor_ids = entries.items.iter().filter_map(|entry| entry.actor_id).collect::<Vec<i32>>();
+    let actors = User::find()
+        .filter(user::Column::Id.is_in(actor_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user.display_name))
+        .collect::<BTreeMap<i32, String>>();
+
+    let entries = Listing {
+        items: entries.items.into_iter().map(|entry| AuditRow {
+            actor: entry.actor_id.and_then(|id| actors.get(&id).cloned()),
+            entry
+        }).collect::<Vec<AuditRow>>(),
+        q: entries.q,
+        page: entries.page,
+        pages: entries.pages,
+        total: entries.total
+    };
+
+    Ok(Template::render("admin/audit", context! {
+        admin: admin.display_name,
+        entries,
+        filters,
+        entity_types: audit::ENTITY_TYPES,
+        actions: audit::ACTIONS
+    }))
+}
+
+/// Visitors without an admin session get the login form in place of the page they asked for.
+#[catch(401)]
+fn unauthorized() -> Template {
+    Template::render("admin/login", context! {})
+}
+
+#[catch(default)]
+fn admin_error(status: Status, _request: &Request) -> AdminError {
+    AdminError(ApiError::new(status, status.reason_lossy().to_string()))
+}
+
+pub fn get_all_admin_methods() -> Vec<rocket::Route> {
+    routes![
+        login_page,
+        login,
+        logout,
+        dashboard,
+        list_books,
+        new_book,
+        edit_book,
+        create_book,
+        update_book,
+        delete_book,
+        restore_book,
+        rename_chapter,
+        delete_chapter,
+        list_authors,
+        new_author,
+        edit_author,
+        create_author,
+        update_author,
+        delete_author,
+        restore_author,
+        list_genres,
+        create_genre,
+        rename_genre,
+        delete_genre,
+        list_users,
+        set_user_role,
+        delete_user,
+        restore_user,
+        list_comments,
+        delete_comment,
+        restore_comment,
+        list_reviews,
+        delete_review,
+        trash,
+        list_audit_log
+    ]
+}
+
+pub fn get_all_admin_catchers() -> Vec<rocket::Catcher> {
+    catchers![unauthorized, admin_error]
+}
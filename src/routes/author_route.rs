@@ -1,118 +1,534 @@
+use rocket::form::Form;
 use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
 
-use rocket::response::status;
 use rocket::http::Status;
 
 use rocket::State;
+use chrono::Utc;
 
-use crate::entities::{author::Model, author::ActiveModel};
-use crate::entities::prelude::Author;
+use crate::audit::{self, Actor};
+use crate::auth::AdminUser;
+use crate::error::ApiError;
+use crate::images::{self, ImageResponse, ImageSize, ImageUpload};
+use crate::entities::{author::Model, author::ActiveModel, author::Column};
+use crate::entities::prelude::{Author, AuthorFollow, Book, BookAuthor, BookRate, Chapter};
+use crate::entities::{author_follow, book_author, book_rate, chapter};
 
-use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    prelude::{DateTime, DbErr}, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    PaginatorTrait, QueryFilter, QueryOrder
+};
 
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate, FromForm)]
+pub(crate) struct AuthorData {
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters long"))]
+    #[schema(example = "Ursula")]
+    first_name: String,
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters long"))]
+    #[schema(example = "Le Guin")]
+    last_name: String,
+    #[validate(length(max = 10000, message = "must be at most 10000 characters long"))]
+    biography: String
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct AuthorWithAvatarUrl {
+    id: i32,
+    first_name: String,
+    last_name: String,
+    biography: String,
+    rating: f32,
+    /// `/api/v1/author/<id>/avatar`, absent when the author has no avatar
+    avatar_url: Option<String>,
+    created_at: DateTime,
+    updated_at: DateTime
+}
+
+impl From<Model> for AuthorWithAvatarUrl {
+    fn from(author: Model) -> AuthorWithAvatarUrl {
+        AuthorWithAvatarUrl {
+            avatar_url: images::image_url(format!("/api/v1/author/{}/avatar", author.id), &author.avatar),
+            id: author.id,
+            first_name: author.first_name,
+            last_name: author.last_name,
+            biography: author.biography,
+            rating: author.rating,
+            created_at: author.created_at,
+            updated_at: author.updated_at
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct BookRatingPoint {
+    book_id: i32,
+    title: String,
+    year: i32,
+    rating: f32,
+    rates: usize
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct AuthorStatistics {
+    author_id: i32,
+    #[schema(example = 4.2)]
+    rating: f32,
+    books: usize,
+    chapters: u64,
+    views: i64,
+    followers: u64,
+    /// Rating of every book in publication order
+    rating_trend: Vec<BookRatingPoint>
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    responses(
+        (status = 200, description = "All authors", body = Vec<AuthorWithAvatarUrl>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/")]
 async fn get_all_authors(
     db: &State<DatabaseConnection>
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<AuthorWithAvatarUrl>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let authors = Author::find().all(db).await;
+    let authors = Author::find().filter(Column::DeletedAt.is_null()).all(db).await;
 
     match authors {
-        Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => Ok(Json(result.into_iter().map(AuthorWithAvatarUrl::from).collect())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    params(
+        ("id" = i32, Path, description = "Author id")
+    ),
+    responses(
+        (status = 200, description = "Author with the id, or an empty author with id -1", body = AuthorWithAvatarUrl),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/<id>")]
 async fn get_author_by_id(
     db: &State<DatabaseConnection>,
     id: i32
-) -> Result<Json<Model>, status::Custom<String>> {
+) -> Result<Json<AuthorWithAvatarUrl>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let author = Author::find_by_id(id).one(db).await;
+    let author = Author::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await;
 
     match author {
-        Ok(Some(author)) => Ok(Json(author)),
+        Ok(Some(author)) => Ok(Json(author.into())),
         Ok(None) => {
-            let empty_author = Model {
+            let empty_author = AuthorWithAvatarUrl {
                 id: -1,
                 first_name: String::new(),
                 last_name: String::new(),
                 biography: String::new(),
                 rating: 0.0,
-                avatar: Vec::new(),
+                avatar_url: None,
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
             };
             Ok(Json(empty_author))
         }
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
-#[post("/", data="<author_data>", format="json")]
-async fn create_author(
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    params(
+        ("id" = i32, Path, description = "Author id"),
+        ("size" = Option<String>, Query, description = "One of original (default), small, medium, large")
+    ),
+    responses(
+        (status = 200, description = "Avatar image", content_type = "image/*"),
+        (status = 304, description = "The avatar matches If-None-Match"),
+        (status = 400, description = "Unknown size", body = ApiError),
+        (status = 404, description = "No such author or the author has no avatar", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/<id>/avatar?<size>")]
+async fn get_author_avatar(
     db: &State<DatabaseConnection>,
-    author_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    id: i32,
+    size: Option<&str>
+) -> Result<ImageResponse, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
+    let size = ImageSize::parse(size)?;
 
-    let author:Result<Model, DbErr> = ActiveModel {
-        first_name: ActiveValue::set(author_data.first_name.clone()),
-        last_name: ActiveValue::set(author_data.last_name.clone()),
-        biography: ActiveValue::set(author_data.biography.clone()),
-        rating: ActiveValue::set(0.0),
-        avatar: ActiveValue::set(author_data.avatar.clone()),
-        ..Default::default()
-    }.insert(db).await;
-
-    match author {
-        Ok(_) => Ok(Json(format!("Author {} {} was successfully created", author_data.first_name.clone(), author_data.last_name.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+    match Author::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await {
+        Ok(Some(author)) => images::serve("author", id, author.avatar, size).await,
+        Ok(None) => Err(ApiError::new(Status::NotFound, format!("No author with id {}", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
-#[put("/<id>", data="<author_data>", format="json")]
-async fn update_author(
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    params(
+        ("id" = i32, Path, description = "Author id")
+    ),
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    security(("admin_session" = []), ("session_token" = [])),
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 401, description = "Missing or invalid session", body = ApiError),
+        (status = 403, description = "Admin role required", body = ApiError),
+        (status = 404, description = "No such author", body = ApiError),
+        (status = 413, description = "The image is larger than 5 MiB", body = ApiError),
+        (status = 422, description = "The file is not a supported image", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/<id>/avatar", data = "<upload>")]
+async fn upload_author_avatar(
     db: &State<DatabaseConnection>,
-    author_data: Json<Model>,
+    _admin: AdminUser,
     id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+    upload: Form<ImageUpload<'_>>
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
+    if Author::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await?.is_none() {
+        return Err(ApiError::new(Status::NotFound, format!("No author with id {}", id)));
+    }
+
+    let image = images::normalize(&upload, images::AVATAR_SIDE).await?;
+
     let updated_author = ActiveModel {
         id: ActiveValue::set(id),
-        first_name: ActiveValue::set(author_data.first_name.clone()),
-        last_name: ActiveValue::set(author_data.last_name.clone()),
-        biography: ActiveValue::set(author_data.biography.clone()),
-        rating: ActiveValue::set(author_data.rating),
-        avatar: ActiveValue::set(author_data.avatar.clone()),
+        avatar: ActiveValue::set(image),
         ..Default::default()
     }.update(db).await;
 
     match updated_author {
-        Ok(result) => Ok(Json(format!("Author {} {} was successfully updated", result.first_name.clone(), result.last_name.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(_) => Ok(Json(format!("Avatar of author {} was updated", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    request_body = AuthorData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/", data="<author_data>", format="json")]
+async fn create_author(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    author_data: Json<AuthorData>,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    save_author(db, &actor, None, &author_data).await?;
+
+    Ok(Json(format!("Author {} {} was successfully created", author_data.first_name.clone(), author_data.last_name.clone())))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    params(
+        ("id" = i32, Path, description = "Author id")
+    ),
+    request_body = AuthorData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/<id>", data="<author_data>", format="json")]
+async fn update_author(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    author_data: Json<AuthorData>,
+    id: i32,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let result = save_author(db, &actor, Some(id), &author_data).await?;
+
+    Ok(Json(format!("Author {} {} was successfully updated", result.first_name.clone(), result.last_name.clone())))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    params(
+        ("id" = i32, Path, description = "Author id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/<id>")]
 async fn delete_author(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let deleted_author = ActiveModel {
-        id: ActiveValue::set(id),
+    let rows_affected = remove_author(db, &actor, id).await?;
+
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/author",
+    tag = "author",
+    params(
+        ("id" = i32, Path, description = "Author id")
+    ),
+    responses(
+        (status = 200, description = "Author statistics", body = AuthorStatistics),
+        (status = 404, description = "No such author", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/<id>/statistics")]
+async fn get_author_statistics(
+    db: &State<DatabaseConnection>,
+    id: i32
+) -> Result<Json<AuthorStatistics>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let author = match Author::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await {
+        Ok(Some(author)) => author,
+        Ok(None) => return Err(ApiError::new(Status::NotFound, format!("No author with id {}", id))),
+        Err(err) => return Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    };
+
+    let books = author.find_related(Book)
+        .filter(crate::entities::book::Column::DeletedAt.is_null())
+        .order_by_asc(crate::entities::book::Column::Year)
+        .all(db)
+        .await;
+
+    let books = match books {
+        Ok(books) => books,
+        Err(err) => return Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    };
+
+    let book_ids = books.iter().map(|book| book.id).collect::<Vec<i32>>();
+
+    let chapters = Chapter::find()
+        .filter(chapter::Column::BookId.is_in(book_ids.clone()))
+        .count(db)
+        .await;
+
+    let rates = BookRate::find()
+        .filter(book_rate::Column::BookId.is_in(book_ids))
+        .all(db)
+        .await;
+
+    let followers = AuthorFollow::find()
+        .filter(author_follow::Column::AuthorId.eq(author.id))
+        .count(db)
+        .await;
+
+    match (chapters, rates, followers) {
+        (Ok(chapters), Ok(rates), Ok(followers)) => {
+            let rating_trend = books.iter()
+                .map(|book| BookRatingPoint {
+                    book_id: book.id,
+                    title: book.title.clone(),
+                    year: book.year,
+                    rating: book.rating,
+                    rates: rates.iter().filter(|rate| rate.book_id == book.id).count()
+                })
+                .collect::<Vec<BookRatingPoint>>();
+
+            Ok(Json(AuthorStatistics {
+                author_id: author.id,
+                rating: author.rating,
+                books: books.len(),
+                chapters,
+                views: books.iter().map(|book| book.views as i64).sum(),
+                followers,
+                rating_trend
+            }))
+        },
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        }
+    }
+}
+
+/// Creates an author, or updates the one with `id`. Shared by the JSON API and the admin panel.
+pub(crate) async fn save_author(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: Option<i32>,
+    author_data: &AuthorData
+) -> Result<Model, ApiError> {
+    author_data.validate()?;
+
+    let before = match id {
+        Some(id) => Author::find_by_id(id).one(db).await?,
+        None => None
+    };
+
+    let author:Result<Model, DbErr> = match id {
+        Some(id) => ActiveModel {
+            id: ActiveValue::set(id),
+            first_name: ActiveValue::set(author_data.first_name.clone()),
+            last_name: ActiveValue::set(author_data.last_name.clone()),
+            biography: ActiveValue::set(author_data.biography.clone()),
+            ..Default::default()
+        }.update(db).await,
+        None => ActiveModel {
+            first_name: ActiveValue::set(author_data.first_name.clone()),
+            last_name: ActiveValue::set(author_data.last_name.clone()),
+            biography: ActiveValue::set(author_data.biography.clone()),
+            rating: ActiveValue::set(0.0),
+            avatar: ActiveValue::set(Vec::new()),
+            ..Default::default()
+        }.insert(db).await
+    };
+
+    let author = author.map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    let action = if before.is_some() { audit::UPDATE } else { audit::CREATE };
+    let before = before.as_ref().map(audit::snapshot);
+    audit::record(db, actor, action, "author", author.id, before, Some(audit::snapshot(&author))).await?;
+
+    Ok(author)
+}
+
+/// Moves an author to the trash, the retention purge removes them for good.
+pub(crate) async fn remove_author(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_author_deleted_at(db, actor, id, Some(Utc::now().naive_utc())).await
+}
+
+/// Takes an author out of the trash.
+pub(crate) async fn restore_author(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_author_deleted_at(db, actor, id, None).await
+}
+
+async fn set_author_deleted_at(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: i32,
+    deleted_at: Option<DateTime>
+) -> Result<u64, ApiError> {
+    let updated_authors = Author::update_many()
+        .col_expr(Column::DeletedAt, Expr::value(deleted_at))
+        .filter(Column::Id.eq(id))
+        .filter(match deleted_at {
+            Some(_) => Column::DeletedAt.is_null(),
+            None => Column::DeletedAt.is_not_null()
+        })
+        .exec(db)
+        .await;
+
+    match updated_authors {
+        Ok(result) => {
+            if result.rows_affected > 0 {
+                let author = Author::find_by_id(id).one(db).await?;
+                audit::record_trash(db, actor, "author", id, deleted_at.is_some(), author).await?;
+            }
+
+            Ok(result.rows_affected)
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+/// Recalculates `author.rating` as the average of every rate given to the author's books,
+/// so books with more rates weigh more. Books in the trash are left out.
+pub async fn update_author_rating(db: &DatabaseConnection, author_id: i32) -> Result<f32, DbErr> {
+    let book_ids = BookAuthor::find()
+        .inner_join(Book)
+        .filter(book_author::Column::AuthorId.eq(author_id))
+        .filter(crate::entities::book::Column::DeletedAt.is_null())
+        .all(db)
+        .await?
+        .iter()
+        .map(|book_author| book_author.book_id).collect::<Vec<i32>>();
+
+    let rates = BookRate::find()
+        .filter(book_rate::Column::BookId.is_in(book_ids))
+        .all(db)
+        .await?
+        .iter()
+        .map(|rate| rate.rate).collect::<Vec<i32>>();
+
+    let new_rating = if rates.is_empty() {
+        0.0
+    } else {
+        rates.iter().sum::<i32>() as f32 / rates.len() as f32
+    };
+
+    ActiveModel {
+        id: ActiveValue::set(author_id),
+        rating: ActiveValue::set(new_rating),
         ..Default::default()
-    }.delete(db).await;
+    }.update(db).await?;
 
-    match deleted_author {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+    Ok(new_rating)
+}
+
+/// Recalculates the rating of every author linked to the book.
+pub(crate) async fn update_book_authors_rating(db: &DatabaseConnection, book_id: i32) -> Result<(), DbErr> {
+    let book_authors = BookAuthor::find()
+        .filter(book_author::Column::BookId.eq(book_id))
+        .all(db)
+        .await?;
+
+    for book_author in book_authors {
+        update_author_rating(db, book_author.author_id).await?;
     }
+
+    Ok(())
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_authors,
+        get_author_by_id,
+        get_author_avatar,
+        upload_author_avatar,
+        create_author,
+        update_author,
+        delete_author,
+        get_author_statistics
+    ),
+    components(schemas(AuthorData, AuthorWithAvatarUrl, BookRatingPoint, AuthorStatistics))
+)]
+pub struct AuthorApi;
+
 pub fn get_all_methods() -> Vec<rocket::Route> {
-    routes![get_all_authors, get_author_by_id, create_author, update_author, delete_author]
+    routes![
+        get_all_authors,
+        get_author_by_id,
+        get_author_avatar,
+        upload_author_avatar,
+        create_author,
+        update_author,
+        delete_author,
+        get_author_statistics
+    ]
 }
\ No newline at end of file
@@ -1,53 +1,127 @@
-use std::fs::File;
-use std::io::Read;
 use rocket::tokio::fs;
 
+use chrono::{Datelike, Utc};
+
+use rocket::form::Form;
 use rocket::serde::json::Json;
+use rocket::serde::json::serde_json::json;
 
-use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
+use sea_orm::sea_query::{Expr, SimpleExpr};
+use sea_orm::{ActiveEnum, ColumnTrait, Iterable, Order, QueryFilter, QueryOrder, Select};
 use serde::{Deserialize, Serialize};
 
-use rocket::response::status;
 use rocket::http::Status;
 
 use rocket::State;
-use utoipa::ToSchema;
-
-use crate::entities::prelude::{Book, BookRate, Genre};
+use utoipa::{OpenApi, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::audit::{self, Actor};
+use crate::auth::AdminUser;
+use crate::error::ApiError;
+use crate::images::{self, ImageResponse, ImageSize, ImageUpload};
+use crate::entities::prelude::{Book, BookRate, BookStatusChange, Genre};
 use crate::entities::book::{ActiveModel, Model, Column};
-use crate::entities::{book_author, book_genre, book_rate};
+use crate::entities::sea_orm_active_enums::BookStatus;
+use crate::entities::{book_author, book_genre, book_rate, book_status_change};
+use crate::routes::{author_route, review_route};
+use crate::views::{ViewCounter, Viewer};
 
-use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, ModelTrait};
+use sea_orm::{prelude::{DateTime, DbErr}, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, ModelTrait};
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BookWithGenresAndRates {
     pub id: i32,
     pub title: String,
     pub description: String,
-    pub cover: Vec<u8>,
     pub rating: f32,
     pub year: i32,
     pub views: i32,
-    pub status: String,
+    pub status: BookStatus,
+    /// `/api/v1/book/<id>/cover`, absent when the book has no cover
+    pub cover_url: Option<String>,
     pub genres: Vec<String>,
-    pub rates: usize
+    pub rates: usize,
+    pub created_at: DateTime,
+    pub updated_at: DateTime
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate, FromForm)]
+pub(crate) struct BookData {
+    #[validate(length(min = 1, max = 200, message = "must be 1 to 200 characters long"))]
+    #[schema(example = "The Left Hand of Darkness")]
+    title: String,
+    #[validate(length(max = 10000, message = "must be at most 10000 characters long"))]
+    description: String,
+    #[validate(custom = "validate_year")]
+    #[schema(example = 1969)]
+    year: i32,
+    /// One of ongoing, completed, hiatus, dropped, announced. Completed and dropped books can only
+    /// become ongoing again, and no book becomes announced once it has another status.
+    #[validate(custom = "validate_status")]
+    #[schema(example = "completed")]
+    status: String
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BookGenreData {
+    book_id: i32,
+    genre_id: i32
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BookAuthorData {
+    book_id: i32,
+    author_id: i32
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct BookRateData {
+    book_id: i32,
+    user_id: i32,
+    #[validate(range(min = 1, max = 5, message = "must be between 1 and 5"))]
+    #[schema(example = 5, minimum = 1, maximum = 5)]
+    rate: i32
 }
 
 #[utoipa::path(
-    context_path = "/book",
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("status" = Option<String>, Query, description = "Comma separated statuses, e.g. ongoing,hiatus"),
+        ("sort" = Option<String>, Query, description = "One of id (default), newest, title, rating, views, status")
+    ),
     responses(
         (status = 200, description = "All books", body = Vec<BookWithGenresAndRates>),
-        (status = 500, description = "No books", body = String)
+        (status = 400, description = "Unknown status or sort", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
     ),
 )]
-#[get("/")]
+#[get("/?<status>&<sort>")]
 async fn get_all_books(
-    db: &State<DatabaseConnection>
-) -> Result<Json<Vec<BookWithGenresAndRates>>, status::Custom<String>> {
+    db: &State<DatabaseConnection>,
+    status: Option<String>,
+    sort: Option<String>
+) -> Result<Json<Vec<BookWithGenresAndRates>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
     let mut books: Vec<BookWithGenresAndRates> = Vec::new();
 
-    let query = Book::find().all(db).await;
+    let query = filter_statuses(Book::find().filter(Column::DeletedAt.is_null()), status)?;
+
+    let query = match sort.as_deref().unwrap_or("id") {
+        "id" => query.order_by_asc(Column::Id),
+        "newest" => query.order_by_desc(Column::CreatedAt).order_by_desc(Column::Id),
+        "title" => query.order_by_asc(Column::Title),
+        "rating" => query.order_by_desc(Column::Rating).order_by_asc(Column::Id),
+        "views" => query.order_by_desc(Column::Views).order_by_asc(Column::Id),
+        "status" => query.order_by(status_order(), Order::Asc).order_by_asc(Column::Title),
+        other => return Err(ApiError::new(
+            Status::BadRequest,
+            format!("Unknown sort {}, expected one of id, newest, title, rating, views, status", other)
+        ))
+    };
+
+    let query = query.all(db).await;
 
     match query {
         Ok(result) => {
@@ -65,55 +139,55 @@ async fn get_all_books(
                     .unwrap()
                     .len();
 
-                let mut book = BookWithGenresAndRates {
+                let book = BookWithGenresAndRates {
                     id: result_book.id,
                     title: result_book.title.clone(),
                     description: result_book.description.clone(),
-                    cover: Vec::new(),
                     rating: result_book.rating,
                     year: result_book.year,
                     views: result_book.views,
-                    status: result_book.status.clone(),
+                    status: result_book.status,
+                    cover_url: cover_url(&result_book).await,
                     genres,
-                    rates
-                };
-
-                let filepath = format!("storage/{}/cover.png", result_book.id);
-                let file = File::options().read(true).open(filepath.clone());
-
-                match file {
-                    Ok(mut res) => {
-                        let metadata = fs::metadata(filepath.clone()).await.expect("Can not read metadata");
-                        let mut buf = vec![0; metadata.len() as usize];
-                        let _ = res.read(&mut buf).expect("Buffer overflow");
-
-                        book.cover = buf;
-                    }
-                    Err(_) => {
-                        book.cover = Vec::new();
-                    }
+                    rates,
+                    created_at: result_book.created_at,
+                    updated_at: result_book.updated_at
                 };
-                
 
                 books.push(book);
             };
 
             return Ok(Json(books));
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("id" = i32, Path, description = "Book id")
+    ),
+    responses(
+        (status = 200, description = "Book with the id", body = BookWithGenresAndRates),
+        (status = 500, description = "No such book or database error", body = ApiError)
+    ),
+)]
 #[get("/<id>")]
 async fn get_book_by_id(
     db: &State<DatabaseConnection>,
+    views: &State<ViewCounter>,
+    viewer: Viewer,
     id: i32
-) -> Result<Json<BookWithGenresAndRates>, status::Custom<String>> {
+) -> Result<Json<BookWithGenresAndRates>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let query = Book::find_by_id(id).one(db).await;
+    let query = Book::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await;
 
     match query {
         Ok(Some(model)) => {
+            views.view(viewer, model.id);
+
             let genres = model.find_related(Genre)
                 .all(db)
                 .await
@@ -127,127 +201,251 @@ async fn get_book_by_id(
                 .unwrap()
                 .len();
             
-            let mut book = BookWithGenresAndRates {
+            let book = BookWithGenresAndRates {
                 id: model.id,
                 title: model.title.clone(),
                 description: model.description.clone(),
-                cover: Vec::new(),
                 rating: model.rating,
                 year: model.year,
                 views: model.views,
-                status: model.status.clone(),
+                status: model.status,
+                cover_url: cover_url(&model).await,
                 genres,
-                rates
-            };
-
-            let filepath = format!("storage/{}/cover.png", model.id);
-            let file = File::options().read(true).open(filepath.clone());
-            
-            match file {
-                Ok(mut res) => {
-                    let metadata = fs::metadata(filepath.clone()).await.expect("Can not read metadata");
-                    let mut buf = vec![0; metadata.len() as usize];
-                    let _ = res.read(&mut buf).expect("Buffer overflow");
-
-                    book.cover = buf;
-                }
-                Err(_) => {
-                    book.cover = Vec::new();
-                }
+                rates,
+                created_at: model.created_at,
+                updated_at: model.updated_at
             };
 
             return Ok(Json(book));
         },
-        Ok(None) => Err(status::Custom(Status::InternalServerError, "No such book".to_string())),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(None) => Err(ApiError::new(Status::InternalServerError, "No such book".to_string())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
-#[post("/", data="<book_data>", format="json")]
-async fn create_book(
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("id" = i32, Path, description = "Book id"),
+        ("size" = Option<String>, Query, description = "One of original (default), small, medium, large")
+    ),
+    responses(
+        (status = 200, description = "Cover image", content_type = "image/*"),
+        (status = 304, description = "The cover matches If-None-Match"),
+        (status = 400, description = "Unknown size", body = ApiError),
+        (status = 404, description = "No such book or the book has no cover", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/<id>/cover?<size>")]
+async fn get_book_cover(
     db: &State<DatabaseConnection>,
-    book_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    id: i32,
+    size: Option<&str>
+) -> Result<ImageResponse, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
+    let size = ImageSize::parse(size)?;
 
-    let book:Result<Model, DbErr> = ActiveModel {
-        title: ActiveValue::set(book_data.title.clone()),
-        description: ActiveValue::set(book_data.description.clone()),
-        cover: ActiveValue::set(book_data.cover.clone()),
-        rating: ActiveValue::set(0.0),
-        year: ActiveValue::set(book_data.year),
-        views: ActiveValue::set(0),
-        status: ActiveValue::set(book_data.status.clone()),
-        ..Default::default()
-    }.insert(db).await;
-
-    match book {
-        Ok(_) => Ok(Json(format!("Book {} was successfully created", book_data.title.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+    match Book::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await {
+        Ok(Some(book)) => images::serve("book", id, load_cover(book).await, size).await,
+        Ok(None) => Err(ApiError::new(Status::NotFound, format!("No book with id {}", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
-#[put("/<id>", data="<book_data>", format="json")]
-async fn update_book(
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("id" = i32, Path, description = "Book id")
+    ),
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    security(("admin_session" = []), ("session_token" = [])),
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 401, description = "Missing or invalid session", body = ApiError),
+        (status = 403, description = "Admin role required", body = ApiError),
+        (status = 404, description = "No such book", body = ApiError),
+        (status = 413, description = "The image is larger than 5 MiB", body = ApiError),
+        (status = 422, description = "The file is not a supported image", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/<id>/cover", data = "<upload>")]
+async fn upload_book_cover(
     db: &State<DatabaseConnection>,
-    book_data: Json<Model>,
+    _admin: AdminUser,
     id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+    upload: Form<ImageUpload<'_>>
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
+    if Book::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await?.is_none() {
+        return Err(ApiError::new(Status::NotFound, format!("No book with id {}", id)));
+    }
+
+    let image = images::normalize(&upload, images::COVER_SIDE).await?;
+
     let updated_book = ActiveModel {
         id: ActiveValue::set(id),
-        title: ActiveValue::set(book_data.title.clone()),
-        description: ActiveValue::set(book_data.description.clone()),
-        cover: ActiveValue::set(book_data.cover.clone()),
-        rating: ActiveValue::set(book_data.rating),
-        year: ActiveValue::set(book_data.year),
-        views: ActiveValue::set(0),
-        status: ActiveValue::set(book_data.status.clone()),
+        cover: ActiveValue::set(image),
         ..Default::default()
     }.update(db).await;
 
     match updated_book {
-        Ok(result) => Ok(Json(format!("Book {} was successfully updated", result.title.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(_) => Ok(Json(format!("Cover of book {} was updated", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    request_body = BookData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/", data="<book_data>", format="json")]
+async fn create_book(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    book_data: Json<BookData>,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    save_book(db, &actor, None, &book_data).await?;
+
+    Ok(Json(format!("Book {} was successfully created", book_data.title.clone())))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("id" = i32, Path, description = "Book id")
+    ),
+    request_body = BookData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 404, description = "No such book", body = ApiError),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/<id>", data="<book_data>", format="json")]
+async fn update_book(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    book_data: Json<BookData>,
+    id: i32,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let result = save_book(db, &actor, Some(id), &book_data).await?;
+
+    Ok(Json(format!("Book {} was successfully updated", result.title.clone())))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("id" = i32, Path, description = "Book id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/<id>")]
 async fn delete_book(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let deleted_book = ActiveModel {
-        id: ActiveValue::set(id),
-        ..Default::default()
-    }.delete(db).await;
+    let rows_affected = remove_book(db, &actor, id).await?;
 
-    match deleted_book {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-    }
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
 }
 
-#[get("/get-ids")]
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("id" = i32, Path, description = "Book id")
+    ),
+    responses(
+        (status = 200, description = "Status changes of the book, oldest first", body = Vec<BookStatusChange>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/<id>/status-history")]
+async fn get_status_history(
+    db: &State<DatabaseConnection>,
+    id: i32
+) -> Result<Json<Vec<book_status_change::Model>>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let changes = BookStatusChange::find()
+        .filter(book_status_change::Column::BookId.eq(id))
+        .order_by_asc(book_status_change::Column::ChangedAt)
+        .order_by_asc(book_status_change::Column::Id)
+        .all(db)
+        .await?;
+
+    Ok(Json(changes))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("status" = Option<String>, Query, description = "Comma separated statuses, e.g. ongoing,hiatus")
+    ),
+    responses(
+        (status = 200, description = "Ids of all books ordered by rating", body = Vec<i32>),
+        (status = 400, description = "Unknown status", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/get-ids?<status>")]
 async fn get_ids(
-    db: &State<DatabaseConnection>
-) -> Result<Json<Vec<i32>>, status::Custom<String>> {
+    db: &State<DatabaseConnection>,
+    status: Option<String>
+) -> Result<Json<Vec<i32>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let books = Book::find().order_by_asc(Column::Rating).all(db).await.expect("Error");
+    let books = filter_statuses(Book::find().filter(Column::DeletedAt.is_null()), status)?
+        .order_by_asc(Column::Rating)
+        .all(db)
+        .await
+        .expect("Error");
     let ids = books.iter().map(|book| book.id).collect::<Vec<i32>>();
 
     Ok(Json(ids))
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    request_body = BookGenreData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/genre", data="<book_genre_data>", format="json")]
 pub async fn add_genre_to_book(
     db: &State<DatabaseConnection>,
-    book_genre_data: Json<book_genre::Model>
-) -> Result<Json<String>, status::Custom<String>> {
+    actor: Actor,
+    book_genre_data: Json<BookGenreData>
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let book_genre = book_genre::ActiveModel {
@@ -257,17 +455,35 @@ pub async fn add_genre_to_book(
     }.insert(db).await;
 
     match book_genre {
-        Ok(_) => Ok(Json(format!("Book genre was successfully created"))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => {
+            let after = json!({"genre_id": result.genre_id});
+            audit::record(db, &actor, audit::UPDATE, "book", result.book_id, Some(json!({})), Some(after)).await?;
+
+            Ok(Json("Book genre was successfully created".to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("book_id" = i32, Path, description = "Book id"),
+        ("genre_id" = i32, Path, description = "Genre id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/genre/<book_id>/<genre_id>")]
 pub async fn delete_genre_from_book(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     book_id: i32,
     genre_id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let book_genre = book_genre::ActiveModel {
@@ -277,16 +493,33 @@ pub async fn delete_genre_from_book(
     }.delete(db).await;
 
     match book_genre {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => {
+            if result.rows_affected > 0 {
+                let before = json!({"genre_id": genre_id});
+                audit::record(db, &actor, audit::UPDATE, "book", book_id, Some(before), Some(json!({}))).await?;
+            }
+
+            Ok(Json(format!("Number of deleted entries: {}", result.rows_affected)))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    request_body = BookAuthorData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/author", data="<book_author_data>", format="json")]
 pub async fn add_author_to_book(
     db: &State<DatabaseConnection>,
-    book_author_data: Json<book_author::Model>
-) -> Result<Json<String>, status::Custom<String>> {
+    actor: Actor,
+    book_author_data: Json<BookAuthorData>
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let book_author = book_author::ActiveModel {
@@ -296,17 +529,38 @@ pub async fn add_author_to_book(
     }.insert(db).await;
 
     match book_author {
-        Ok(_) => Ok(Json(format!("Book author was successfully created"))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => match author_route::update_author_rating(db, result.author_id).await {
+            Ok(_) => {
+                let after = json!({"author_id": result.author_id});
+                audit::record(db, &actor, audit::UPDATE, "book", result.book_id, Some(json!({})), Some(after)).await?;
+
+                Ok(Json("Book author was successfully created".to_string()))
+            },
+            Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("book_id" = i32, Path, description = "Book id"),
+        ("author_id" = i32, Path, description = "Author id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/author/<book_id>/<author_id>")]
 pub async fn delete_author_from_book(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     book_id: i32,
     author_id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let book_author = book_author::ActiveModel {
@@ -316,24 +570,39 @@ pub async fn delete_author_from_book(
     }.delete(db).await;
 
     match book_author {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => match author_route::update_author_rating(db, author_id).await {
+            Ok(_) => {
+                if result.rows_affected > 0 {
+                    let before = json!({"author_id": author_id});
+                    audit::record(db, &actor, audit::UPDATE, "book", book_id, Some(before), Some(json!({}))).await?;
+                }
+
+                Ok(Json(format!("Number of deleted entries: {}", result.rows_affected)))
+            },
+            Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    request_body = BookRateData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Rate is not between 1 and 5", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/rate", data="<book_rate_data>", format="json")]
 pub async fn add_rate_to_book(
     db: &State<DatabaseConnection>,
-    book_rate_data: Json<book_rate::Model>
-) -> Result<Json<String>, status::Custom<String>> {
+    book_rate_data: Json<BookRateData>
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    if book_rate_data.rate < 1 || book_rate_data.rate > 5 {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            "Saving rate error: Invalid rate value".to_string()
-        ))
-    }
+    book_rate_data.validate()?;
 
     let book_rate = book_rate::ActiveModel {
         book_id: ActiveValue::set(book_rate_data.book_id.clone()),
@@ -344,44 +613,35 @@ pub async fn add_rate_to_book(
 
     match book_rate {
         Ok(result) => {
-            let book_rates = BookRate::find()
-                .filter(book_rate::Column::BookId.eq(result.book_id))
-                .all(db)
-                .await
-                .unwrap()
-                .iter()
-                .map(|rate| rate.rate.clone()).collect::<Vec<i32>>();
+            let updated_rating = update_book_rating(db, result.book_id).await;
 
-            let new_rating = book_rates.iter().sum::<i32>() as f32 / book_rates.len() as f32;
-
-            let upated_book = ActiveModel {
-                id: ActiveValue::set(result.book_id),
-                rating: ActiveValue::set(new_rating),
-                ..Default::default()
-            }.update(db).await;
-
-            match upated_book {
+            match updated_rating {
                 Ok(_) => Ok(Json(format!("Book rate was successfully created"))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
             }
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    request_body = BookRateData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 422, description = "Rate is not between 1 and 5", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[put("/rate", data="<book_rate_data>", format="json")]
 pub async fn update_rate_to_book(
     db: &State<DatabaseConnection>,
-    book_rate_data: Json<book_rate::Model>
-) -> Result<Json<String>, status::Custom<String>> {
+    book_rate_data: Json<BookRateData>
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    if book_rate_data.rate < 1 || book_rate_data.rate > 5 {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            "Saving rate error: Invalid rate value".to_string()
-        ))
-    }
+    book_rate_data.validate()?;
 
     let book_rate = book_rate::ActiveModel {
         book_id: ActiveValue::set(book_rate_data.book_id.clone()),
@@ -392,39 +652,51 @@ pub async fn update_rate_to_book(
 
     match book_rate {
         Ok(result) => {
-            let book_rates = BookRate::find()
-                .filter(book_rate::Column::BookId.eq(result.book_id))
-                .all(db)
-                .await
-                .unwrap()
-                .iter()
-                .map(|rate| rate.rate.clone()).collect::<Vec<i32>>();
-
-            let new_rating = book_rates.iter().sum::<i32>() as f32 / book_rates.len() as f32;
+            if let Err(err) = review_route::sync_review_rate(db, result.book_id, result.user_id, result.rate).await {
+                return Err(ApiError::new(Status::InternalServerError, err.to_string()));
+            }
 
-            let upated_book = ActiveModel {
-                id: ActiveValue::set(result.book_id),
-                rating: ActiveValue::set(new_rating),
-                ..Default::default()
-            }.update(db).await;
+            let updated_rating = update_book_rating(db, result.book_id).await;
 
-            match upated_book {
+            match updated_rating {
                 Ok(_) => Ok(Json(format!("Book rate was successfully created"))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
             }
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/book",
+    tag = "book",
+    params(
+        ("book_id" = i32, Path, description = "Book id"),
+        ("user_id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Deletion message", body = String),
+        (status = 409, description = "The rate belongs to a review", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/rate/<book_id>/<user_id>")]
 pub async fn delete_rate_from_book(
     db: &State<DatabaseConnection>,
     book_id: i32,
     user_id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
+    match review_route::has_review(db, book_id, user_id).await {
+        Ok(true) => return Err(ApiError::new(
+            Status::Conflict,
+            format!("Rate of book {} by user {} belongs to a review, delete the review instead", book_id, user_id)
+        )),
+        Ok(false) => (),
+        Err(err) => return Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+
     let book_rate = book_rate::ActiveModel {
         book_id: ActiveValue::set(book_id.clone()),
         user_id: ActiveValue::set(user_id.clone()),
@@ -433,35 +705,270 @@ pub async fn delete_rate_from_book(
 
     match book_rate {
         Ok(_) => {
-            let book_rates = BookRate::find()
-                .filter(book_rate::Column::BookId.eq(book_id))
-                .all(db)
-                .await
-                .unwrap()
-                .iter()
-                .map(|rate| rate.rate.clone()).collect::<Vec<i32>>();
+            let updated_rating = update_book_rating(db, book_id).await;
 
-            let new_rating = book_rates.iter().sum::<i32>() as f32 / book_rates.len() as f32;
+            match updated_rating {
+                Ok(_) => Ok(Json(format!("Book rate was successfully deleted"))),
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+            }
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
 
-            let upated_book = ActiveModel {
-                id: ActiveValue::set(book_id),
-                rating: ActiveValue::set(new_rating),
-                ..Default::default()
-            }.update(db).await;
+/// Cover stored in the database, or the `storage/<id>/cover.png` file of older books.
+async fn load_cover(book: Model) -> Vec<u8> {
+    if !book.cover.is_empty() {
+        return book.cover;
+    }
 
-            match upated_book {
-                Ok(_) => Ok(Json(format!("Book rate was successfully deleted"))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+    fs::read(format!("storage/{}/cover.png", book.id)).await.unwrap_or_default()
+}
+
+async fn cover_url(book: &Model) -> Option<String> {
+    let has_cover = !book.cover.is_empty() || fs::metadata(format!("storage/{}/cover.png", book.id)).await.is_ok();
+
+    if has_cover { Some(format!("/api/v1/book/{}/cover", book.id)) } else { None }
+}
+
+fn validate_year(year: i32) -> Result<(), ValidationError> {
+    // Books can be announced for the next year
+    let max_year = Utc::now().year() + 1;
+
+    if (0..=max_year).contains(&year) {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("range");
+    error.message = Some(format!("must be between 0 and {}", max_year).into());
+
+    Err(error)
+}
+
+fn validate_status(status: &str) -> Result<(), ValidationError> {
+    if BookStatus::parse(status).is_some() {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("status");
+    error.message = Some(format!("must be one of {}", BookStatus::names().join(", ")).into());
+
+    Err(error)
+}
+
+/// Keeps the books with one of the comma separated `statuses`.
+fn filter_statuses(query: Select<Book>, statuses: Option<String>) -> Result<Select<Book>, ApiError> {
+    let Some(statuses) = statuses else {
+        return Ok(query);
+    };
+
+    let statuses = statuses
+        .split(',')
+        .map(|name| BookStatus::parse(name.trim()).ok_or_else(|| ApiError::new(
+            Status::BadRequest,
+            format!("Unknown status {}, expected one of {}", name.trim(), BookStatus::names().join(", "))
+        )))
+        .collect::<Result<Vec<BookStatus>, ApiError>>()?;
+
+    Ok(query.filter(Column::Status.is_in(statuses)))
+}
+
+/// Statuses in their declared order, the same on every backend. SQLite keeps them as text and
+/// would sort them alphabetically.
+fn status_order() -> SimpleExpr {
+    let mut statuses = BookStatus::iter().enumerate();
+    let (_, first) = statuses.next().expect("there are statuses");
+
+    statuses
+        .fold(Expr::case(Column::Status.eq(first), 0), |order, (index, status)| {
+            order.case(Column::Status.eq(status), index as i32)
+        })
+        .into()
+}
+
+/// Creates a book, or updates the one with `id`. Rating and views are only changed by readers.
+/// Shared by the JSON API and the admin panel.
+pub(crate) async fn save_book(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: Option<i32>,
+    book_data: &BookData
+) -> Result<Model, ApiError> {
+    book_data.validate()?;
+
+    let status = BookStatus::parse(&book_data.status).expect("status is validated");
+
+    let before = match id {
+        Some(id) => match Book::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await? {
+            Some(book) => Some(book),
+            None => return Err(ApiError::new(Status::NotFound, format!("No book with id {}", id)))
+        },
+        None => None
+    };
+
+    if let Some(before) = &before {
+        if !before.status.can_become(status) {
+            return Err(ApiError::field(
+                "status",
+                &format!("can not change from {} to {}", before.status.to_value(), status.to_value())
+            ));
+        }
+    }
+
+    let book:Result<Model, DbErr> = match id {
+        Some(id) => ActiveModel {
+            id: ActiveValue::set(id),
+            title: ActiveValue::set(book_data.title.clone()),
+            description: ActiveValue::set(book_data.description.clone()),
+            year: ActiveValue::set(book_data.year),
+            status: ActiveValue::set(status),
+            ..Default::default()
+        }.update(db).await,
+        None => ActiveModel {
+            title: ActiveValue::set(book_data.title.clone()),
+            description: ActiveValue::set(book_data.description.clone()),
+            cover: ActiveValue::set(Vec::new()),
+            rating: ActiveValue::set(0.0),
+            year: ActiveValue::set(book_data.year),
+            views: ActiveValue::set(0),
+            status: ActiveValue::set(status),
+            ..Default::default()
+        }.insert(db).await
+    };
+
+    let book = book.map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    let from_status = before.as_ref().map(|before| before.status);
+    if from_status != Some(book.status) {
+        record_status_change(db, actor, book.id, from_status, book.status).await?;
+    }
+
+    let action = if before.is_some() { audit::UPDATE } else { audit::CREATE };
+    let before = before.as_ref().map(audit::snapshot);
+    audit::record(db, actor, action, "book", book.id, before, Some(audit::snapshot(&book))).await?;
+
+    Ok(book)
+}
+
+async fn record_status_change(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    book_id: i32,
+    from_status: Option<BookStatus>,
+    to_status: BookStatus
+) -> Result<(), DbErr> {
+    book_status_change::ActiveModel {
+        book_id: ActiveValue::set(book_id),
+        from_status: ActiveValue::set(from_status),
+        to_status: ActiveValue::set(to_status),
+        user_id: ActiveValue::set(actor.user_id),
+        changed_at: ActiveValue::set(Utc::now().naive_utc()),
+        ..Default::default()
+    }.insert(db).await?;
+
+    Ok(())
+}
+
+/// Moves a book to the trash and recomputes the ratings of its authors, which leave out trashed books.
+/// The book, its chapters and their files are removed for good by the retention purge.
+pub(crate) async fn remove_book(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_book_deleted_at(db, actor, id, Some(Utc::now().naive_utc())).await
+}
+
+/// Takes a book out of the trash.
+pub(crate) async fn restore_book(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_book_deleted_at(db, actor, id, None).await
+}
+
+async fn set_book_deleted_at(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: i32,
+    deleted_at: Option<DateTime>
+) -> Result<u64, ApiError> {
+    let updated_books = Book::update_many()
+        .col_expr(Column::DeletedAt, Expr::value(deleted_at))
+        .filter(Column::Id.eq(id))
+        .filter(match deleted_at {
+            Some(_) => Column::DeletedAt.is_null(),
+            None => Column::DeletedAt.is_not_null()
+        })
+        .exec(db)
+        .await;
+
+    match updated_books {
+        Ok(result) => {
+            if let Err(err) = author_route::update_book_authors_rating(db, id).await {
+                return Err(ApiError::new(Status::InternalServerError, err.to_string()));
             }
+
+            if result.rows_affected > 0 {
+                let book = Book::find_by_id(id).one(db).await?;
+                audit::record_trash(db, actor, "book", id, deleted_at.is_some(), book).await?;
+            }
+
+            Ok(result.rows_affected)
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+/// Recalculates `book.rating` from its rates and propagates the change to the book's authors.
+pub async fn update_book_rating(db: &DatabaseConnection, book_id: i32) -> Result<f32, DbErr> {
+    let book_rates = BookRate::find()
+        .filter(book_rate::Column::BookId.eq(book_id))
+        .all(db)
+        .await?
+        .iter()
+        .map(|rate| rate.rate).collect::<Vec<i32>>();
+
+    let new_rating = if book_rates.is_empty() {
+        0.0
+    } else {
+        book_rates.iter().sum::<i32>() as f32 / book_rates.len() as f32
+    };
+
+    ActiveModel {
+        id: ActiveValue::set(book_id),
+        rating: ActiveValue::set(new_rating),
+        ..Default::default()
+    }.update(db).await?;
+
+    author_route::update_book_authors_rating(db, book_id).await?;
+
+    Ok(new_rating)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_books,
+        get_book_by_id,
+        get_book_cover,
+        upload_book_cover,
+        create_book,
+        update_book,
+        delete_book,
+        get_ids,
+        get_status_history,
+        add_genre_to_book,
+        delete_genre_from_book,
+        add_author_to_book,
+        delete_author_from_book,
+        add_rate_to_book,
+        update_rate_to_book,
+        delete_rate_from_book
+    ),
+    components(schemas(BookData, BookGenreData, BookAuthorData, BookRateData, BookWithGenresAndRates))
+)]
+pub struct BookApi;
+
 pub fn get_all_methods() -> Vec<rocket::Route> {
     routes![
         get_all_books,
         get_book_by_id,
+        get_book_cover,
+        upload_book_cover,
         create_book,
         update_book,
         delete_book,
@@ -472,6 +979,7 @@ pub fn get_all_methods() -> Vec<rocket::Route> {
         add_rate_to_book,
         update_rate_to_book,
         delete_rate_from_book,
-        get_ids
+        get_ids,
+        get_status_history
     ]
 }
\ No newline at end of file
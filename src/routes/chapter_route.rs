@@ -3,51 +3,109 @@ use std::io::Read;
 
 use chrono::NaiveDate;
 use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
 use rocket::tokio::fs;
 use rocket::State;
 
-use rocket::response::status;
 use rocket::http::Status;
 
-use crate::entities::prelude::Chapter;
+use crate::audit::{self, Actor};
+use crate::error::ApiError;
+use crate::entities::book;
+use crate::entities::prelude::{Book, Chapter};
 use crate::entities::chapter::{ActiveModel, Column, Model};
-use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use crate::events::{self, EventHub};
+use crate::metrics::{self, Metrics};
+use crate::routes::notification_route;
+use crate::views::{ViewCounter, Viewer};
+use sea_orm::{prelude::DbErr, ActiveModelTrait, SqlErr, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Select};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+struct ChapterData {
+    book_id: i32,
+    #[validate(length(min = 1, max = 200, message = "must be 1 to 200 characters long"))]
+    #[schema(example = "The Ice")]
+    title: String,
+    #[validate(range(min = 1, message = "must be positive"))]
+    #[schema(example = 1)]
+    number: i32,
+    date: NaiveDate
+}
+
+/// Title edit of the admin panel, the number and files of a chapter stay as uploaded.
+#[derive(Debug, Serialize, Deserialize, Validate, FromForm)]
+pub(crate) struct ChapterTitleData {
+    #[validate(length(min = 1, max = 200, message = "must be 1 to 200 characters long"))]
+    title: String
+}
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    responses(
+        (status = 200, description = "All chapters", body = Vec<Chapter>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/")]
 async fn get_all_chapters(
     db: &State<DatabaseConnection>
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<Model>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let chapters = Chapter::find().all(db).await;
+    let chapters = visible_chapters().all(db).await;
 
     match chapters {
         Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    params(
+        ("id" = i32, Path, description = "Book id")
+    ),
+    responses(
+        (status = 200, description = "Chapters of the book", body = Vec<Chapter>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/book-chapters/<id>")]
 async fn get_book_chapters(
     db: &State<DatabaseConnection>,
     id: i32
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<Model>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let chapters = Chapter::find().filter(Column::BookId.eq(id)).all(db).await;
+    let chapters = visible_chapters().filter(Column::BookId.eq(id)).all(db).await;
 
     match chapters {
         Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    params(
+        ("id" = i32, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "Chapter with the id, or an empty chapter with id -1", body = Chapter),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/<id>")]
 async fn get_chapter_by_id(
     db: &State<DatabaseConnection>,
     id: i32
-) -> Result<Json<Model>, status::Custom<String>> {
+) -> Result<Json<Model>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let chapter = Chapter::find_by_id(id).one(db).await;
+    let chapter = visible_chapters().filter(Column::Id.eq(id)).one(db).await;
     let null_date = NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
 
     match chapter {
@@ -63,16 +121,32 @@ async fn get_chapter_by_id(
             };
             Ok(Json(empty_chapter))
         }
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    request_body = ChapterData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 409, description = "The book already has a chapter with this number", body = ApiError),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/", data="<chapter_data>", format="json")]
 async fn create_chapter(
     db: &State<DatabaseConnection>,
-    chapter_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    hub: &State<EventHub>,
+    actor: Actor,
+    chapter_data: Json<ChapterData>,
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    chapter_data.validate()?;
+
     let is_chapter_exists = Chapter::find()
         .filter(Column::BookId.eq(chapter_data.book_id))
         .filter(Column::Number.eq(chapter_data.number))
@@ -80,56 +154,86 @@ async fn create_chapter(
         .await;
 
     match is_chapter_exists {
-        Ok(Some(chapter)) => Err(status::Custom(
-            Status::InternalServerError,
-            format!("Book {} has chapter with number {}", chapter.book_id, chapter.number)
-        )),
+        Ok(Some(chapter)) => Err(duplicate_number(chapter.book_id, chapter.number)),
         Ok(None) => {
             let filepath = format!("/{}/{}/", chapter_data.book_id, chapter_data.number);
-            let chapter_dir = fs::create_dir(
-                format!("storage{}", filepath)
-            ).await;
 
-            match chapter_dir {
-                Ok(_) => {
-                    let chapter:Result<Model, DbErr> = ActiveModel {
-                        book_id: ActiveValue::set(chapter_data.book_id.clone()),
-                        title: ActiveValue::set(chapter_data.title.clone()),
-                        filepath: ActiveValue::set(filepath),
-                        number: ActiveValue::set(chapter_data.number.clone()),
-                        date: ActiveValue::set(chapter_data.date),
-                        ..Default::default()
-                    }.insert(db).await;
-
-                    match chapter {
-                        Ok(_) => Ok(Json(format!("Chapter {} was successfully created", chapter_data.title.clone()))),
-                        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-                    }  
+            // The row goes first, so a request that raced for the number stops at the unique index
+            // before touching the directory of the chapter that won
+            let chapter:Result<Model, DbErr> = ActiveModel {
+                book_id: ActiveValue::set(chapter_data.book_id),
+                title: ActiveValue::set(chapter_data.title.clone()),
+                filepath: ActiveValue::set(filepath.clone()),
+                number: ActiveValue::set(chapter_data.number),
+                date: ActiveValue::set(chapter_data.date),
+                ..Default::default()
+            }.insert(db).await;
+
+            let result = match chapter {
+                Ok(result) => result,
+                Err(err) => return Err(chapter_error(err, chapter_data.book_id, chapter_data.number))
+            };
+
+            // A directory left behind with this number is reused
+            if let Err(err) = fs::create_dir_all(format!("storage{}", filepath)).await {
+                Chapter::delete_by_id(result.id).exec(db).await?;
+
+                return Err(ApiError::new(Status::InternalServerError, err.to_string()));
+            }
+
+            match notification_route::notify_new_chapter(db, &result).await {
+                Ok(notifications) => {
+                    for notification in notifications {
+                        hub.publish(notification.user_id, events::NOTIFICATION, &notification);
+                    }
+
+                    let after = audit::snapshot(&result);
+                    audit::record(db, &actor, audit::CREATE, "chapter", result.id, None, Some(after)).await?;
+
+                    Ok(Json(format!("Chapter {} was successfully created", chapter_data.title.clone())))
                 },
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
             }
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    params(
+        ("id" = i32, Path, description = "Chapter id")
+    ),
+    request_body = ChapterData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 404, description = "No such chapter", body = ApiError),
+        (status = 409, description = "The book already has a chapter with this number", body = ApiError),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Chapter number can not be changed or database error", body = ApiError)
+    ),
+)]
 #[put("/<id>", data="<chapter_data>", format="json")]
 async fn update_chapter(
     db: &State<DatabaseConnection>,
-    chapter_data: Json<Model>,
+    actor: Actor,
+    chapter_data: Json<ChapterData>,
     id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let is_chapter_exists = Chapter::find()
-        .filter(Column::Id.eq(id))
-        .all(db)
-        .await
-        .unwrap();
 
-    if is_chapter_exists[0].number != chapter_data.number {
-        return Err(status::Custom(
+    chapter_data.validate()?;
+
+    let before = match Chapter::find_by_id(id).one(db).await? {
+        Some(chapter) => chapter,
+        None => return Err(ApiError::new(Status::NotFound, format!("No chapter with id {}", id)))
+    };
+
+    if before.number != chapter_data.number {
+        return Err(ApiError::new(
             Status::InternalServerError,
-            format!("Book {} has chapter with number {}", is_chapter_exists[0].book_id, chapter_data.number)
+            format!("Book {} has chapter with number {}", before.book_id, chapter_data.number)
         ))
     }
 
@@ -146,59 +250,70 @@ async fn update_chapter(
     }.update(db).await;
 
     match updated_chapter {
-        Ok(_) => Ok(Json(format!("Chapter {} was successfully updated", chapter_data.title.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(chapter) => {
+            let before = Some(audit::snapshot(&before));
+            audit::record(db, &actor, audit::UPDATE, "chapter", id, before, Some(audit::snapshot(&chapter))).await?;
+
+            Ok(Json(format!("Chapter {} was successfully updated", chapter_data.title.clone())))
+        },
+        Err(err) => Err(chapter_error(err, chapter_data.book_id, chapter_data.number))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    params(
+        ("id" = i32, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 404, description = "No such chapter", body = ApiError),
+        (status = 500, description = "Database or storage error", body = ApiError)
+    ),
+)]
 #[delete("/<id>")]
 async fn delete_chapter(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let delete_chapter = Chapter::find()
-        .filter(Column::Id.eq(id))
-        .all(db)
-        .await
-        .unwrap();
+    let rows_affected = remove_chapter(db, &actor, id).await?;
 
-    let deleted_chapter = ActiveModel {
-        id: ActiveValue::set(id),
-        ..Default::default()
-    }.delete(db).await;
-
-    match deleted_chapter {
-        Ok(result) => {
-            let filepath = format!("/{}/{}/", delete_chapter[0].book_id, delete_chapter[0].number);
-            let chapter_dir = fs::remove_dir(
-                format!("storage{}", filepath)
-            ).await;
-
-            match chapter_dir {
-                Ok(_) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-            }
-        },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-    }
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    params(
+        ("chapter_id" = i32, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "Chapter text", body = String),
+        (status = 404, description = "No such chapter", body = ApiError),
+        (status = 500, description = "Storage error", body = ApiError)
+    ),
+)]
 #[get("/text/<chapter_id>")]
 async fn get_chapter_text(
     db: &State<DatabaseConnection>,
+    metrics: &State<Metrics>,
+    views: &State<ViewCounter>,
+    viewer: Viewer,
     chapter_id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let chapter_data = Chapter::find()
+    let chapter_data = visible_chapters()
         .filter(Column::Id.eq(chapter_id))
         .all(db)
         .await
         .unwrap();
 
     if chapter_data.len() == 0 {
-        return Err(status::Custom(Status::NotFound, format!("No chapter with id {}", chapter_id)))
+        return Err(ApiError::new(Status::NotFound, format!("No chapter with id {}", chapter_id)))
     }
 
     let filepath = format!("storage{}/text.txt", chapter_data[0].filepath);
@@ -208,27 +323,44 @@ async fn get_chapter_text(
         Ok(mut result) => {
             let mut content = String::new();
             let _ = result.read_to_string(&mut content);
+            metrics.chapter_served(metrics::CHAPTER_TEXT, content.len());
+            views.view(viewer, chapter_data[0].book_id);
 
             Ok(Json(content))
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/chapter",
+    tag = "chapter",
+    params(
+        ("chapter_id" = i32, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "Chapter audio", body = String),
+        (status = 404, description = "No such chapter", body = ApiError),
+        (status = 500, description = "Storage error", body = ApiError)
+    ),
+)]
 #[get("/audio/<chapter_id>")]
 async fn get_chapter_audio(
     db: &State<DatabaseConnection>,
+    metrics: &State<Metrics>,
+    views: &State<ViewCounter>,
+    viewer: Viewer,
     chapter_id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let chapter_data = Chapter::find()
+    let chapter_data = visible_chapters()
         .filter(Column::Id.eq(chapter_id))
         .all(db)
         .await
         .unwrap();
 
     if chapter_data.len() == 0 {
-        return Err(status::Custom(Status::NotFound, format!("No chapter with id {}", chapter_id)))
+        return Err(ApiError::new(Status::NotFound, format!("No chapter with id {}", chapter_id)))
     }
 
     let filepath = format!("storage{}/audio.txt", chapter_data[0].filepath);
@@ -238,13 +370,104 @@ async fn get_chapter_audio(
         Ok(mut result) => {
             let mut content = String::new();
             let _ = result.read_to_string(&mut content);
+            metrics.chapter_served(metrics::CHAPTER_AUDIO, content.len());
+            views.view(viewer, chapter_data[0].book_id);
 
             Ok(Json(content))
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+/// Chapters of books that are not in the trash.
+fn visible_chapters() -> Select<Chapter> {
+    Chapter::find()
+        .inner_join(Book)
+        .filter(book::Column::DeletedAt.is_null())
+}
+
+pub(crate) async fn rename_chapter(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: i32,
+    title_data: &ChapterTitleData
+) -> Result<Model, ApiError> {
+    title_data.validate()?;
+
+    let before = Chapter::find_by_id(id).one(db).await?;
+
+    let updated_chapter = ActiveModel {
+        id: ActiveValue::set(id),
+        title: ActiveValue::set(title_data.title.clone()),
+        ..Default::default()
+    }.update(db).await;
+
+    let chapter = updated_chapter.map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    let before = before.as_ref().map(audit::snapshot);
+    audit::record(db, actor, audit::UPDATE, "chapter", id, before, Some(audit::snapshot(&chapter))).await?;
+
+    Ok(chapter)
+}
+
+fn duplicate_number(book_id: i32, number: i32) -> ApiError {
+    ApiError::new(Status::Conflict, format!("Book {} has chapter with number {}", book_id, number))
+}
+
+/// A chapter that clashes with the unique `(book_id, number)` index is reported like the checks do.
+fn chapter_error(err: DbErr, book_id: i32, number: i32) -> ApiError {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => duplicate_number(book_id, number),
+        _ => ApiError::new(Status::InternalServerError, err.to_string())
+    }
+}
+
+/// Deletes a chapter together with its storage directory.
+pub(crate) async fn remove_chapter(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    let chapter = match Chapter::find_by_id(id).one(db).await? {
+        Some(chapter) => chapter,
+        None => return Err(ApiError::new(Status::NotFound, format!("No chapter with id {}", id)))
+    };
+
+    let deleted_chapter = ActiveModel {
+        id: ActiveValue::set(id),
+        ..Default::default()
+    }.delete(db).await;
+
+    match deleted_chapter {
+        Ok(result) => {
+            let filepath = format!("/{}/{}/", chapter.book_id, chapter.number);
+            let chapter_dir = fs::remove_dir_all(
+                format!("storage{}", filepath)
+            ).await;
+
+            audit::record(db, actor, audit::DELETE, "chapter", id, Some(audit::snapshot(&chapter)), None).await?;
+
+            match chapter_dir {
+                Ok(_) => Ok(result.rows_affected),
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+            }
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_chapters,
+        get_book_chapters,
+        get_chapter_by_id,
+        create_chapter,
+        update_chapter,
+        delete_chapter,
+        get_chapter_text,
+        get_chapter_audio
+    ),
+    components(schemas(ChapterData))
+)]
+pub struct ChapterApi;
+
 pub fn get_all_chapter_methods() -> Vec<rocket::Route> {
     routes![
         get_all_chapters,
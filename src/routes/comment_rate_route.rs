@@ -1,39 +1,69 @@
 use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
 use rocket::State;
 
-use rocket::response::status;
 use rocket::http::Status;
 
+use crate::error::ApiError;
 use crate::entities::{comment, prelude::CommentRate};
+use crate::events::{self, EventHub};
+use crate::metrics::{self, Metrics};
 use crate::entities::comment_rate::{Model, ActiveModel, Column};
 use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter};
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct CommentRateData {
+    comment_id: i32,
+    user_id: i32,
+    /// 1 for an upvote, -1 for a downvote
+    #[schema(example = 1)]
+    rate: i32
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/comment-rate",
+    tag = "comment-rate",
+    responses(
+        (status = 200, description = "All comment rates", body = Vec<CommentRate>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/")]
 async fn get_all_comment_rates(
     db: &State<DatabaseConnection>
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<Model>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let comment_rates = CommentRate::find().all(db).await;
 
     match comment_rates {
         Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment-rate",
+    tag = "comment-rate",
+    request_body = CommentRateData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Rate is not 1 or -1", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/", data="<comment_rate_data>", format="json")]
 async fn create_comment_rate(
     db: &State<DatabaseConnection>,
-    comment_rate_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    hub: &State<EventHub>,
+    metrics: &State<Metrics>,
+    comment_rate_data: Json<CommentRateData>,
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     if comment_rate_data.rate != -1 && comment_rate_data.rate != 1 {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            "Saving rate error: Invalid rate value".to_string()
-        ))
+        return Err(ApiError::field("rate", "must be 1 or -1"))
     }
 
     let comment_rate:Result<Model, DbErr> = ActiveModel {
@@ -44,49 +74,47 @@ async fn create_comment_rate(
     }.insert(db).await;
 
     match comment_rate {
-        Ok(_) => {
-            let upvotes = CommentRate::find()
-                .filter(Column::CommentId.eq(comment_rate_data.comment_id))
-                .filter(Column::Rate.eq(1))
-                .all(db)
-                .await
-                .unwrap();
-
-            let downvotes = CommentRate::find()
-                .filter(Column::CommentId.eq(comment_rate_data.comment_id))
-                .filter(Column::Rate.eq(-1))
-                .all(db)
-                .await
-                .unwrap();
-
-            let updated_comment = comment::ActiveModel {
-                id: ActiveValue::set(comment_rate_data.comment_id),
-                upvotes: ActiveValue::set(upvotes.len() as i32),
-                downvotes: ActiveValue::set(downvotes.len() as i32),
-                ..Default::default()
-            }.update(db).await;
+        Ok(comment_rate) => {
+            let updated_comment = update_comment_votes(db, comment_rate.comment_id).await;
 
             match updated_comment {
-                Ok(_) => Ok(Json(format!("Comment rate was successfully created"))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+                Ok(result) => {
+                    metrics.vote(metrics::VOTE_COMMENT);
+
+                    if result.user_id != comment_rate.user_id {
+                        hub.publish(result.user_id, events::COMMENT_VOTE, &comment_rate);
+                    }
+
+                    Ok(Json("Comment rate was successfully created".to_string()))
+                },
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
             }
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment-rate",
+    tag = "comment-rate",
+    request_body = CommentRateData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 422, description = "Rate is not 1 or -1", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[put("/", data="<comment_rate_data>", format="json")]
 async fn update_comment_rate(
     db: &State<DatabaseConnection>,
-    comment_rate_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    hub: &State<EventHub>,
+    metrics: &State<Metrics>,
+    comment_rate_data: Json<CommentRateData>,
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    if comment_rate_data.rate != 1 && comment_rate_data.rate != -1 {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            "Saving rate error: Invalid rate value".to_string()
-        ))
+    if comment_rate_data.rate != -1 && comment_rate_data.rate != 1 {
+        return Err(ApiError::field("rate", "must be 1 or -1"))
     }
 
     let comment_rate:Result<Model, DbErr> = ActiveModel {
@@ -97,43 +125,44 @@ async fn update_comment_rate(
     }.update(db).await;
 
     match comment_rate {
-        Ok(_) => {
-            let upvotes = CommentRate::find()
-                .filter(Column::CommentId.eq(comment_rate_data.comment_id))
-                .filter(Column::Rate.eq(1))
-                .all(db)
-                .await
-                .unwrap();
-
-            let downvotes = CommentRate::find()
-                .filter(Column::CommentId.eq(comment_rate_data.comment_id))
-                .filter(Column::Rate.eq(-1))
-                .all(db)
-                .await
-                .unwrap();
-
-            let updated_comment = comment::ActiveModel {
-                id: ActiveValue::set(comment_rate_data.comment_id),
-                upvotes: ActiveValue::set(upvotes.len() as i32),
-                downvotes: ActiveValue::set(downvotes.len() as i32),
-                ..Default::default()
-            }.update(db).await;
+        Ok(comment_rate) => {
+            let updated_comment = update_comment_votes(db, comment_rate.comment_id).await;
 
             match updated_comment {
-                Ok(_) => Ok(Json(format!("Comment rate was successfully updated"))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+                Ok(result) => {
+                    metrics.vote(metrics::VOTE_COMMENT);
+
+                    if result.user_id != comment_rate.user_id {
+                        hub.publish(result.user_id, events::COMMENT_VOTE, &comment_rate);
+                    }
+
+                    Ok(Json("Comment rate was successfully updated".to_string()))
+                },
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
             }
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment-rate",
+    tag = "comment-rate",
+    params(
+        ("comment_id" = i32, Path, description = "Comment id"),
+        ("user_id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Deletion message", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/<comment_id>/<user_id>")]
 async fn delete_comment_rate(
     db: &State<DatabaseConnection>,
     comment_id: i32,
     user_id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let deleted_comment_rate = ActiveModel {
@@ -144,36 +173,44 @@ async fn delete_comment_rate(
 
     match deleted_comment_rate {
         Ok(_) => {
-            let upvotes = CommentRate::find()
-                .filter(Column::CommentId.eq(comment_id))
-                .filter(Column::Rate.eq(1))
-                .all(db)
-                .await
-                .unwrap();
-
-            let downvotes = CommentRate::find()
-                .filter(Column::CommentId.eq(comment_id))
-                .filter(Column::Rate.eq(-1))
-                .all(db)
-                .await
-                .unwrap();
-
-            let updated_comment = comment::ActiveModel {
-                id: ActiveValue::set(comment_id),
-                upvotes: ActiveValue::set(upvotes.len() as i32),
-                downvotes: ActiveValue::set(downvotes.len() as i32),
-                ..Default::default()
-            }.update(db).await;
+            let updated_comment = update_comment_votes(db, comment_id).await;
 
             match updated_comment {
                 Ok(_) => Ok(Json(format!("Comment rate was successfully deleted"))),
-                Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
             }
         },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+/// Recounts the upvotes/downvotes stored on the comment.
+pub async fn update_comment_votes(db: &DatabaseConnection, comment_id: i32) -> Result<comment::Model, DbErr> {
+    let rates = CommentRate::find()
+        .filter(Column::CommentId.eq(comment_id))
+        .all(db)
+        .await?;
+
+    comment::ActiveModel {
+        id: ActiveValue::set(comment_id),
+        upvotes: ActiveValue::set(rates.iter().filter(|rate| rate.rate == 1).count() as i32),
+        downvotes: ActiveValue::set(rates.iter().filter(|rate| rate.rate == -1).count() as i32),
+        ..Default::default()
+    }.update(db).await
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_comment_rates,
+        create_comment_rate,
+        update_comment_rate,
+        delete_comment_rate
+    ),
+    components(schemas(CommentRateData))
+)]
+pub struct CommentRateApi;
+
 pub fn get_all_comment_rate_methods() -> Vec<rocket::Route> {
     routes![get_all_comment_rates, create_comment_rate, update_comment_rate, delete_comment_rate]
 }
\ No newline at end of file
@@ -1,40 +1,174 @@
+use std::collections::HashMap;
+
 use rocket::serde::json::Json;
-use rocket::response::status;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
 
 use rocket::http::Status;
 use rocket::State;
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
 use sea_orm::{ColumnTrait, QueryFilter};
 
-use crate::entities::prelude::{Comment, Chapter};
-use crate::entities::comment::{ActiveModel, Model};
-use crate::entities::chapter;
+use crate::audit::{self, Actor};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::entities::prelude::{Comment, Chapter, ReadingProgress};
+use crate::entities::comment::{ActiveModel, Column, Model};
+use crate::entities::{chapter, reading_progress};
+use crate::markup;
+use crate::metrics::Metrics;
+
+use sea_orm::{prelude::{DateTime, DbErr}, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+struct CommentData {
+    book_id: i32,
+    user_id: i32,
+    chapter_id: i32,
+    #[validate(length(min = 1, max = 2000, message = "must be 1 to 2000 characters long"))]
+    #[schema(example = "Loved it, ||the hero survives||")]
+    text: String
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+struct CommentTextData {
+    #[validate(length(min = 1, max = 2000, message = "must be 1 to 2000 characters long"))]
+    #[schema(example = "Loved it, ||the hero survives||")]
+    text: String
+}
 
-use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct CommentWithMarkup {
+    #[serde(flatten)]
+    #[schema(value_type = Comment)]
+    comment: Model,
+    /// Sanitized text with `||spoilers||` rendered as `<span class="spoiler">`
+    #[schema(example = "Loved it, <span class=\"spoiler\">the hero survives</span>")]
+    html: String,
+    /// Set when the comment is on a chapter the reader has not reached yet.
+    /// The raw `text` is left empty then, the comment is only in `html` as a spoiler
+    spoiler_hidden: bool
+}
 
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    responses(
+        (status = 200, description = "All comments", body = Vec<CommentWithMarkup>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/")]
 async fn get_all_comments(
     db: &State<DatabaseConnection>
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<CommentWithMarkup>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let comments = Comment::find().all(db).await;
+    let comments = Comment::find().filter(Column::DeletedAt.is_null()).all(db).await;
 
     match comments {
+        Ok(result) => Ok(Json(result.into_iter().map(|comment| with_markup(comment, false)).collect())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    params(
+        ("book_id" = i32, Path, description = "Book id"),
+    ),
+    security((), ("session_token" = [])),
+    responses(
+        (status = 200, description = "Comments of the book", body = Vec<CommentWithMarkup>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/book/<book_id>")]
+async fn get_book_comments(
+    db: &State<DatabaseConnection>,
+    book_id: i32,
+    reader: Option<AuthUser>
+) -> Result<Json<Vec<CommentWithMarkup>>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let comments = Comment::find()
+        .filter(Column::BookId.eq(book_id))
+        .filter(Column::DeletedAt.is_null())
+        .all(db)
+        .await;
+
+    let result = match comments {
+        Ok(comments) => hide_spoilers(db, comments, reader.map(|reader| reader.id)).await,
+        Err(err) => Err(err)
+    };
+
+    match result {
         Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    params(
+        ("chapter_id" = i32, Path, description = "Chapter id"),
+    ),
+    security((), ("session_token" = [])),
+    responses(
+        (status = 200, description = "Comments of the chapter", body = Vec<CommentWithMarkup>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/chapter/<chapter_id>")]
+async fn get_chapter_comments(
+    db: &State<DatabaseConnection>,
+    chapter_id: i32,
+    reader: Option<AuthUser>
+) -> Result<Json<Vec<CommentWithMarkup>>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let comments = Comment::find()
+        .filter(Column::ChapterId.eq(chapter_id))
+        .filter(Column::DeletedAt.is_null())
+        .all(db)
+        .await;
+
+    let result = match comments {
+        Ok(comments) => hide_spoilers(db, comments, reader.map(|reader| reader.id)).await,
+        Err(err) => Err(err)
+    };
+
+    match result {
+        Ok(result) => Ok(Json(result)),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    params(
+        ("id" = i32, Path, description = "Comment id")
+    ),
+    responses(
+        (status = 200, description = "Comment with the id, or an empty comment with id -1", body = CommentWithMarkup),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/<id>")]
 async fn get_comment_by_id(
     db: &State<DatabaseConnection>,
     id: i32
-) -> Result<Json<Model>, status::Custom<String>> {
+) -> Result<Json<CommentWithMarkup>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let comment = Comment::find_by_id(id).one(db).await;
+    let comment = Comment::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await;
 
     match comment {
-        Ok(Some(comment)) => Ok(Json(comment)),
+        Ok(Some(comment)) => Ok(Json(with_markup(comment, false))),
         Ok(None) => {
             let empty_comment = Model {
                 id: -1,
@@ -44,19 +178,36 @@ async fn get_comment_by_id(
                 text: String::new(),
                 upvotes: 0,
                 downvotes: 0,
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
+                deleted_at: None,
             };
-            Ok(Json(empty_comment))
+            Ok(Json(with_markup(empty_comment, false)))
         }
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    request_body = CommentData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/", data="<comment_data>", format="json")]
 async fn create_comment(
     db: &State<DatabaseConnection>,
-    comment_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    metrics: &State<Metrics>,
+    comment_data: Json<CommentData>,
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    comment_data.validate()?;
+
     let chapter = Chapter::find_by_id(comment_data.chapter_id)
         .filter(chapter::Column::BookId.eq(comment_data.book_id))
         .all(db)
@@ -64,7 +215,7 @@ async fn create_comment(
         .unwrap();
     
     // if chapter.is_empty() {
-    //     return Err(status::Custom(
+    //     return Err(ApiError::new(
     //         Status::InternalServerError,
     //         format!("No such chapter with id {} and book_id {}", comment_data.chapter_id, comment_data.book_id)
     //     ));
@@ -81,31 +232,37 @@ async fn create_comment(
     }.insert(db).await;
 
     match comment {
-        Ok(_) => Ok(Json(format!("Comment was successfully created"))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(_) => {
+            metrics.comment_created();
+
+            Ok(Json("Comment was successfully created".to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    params(
+        ("id" = i32, Path, description = "Comment id")
+    ),
+    request_body = CommentTextData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[put("/<id>", data="<comment_data>", format="json")]
 async fn update_comment(
     db: &State<DatabaseConnection>,
-    comment_data: Json<Model>,
+    comment_data: Json<CommentTextData>,
     id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let chapter = Chapter::find_by_id(comment_data.chapter_id)
-        .filter(chapter::Column::BookId.eq(comment_data.book_id))
-        .all(db)
-        .await
-        .unwrap();
-    
-    if chapter.is_empty() {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            format!("No such chapter with id {} and book_id {}", comment_data.chapter_id, comment_data.book_id)
-        ));
-    }
+    comment_data.validate()?;
 
     let updated_comment = ActiveModel {
         id: ActiveValue::set(id),
@@ -115,28 +272,151 @@ async fn update_comment(
 
     match updated_comment {
         Ok(result) => Ok(Json(format!("Comment {} was successfully updated", result.id.to_string()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/comment",
+    tag = "comment",
+    params(
+        ("id" = i32, Path, description = "Comment id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/<id>")]
 async fn delete_comment(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let deleted_comment = ActiveModel {
-        id: ActiveValue::set(id),
-        ..Default::default()
-    }.delete(db).await;
+    let rows_affected = remove_comment(db, &actor, id).await?;
+
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
+}
+
+/// Moves a comment to the trash, the retention purge removes it for good.
+pub(crate) async fn remove_comment(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_comment_deleted_at(db, actor, id, Some(Utc::now().naive_utc())).await
+}
+
+/// Takes a comment out of the trash.
+pub(crate) async fn restore_comment(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_comment_deleted_at(db, actor, id, None).await
+}
+
+async fn set_comment_deleted_at(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: i32,
+    deleted_at: Option<DateTime>
+) -> Result<u64, ApiError> {
+    let updated_comments = Comment::update_many()
+        .col_expr(Column::DeletedAt, Expr::value(deleted_at))
+        .filter(Column::Id.eq(id))
+        .filter(match deleted_at {
+            Some(_) => Column::DeletedAt.is_null(),
+            None => Column::DeletedAt.is_not_null()
+        })
+        .exec(db)
+        .await;
 
-    match deleted_comment {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+    match updated_comments {
+        Ok(result) => {
+            if result.rows_affected > 0 {
+                let comment = Comment::find_by_id(id).one(db).await?;
+                audit::record_trash(db, actor, "comment", id, deleted_at.is_some(), comment).await?;
+            }
+
+            Ok(result.rows_affected)
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+fn with_markup(mut comment: Model, spoiler_hidden: bool) -> CommentWithMarkup {
+    let html = if spoiler_hidden {
+        markup::render_hidden(&std::mem::take(&mut comment.text))
+    } else {
+        markup::render(&comment.text)
+    };
+
+    CommentWithMarkup {
+        comment,
+        html,
+        spoiler_hidden
     }
 }
 
+/// Hides comments left on chapters the signed in reader has not read yet.
+/// Without a session every comment is shown as is.
+async fn hide_spoilers(
+    db: &DatabaseConnection,
+    comments: Vec<Model>,
+    user_id: Option<i32>
+) -> Result<Vec<CommentWithMarkup>, DbErr> {
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(comments.into_iter().map(|comment| with_markup(comment, false)).collect())
+    };
+
+    let chapter_ids = comments.iter().map(|comment| comment.chapter_id).collect::<Vec<i32>>();
+
+    let chapter_numbers = Chapter::find()
+        .filter(chapter::Column::Id.is_in(chapter_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|chapter| (chapter.id, chapter.number))
+        .collect::<HashMap<i32, i32>>();
+
+    let progress = ReadingProgress::find()
+        .filter(reading_progress::Column::UserId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|progress| (progress.book_id, progress.chapter_number))
+        .collect::<HashMap<i32, i32>>();
+
+    Ok(comments.into_iter().map(|comment| {
+        let read_up_to = progress.get(&comment.book_id).copied().unwrap_or(0);
+        let spoiler_hidden = match chapter_numbers.get(&comment.chapter_id) {
+            Some(number) => *number > read_up_to,
+            None => false
+        };
+
+        with_markup(comment, spoiler_hidden)
+    }).collect())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_comments,
+        get_book_comments,
+        get_chapter_comments,
+        get_comment_by_id,
+        create_comment,
+        update_comment,
+        delete_comment
+    ),
+    components(schemas(CommentData, CommentTextData, CommentWithMarkup))
+)]
+pub struct CommentApi;
+
 pub fn get_all_comment_methods() -> Vec<rocket::Route> {
-    routes![get_all_comments, get_comment_by_id, create_comment, update_comment, delete_comment]
+    routes![
+        get_all_comments,
+        get_comment_by_id,
+        get_book_comments,
+        get_chapter_comments,
+        create_comment,
+        update_comment,
+        delete_comment
+    ]
 }
\ No newline at end of file
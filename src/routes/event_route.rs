@@ -0,0 +1,68 @@
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Shutdown, State};
+use utoipa::OpenApi;
+
+use crate::auth::AuthUser;
+use crate::events::{EventHub, LastEventId, PushEvent};
+
+fn to_sse(event: &PushEvent) -> Event {
+    Event::json(&event.data)
+        .id(event.id.to_string())
+        .event(event.kind)
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/events",
+    tag = "events",
+    params(
+        ("Last-Event-ID" = Option<u64>, Header, description = "Id of the last received event to resume from"),
+        ("token" = Option<String>, Query, description = "Session token for clients that can not send the Authorization header")
+    ),
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Server-Sent Events stream of notification and comment_vote events", body = String, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid session token", body = ApiError)
+    ),
+)]
+#[get("/")]
+fn get_user_events(
+    user: AuthUser,
+    hub: &State<EventHub>,
+    last_event_id: LastEventId,
+    mut end: Shutdown
+) -> EventStream![] {
+    let (missed, mut receiver) = hub.subscribe(user.id, last_event_id.0);
+
+    EventStream! {
+        for event in missed {
+            yield to_sse(&event);
+        }
+
+        loop {
+            let event = select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut end => break,
+            };
+
+            yield to_sse(&event);
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_user_events
+    )
+)]
+pub struct EventApi;
+
+pub fn get_all_event_methods() -> Vec<rocket::Route> {
+    routes![get_user_events]
+}
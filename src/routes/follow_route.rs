@@ -0,0 +1,206 @@
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+use rocket::http::Status;
+
+use rocket::State;
+
+use crate::error::ApiError;
+use crate::entities::prelude::{Author, AuthorFollow, Book, BookFollow};
+use crate::entities::{author, author_follow, book, book_follow};
+
+use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct BookFollowData {
+    user_id: i32,
+    book_id: i32
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct AuthorFollowData {
+    user_id: i32,
+    author_id: i32
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct UserFollows {
+    books: Vec<i32>,
+    authors: Vec<i32>
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/follow",
+    tag = "follow",
+    params(
+        ("user_id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Followed books and authors", body = UserFollows),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/<user_id>")]
+async fn get_user_follows(
+    db: &State<DatabaseConnection>,
+    user_id: i32
+) -> Result<Json<UserFollows>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    // Follows of trashed books and authors come back when they are restored
+    let books = BookFollow::find()
+        .inner_join(Book)
+        .filter(book_follow::Column::UserId.eq(user_id))
+        .filter(book::Column::DeletedAt.is_null())
+        .all(db)
+        .await;
+
+    let authors = AuthorFollow::find()
+        .inner_join(Author)
+        .filter(author_follow::Column::UserId.eq(user_id))
+        .filter(author::Column::DeletedAt.is_null())
+        .all(db)
+        .await;
+
+    match (books, authors) {
+        (Ok(books), Ok(authors)) => Ok(Json(UserFollows {
+            books: books.iter().map(|follow| follow.book_id).collect(),
+            authors: authors.iter().map(|follow| follow.author_id).collect()
+        })),
+        (Err(err), _) | (_, Err(err)) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/follow",
+    tag = "follow",
+    request_body = BookFollowData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/book", data="<book_follow_data>", format="json")]
+async fn follow_book(
+    db: &State<DatabaseConnection>,
+    book_follow_data: Json<BookFollowData>
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let book_follow: Result<book_follow::Model, DbErr> = book_follow::ActiveModel {
+        user_id: ActiveValue::set(book_follow_data.user_id),
+        book_id: ActiveValue::set(book_follow_data.book_id),
+    }.insert(db).await;
+
+    match book_follow {
+        Ok(result) => Ok(Json(format!("User {} now follows book {}", result.user_id, result.book_id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/follow",
+    tag = "follow",
+    params(
+        ("user_id" = i32, Path, description = "User id"),
+        ("book_id" = i32, Path, description = "Book id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[delete("/book/<user_id>/<book_id>")]
+async fn unfollow_book(
+    db: &State<DatabaseConnection>,
+    user_id: i32,
+    book_id: i32
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let book_follow = book_follow::ActiveModel {
+        user_id: ActiveValue::set(user_id),
+        book_id: ActiveValue::set(book_id),
+    }.delete(db).await;
+
+    match book_follow {
+        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/follow",
+    tag = "follow",
+    request_body = AuthorFollowData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/author", data="<author_follow_data>", format="json")]
+async fn follow_author(
+    db: &State<DatabaseConnection>,
+    author_follow_data: Json<AuthorFollowData>
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let author_follow: Result<author_follow::Model, DbErr> = author_follow::ActiveModel {
+        user_id: ActiveValue::set(author_follow_data.user_id),
+        author_id: ActiveValue::set(author_follow_data.author_id),
+    }.insert(db).await;
+
+    match author_follow {
+        Ok(result) => Ok(Json(format!("User {} now follows author {}", result.user_id, result.author_id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/follow",
+    tag = "follow",
+    params(
+        ("user_id" = i32, Path, description = "User id"),
+        ("author_id" = i32, Path, description = "Author id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[delete("/author/<user_id>/<author_id>")]
+async fn unfollow_author(
+    db: &State<DatabaseConnection>,
+    user_id: i32,
+    author_id: i32
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let author_follow = author_follow::ActiveModel {
+        user_id: ActiveValue::set(user_id),
+        author_id: ActiveValue::set(author_id),
+    }.delete(db).await;
+
+    match author_follow {
+        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_user_follows,
+        follow_book,
+        unfollow_book,
+        follow_author,
+        unfollow_author
+    ),
+    components(schemas(BookFollowData, AuthorFollowData, UserFollows))
+)]
+pub struct FollowApi;
+
+pub fn get_all_follow_methods() -> Vec<rocket::Route> {
+    routes![get_user_follows, follow_book, unfollow_book, follow_author, unfollow_author]
+}
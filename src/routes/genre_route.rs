@@ -1,34 +1,64 @@
 use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
 
-use rocket::response::status;
 use rocket::http::Status;
 
 use rocket::State;
 
+use crate::audit::{self, Actor};
+use crate::error::ApiError;
 use crate::entities::prelude::Genre;
-use crate::entities::{genre::Model, genre::ActiveModel};
+use crate::entities::{genre::Model, genre::ActiveModel, genre::Column};
 
-use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+use sea_orm::{prelude::{DateTime, DbErr}, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate, FromForm)]
+pub(crate) struct GenreData {
+    #[validate(length(min = 1, max = 50, message = "must be 1 to 50 characters long"))]
+    #[schema(example = "Fantasy")]
+    title: String
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/genre",
+    tag = "genre",
+    responses(
+        (status = 200, description = "All genres", body = Vec<Genre>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/")]
 async fn get_all_genres(
     db: &State<DatabaseConnection>
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<Model>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
     let genres = Genre::find().all(db).await;
 
     match genres {
         Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/genre",
+    tag = "genre",
+    params(
+        ("id" = i32, Path, description = "Genre id")
+    ),
+    responses(
+        (status = 200, description = "Genre with the id, or an empty genre with id -1", body = Genre),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/<id>")]
 async fn get_genre_by_id(
     db: &State<DatabaseConnection>,
     id: i32
-) -> Result<Json<Model>, status::Custom<String>> {
+) -> Result<Json<Model>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
     let genre = Genre::find_by_id(id).one(db).await;
 
@@ -38,74 +68,172 @@ async fn get_genre_by_id(
             let empty_genre = Model {
                 id: -1,
                 title: String::new(),
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
             };
             Ok(Json(empty_genre))
         }
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/genre",
+    tag = "genre",
+    request_body = GenreData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/", data="<genre_data>", format="json")]
 async fn create_genre(
     db: &State<DatabaseConnection>,
-    genre_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    actor: Actor,
+    genre_data: Json<GenreData>,
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let mut genre_title = genre_data.title.clone().to_lowercase();
-    let title = genre_title.remove(0).to_uppercase().to_string() + &genre_title;
 
-    let genre:Result<Model, DbErr> = ActiveModel {
-        title: ActiveValue::set(title),
-        ..Default::default()
-    }.insert(db).await;
+    save_genre(db, &actor, None, &genre_data).await?;
 
-    match genre {
-        Ok(_) => Ok(Json(format!("Genre {} was successfully created", genre_data.title.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-    }
+    Ok(Json(format!("Genre {} was successfully created", genre_data.title.clone())))
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/genre",
+    tag = "genre",
+    params(
+        ("id" = i32, Path, description = "Genre id")
+    ),
+    request_body = GenreData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[put("/<id>", data="<genre_data>", format="json")]
 async fn update_genre(
     db: &State<DatabaseConnection>,
-    genre_data: Json<Model>,
+    actor: Actor,
+    genre_data: Json<GenreData>,
     id: i32,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let mut genre_title = genre_data.title.clone().to_lowercase();
-    let title = genre_title.remove(0).to_uppercase().to_string() + &genre_title;
+    let result = save_genre(db, &actor, Some(id), &genre_data).await?;
 
-    let updated_genre = ActiveModel {
-        id: ActiveValue::set(id),
-        title: ActiveValue::set(title),
-        ..Default::default()
-    }.update(db).await;
-
-    match updated_genre {
-        Ok(result) => Ok(Json(format!("Genre {} was successfully updated", result.title.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-    }
+    Ok(Json(format!("Genre {} was successfully updated", result.title.clone())))
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/genre",
+    tag = "genre",
+    params(
+        ("id" = i32, Path, description = "Genre id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/<id>")]
 async fn delete_genre(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
+    let rows_affected = remove_genre(db, &actor, id).await?;
+
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
+}
+
+/// Creates a genre, or updates the one with `id`. The title is stored capitalized.
+/// Shared by the JSON API and the admin panel.
+pub(crate) async fn save_genre(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: Option<i32>,
+    genre_data: &GenreData
+) -> Result<Model, ApiError> {
+    genre_data.validate()?;
+
+    let mut genre_title = genre_data.title.clone().to_lowercase();
+    let title = genre_title.remove(0).to_uppercase().to_string() + &genre_title;
+
+    check_unique_title(db, &title, id).await?;
+
+    let before = match id {
+        Some(id) => Genre::find_by_id(id).one(db).await?,
+        None => None
+    };
+
+    let genre:Result<Model, DbErr> = match id {
+        Some(id) => ActiveModel {
+            id: ActiveValue::set(id),
+            title: ActiveValue::set(title),
+            ..Default::default()
+        }.update(db).await,
+        None => ActiveModel {
+            title: ActiveValue::set(title),
+            ..Default::default()
+        }.insert(db).await
+    };
+
+    let genre = genre.map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    let action = if before.is_some() { audit::UPDATE } else { audit::CREATE };
+    let before = before.as_ref().map(audit::snapshot);
+    audit::record(db, actor, action, "genre", genre.id, before, Some(audit::snapshot(&genre))).await?;
+
+    Ok(genre)
+}
+
+pub(crate) async fn remove_genre(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    let before = Genre::find_by_id(id).one(db).await?;
+
     let deleted_genre = ActiveModel {
         id: ActiveValue::set(id),
         ..Default::default()
     }.delete(db).await;
 
     match deleted_genre {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => {
+            if let Some(before) = before {
+                audit::record(db, actor, audit::DELETE, "genre", id, Some(audit::snapshot(&before)), None).await?;
+            }
+
+            Ok(result.rows_affected)
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+async fn check_unique_title(db: &DatabaseConnection, title: &str, id: Option<i32>) -> Result<(), ApiError> {
+    let genre = Genre::find().filter(Column::Title.eq(title)).one(db).await?;
+
+    match genre {
+        Some(genre) if Some(genre.id) != id => Err(ApiError::field("title", "is already taken")),
+        _ => Ok(())
     }
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_genres,
+        get_genre_by_id,
+        create_genre,
+        update_genre,
+        delete_genre
+    ),
+    components(schemas(GenreData))
+)]
+pub struct GenreApi;
+
 pub fn get_all_methods() -> Vec<rocket::Route> {
     routes![get_all_genres, get_genre_by_id, create_genre, update_genre, delete_genre]
 }
\ No newline at end of file
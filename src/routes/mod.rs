@@ -4,4 +4,11 @@ pub mod author_route;
 pub mod book_route;
 pub mod chapter_route;
 pub mod comment_route;
-pub mod comment_rate_route;
\ No newline at end of file
+pub mod comment_rate_route;
+pub mod review_route;
+pub mod follow_route;
+pub mod notification_route;
+pub mod event_route;
+pub mod admin_route;
+pub mod status_route;
+
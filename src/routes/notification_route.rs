@@ -0,0 +1,325 @@
+use std::collections::BTreeSet;
+
+use chrono::Utc;
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+use rocket::http::Status;
+
+use rocket::State;
+
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::entities::prelude::{AuthorFollow, Book, BookAuthor, BookFollow, Notification};
+use crate::entities::notification::{ActiveModel, Column, Model};
+use crate::entities::{author_follow, book, book_author, book_follow, chapter};
+
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    prelude::DbErr, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder
+};
+
+pub const NEW_CHAPTER: &str = "new_chapter";
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct NotificationPage {
+    #[schema(value_type = Vec<Notification>)]
+    items: Vec<Model>,
+    page: u64,
+    per_page: u64,
+    total: u64,
+    unread: u64
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct UnreadCount {
+    unread: u64
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/notification",
+    tag = "notification",
+    params(
+        ("page" = Option<u64>, Query, description = "Page number starting from 1"),
+        ("per_page" = Option<u64>, Query, description = "Page size, 20 by default and at most 100"),
+        ("unread_only" = Option<bool>, Query, description = "Return only unread notifications")
+    ),
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Page of the signed in user's notifications, newest first", body = NotificationPage),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/?<page>&<per_page>&<unread_only>")]
+async fn get_user_notifications(
+    db: &State<DatabaseConnection>,
+    user: AuthUser,
+    page: Option<u64>,
+    per_page: Option<u64>,
+    unread_only: Option<bool>
+) -> Result<Json<NotificationPage>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+
+    let mut query = Notification::find().filter(Column::UserId.eq(user.id));
+
+    if unread_only.unwrap_or(false) {
+        query = query.filter(Column::IsRead.eq(false));
+    }
+
+    let paginator = query
+        .order_by_desc(Column::CreatedAt)
+        .order_by_desc(Column::Id)
+        .paginate(db, per_page);
+
+    let total = paginator.num_items().await;
+    let items = paginator.fetch_page(page - 1).await;
+    let unread = count_unread(db, user.id).await;
+
+    match (total, items, unread) {
+        (Ok(total), Ok(items), Ok(unread)) => Ok(Json(NotificationPage {
+            items,
+            page,
+            per_page,
+            total,
+            unread
+        })),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/notification",
+    tag = "notification",
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Number of the signed in user's unread notifications", body = UnreadCount),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/unread-count")]
+async fn get_unread_count(
+    db: &State<DatabaseConnection>,
+    user: AuthUser
+) -> Result<Json<UnreadCount>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match count_unread(db, user.id).await {
+        Ok(unread) => Ok(Json(UnreadCount { unread })),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/notification",
+    tag = "notification",
+    params(
+        ("id" = i32, Path, description = "Notification id")
+    ),
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 404, description = "No such notification for the signed in user", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/read/<id>")]
+async fn mark_notification_as_read(
+    db: &State<DatabaseConnection>,
+    user: AuthUser,
+    id: i32
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    // Notifications of other users are reported as missing
+    let updated_notifications = Notification::update_many()
+        .col_expr(Column::IsRead, Expr::value(true))
+        .filter(Column::Id.eq(id))
+        .filter(Column::UserId.eq(user.id))
+        .exec(db)
+        .await;
+
+    match updated_notifications {
+        Ok(result) if result.rows_affected == 0 => Err(ApiError::new(
+            Status::NotFound,
+            format!("No notification with id {}", id)
+        )),
+        Ok(_) => Ok(Json(format!("Notification {} was marked as read", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/notification",
+    tag = "notification",
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Number of updated entries", body = String),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/read-all")]
+async fn mark_all_as_read(
+    db: &State<DatabaseConnection>,
+    user: AuthUser
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let updated_notifications = Notification::update_many()
+        .col_expr(Column::IsRead, Expr::value(true))
+        .filter(Column::UserId.eq(user.id))
+        .filter(Column::IsRead.eq(false))
+        .exec(db)
+        .await;
+
+    match updated_notifications {
+        Ok(result) => Ok(Json(format!("Number of updated entries: {}", result.rows_affected))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/notification",
+    tag = "notification",
+    params(
+        ("id" = i32, Path, description = "Notification id")
+    ),
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 404, description = "No such notification for the signed in user", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[delete("/<id>")]
+async fn delete_notification(
+    db: &State<DatabaseConnection>,
+    user: AuthUser,
+    id: i32
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let deleted_notification = Notification::delete_many()
+        .filter(Column::Id.eq(id))
+        .filter(Column::UserId.eq(user.id))
+        .exec(db)
+        .await;
+
+    match deleted_notification {
+        Ok(result) if result.rows_affected == 0 => Err(ApiError::new(
+            Status::NotFound,
+            format!("No notification with id {}", id)
+        )),
+        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+async fn count_unread(db: &DatabaseConnection, user_id: i32) -> Result<u64, DbErr> {
+    Notification::find()
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::IsRead.eq(false))
+        .count(db)
+        .await
+}
+
+/// Notifies everyone following the chapter's book or any of its authors.
+/// Returns the created notifications.
+pub(crate) async fn notify_new_chapter(db: &DatabaseConnection, chapter: &chapter::Model) -> Result<Vec<Model>, DbErr> {
+    let mut user_ids = BookFollow::find()
+        .filter(book_follow::Column::BookId.eq(chapter.book_id))
+        .all(db)
+        .await?
+        .iter()
+        .map(|follow| follow.user_id)
+        .collect::<BTreeSet<i32>>();
+
+    let author_ids = BookAuthor::find()
+        .filter(book_author::Column::BookId.eq(chapter.book_id))
+        .all(db)
+        .await?
+        .iter()
+        .map(|book_author| book_author.author_id)
+        .collect::<Vec<i32>>();
+
+    let author_followers = AuthorFollow::find()
+        .filter(author_follow::Column::AuthorId.is_in(author_ids))
+        .all(db)
+        .await?;
+
+    user_ids.extend(author_followers.iter().map(|follow| follow.user_id));
+
+    if user_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let book = Book::find_by_id(chapter.book_id)
+        .filter(book::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+
+    // Trashed books notify nobody
+    let book_title = match book {
+        Some(book) => book.title,
+        None => return Ok(Vec::new())
+    };
+
+    let message = format!("{}: new chapter {} \"{}\"", book_title, chapter.number, chapter.title);
+    let created_at = Utc::now().naive_utc();
+
+    let notifications = user_ids.iter().map(|user_id| ActiveModel {
+        user_id: ActiveValue::set(*user_id),
+        kind: ActiveValue::set(NEW_CHAPTER.to_string()),
+        book_id: ActiveValue::set(Some(chapter.book_id)),
+        chapter_id: ActiveValue::set(Some(chapter.id)),
+        message: ActiveValue::set(message.clone()),
+        is_read: ActiveValue::set(false),
+        created_at: ActiveValue::set(created_at),
+        ..Default::default()
+    }).collect::<Vec<ActiveModel>>();
+
+    Notification::insert_many(notifications).exec(db).await?;
+
+    Notification::find()
+        .filter(Column::ChapterId.eq(chapter.id))
+        .filter(Column::Kind.eq(NEW_CHAPTER))
+        .filter(Column::UserId.is_in(user_ids))
+        .all(db)
+        .await
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_user_notifications,
+        get_unread_count,
+        mark_notification_as_read,
+        mark_all_as_read,
+        delete_notification
+    ),
+    components(schemas(NotificationPage, UnreadCount))
+)]
+pub struct NotificationApi;
+
+pub fn get_all_notification_methods() -> Vec<rocket::Route> {
+    routes![
+        get_user_notifications,
+        get_unread_count,
+        mark_notification_as_read,
+        mark_all_as_read,
+        delete_notification
+    ]
+}
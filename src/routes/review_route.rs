@@ -0,0 +1,544 @@
+use chrono::Utc;
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
+
+use rocket::http::Status;
+
+use rocket::State;
+
+use crate::audit::{self, Actor};
+use crate::error::ApiError;
+use crate::entities::prelude::{Book, BookRate, Review, ReviewVote, User};
+use crate::entities::review::{ActiveModel, Column, Model};
+use crate::entities::{book, book_rate, review_vote, user};
+use crate::routes::book_route;
+use crate::markup;
+use crate::metrics::{self, Metrics};
+
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    prelude::DbErr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, Select
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+struct ReviewData {
+    user_id: i32,
+    book_id: i32,
+    #[validate(range(min = 1, max = 5, message = "must be between 1 and 5"))]
+    #[schema(example = 5, minimum = 1, maximum = 5)]
+    rate: i32,
+    #[validate(length(min = 1, max = 200, message = "must be 1 to 200 characters long"))]
+    #[schema(example = "A slow start that pays off")]
+    title: String,
+    #[validate(length(min = 1, max = 20000, message = "must be 1 to 20000 characters long"))]
+    #[schema(example = "The first chapters drag, but ||the twist in the finale|| makes up for it.")]
+    body: String,
+    /// Hides the whole review behind a spoiler
+    spoiler: bool
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ReviewVoteData {
+    review_id: i32,
+    user_id: i32,
+    /// 1 for an upvote, -1 for a downvote
+    #[schema(example = 1)]
+    vote: i32
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ReviewWithMarkup {
+    #[serde(flatten)]
+    #[schema(value_type = Review)]
+    review: Model,
+    /// Sanitized body with `||spoilers||` rendered as `<span class="spoiler">`.
+    /// For spoiler reviews the raw `body` is left empty and the whole body is a spoiler here
+    body_html: String
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    params(
+        ("book_id" = i32, Path, description = "Book id"),
+        ("sort" = Option<String>, Query, description = "One of newest (default), oldest, helpful, rate_high, rate_low")
+    ),
+    responses(
+        (status = 200, description = "Reviews of the book", body = Vec<ReviewWithMarkup>),
+        (status = 400, description = "Unknown sort", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/book/<book_id>?<sort>")]
+async fn get_book_reviews(
+    db: &State<DatabaseConnection>,
+    book_id: i32,
+    sort: Option<String>
+) -> Result<Json<Vec<ReviewWithMarkup>>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+    let query = visible_reviews().filter(Column::BookId.eq(book_id));
+
+    let query = match sort.as_deref().unwrap_or("newest") {
+        "newest" => query.order_by_desc(Column::CreatedAt),
+        "oldest" => query.order_by_asc(Column::CreatedAt),
+        "helpful" => query.order_by_desc(Column::Helpful).order_by_asc(Column::Unhelpful),
+        "rate_high" => query.order_by_desc(Column::Rate).order_by_desc(Column::CreatedAt),
+        "rate_low" => query.order_by_asc(Column::Rate).order_by_desc(Column::CreatedAt),
+        other => return Err(ApiError::new(
+            Status::BadRequest,
+            format!("Unknown sort {}, expected one of newest, oldest, helpful, rate_high, rate_low", other)
+        ))
+    };
+
+    let reviews = query.all(db).await;
+
+    match reviews {
+        Ok(result) => Ok(Json(result.into_iter().map(with_markup).collect())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    params(
+        ("id" = i32, Path, description = "Review id")
+    ),
+    responses(
+        (status = 200, description = "Review with the id", body = ReviewWithMarkup),
+        (status = 404, description = "No such review", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/<id>")]
+async fn get_review_by_id(
+    db: &State<DatabaseConnection>,
+    id: i32
+) -> Result<Json<ReviewWithMarkup>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+    let review = visible_reviews().filter(Column::Id.eq(id)).one(db).await;
+
+    match review {
+        Ok(Some(review)) => Ok(Json(with_markup(review))),
+        Ok(None) => Err(ApiError::new(Status::NotFound, format!("No review with id {}", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    request_body = ReviewData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 409, description = "The user has already reviewed the book", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/", data="<review_data>", format="json")]
+async fn create_review(
+    db: &State<DatabaseConnection>,
+    review_data: Json<ReviewData>,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    review_data.validate()?;
+
+    let is_review_exists = Review::find()
+        .filter(Column::UserId.eq(review_data.user_id))
+        .filter(Column::BookId.eq(review_data.book_id))
+        .one(db)
+        .await;
+
+    match is_review_exists {
+        Ok(Some(review)) => Err(ApiError::new(
+            Status::Conflict,
+            format!("User {} has already reviewed book {}", review.user_id, review.book_id)
+        )),
+        Ok(None) => {
+            let review: Result<Model, DbErr> = ActiveModel {
+                user_id: ActiveValue::set(review_data.user_id),
+                book_id: ActiveValue::set(review_data.book_id),
+                rate: ActiveValue::set(review_data.rate),
+                title: ActiveValue::set(review_data.title.clone()),
+                body: ActiveValue::set(review_data.body.clone()),
+                spoiler: ActiveValue::set(review_data.spoiler),
+                helpful: ActiveValue::set(0),
+                unhelpful: ActiveValue::set(0),
+                created_at: ActiveValue::set(Utc::now().naive_utc()),
+                edited_at: ActiveValue::set(None),
+                ..Default::default()
+            }.insert(db).await;
+
+            match review {
+                Ok(result) => match save_book_rate(db, result.book_id, result.user_id, result.rate).await {
+                    Ok(_) => Ok(Json(format!("Review {} was successfully created", result.title))),
+                    Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+                },
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+            }
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    params(
+        ("id" = i32, Path, description = "Review id")
+    ),
+    request_body = ReviewData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/<id>", data="<review_data>", format="json")]
+async fn update_review(
+    db: &State<DatabaseConnection>,
+    review_data: Json<ReviewData>,
+    id: i32,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    review_data.validate()?;
+
+    let updated_review = ActiveModel {
+        id: ActiveValue::set(id),
+        rate: ActiveValue::set(review_data.rate),
+        title: ActiveValue::set(review_data.title.clone()),
+        body: ActiveValue::set(review_data.body.clone()),
+        spoiler: ActiveValue::set(review_data.spoiler),
+        edited_at: ActiveValue::set(Some(Utc::now().naive_utc())),
+        ..Default::default()
+    }.update(db).await;
+
+    match updated_review {
+        Ok(result) => match save_book_rate(db, result.book_id, result.user_id, result.rate).await {
+            Ok(_) => Ok(Json(format!("Review {} was successfully updated", result.title))),
+            Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    params(
+        ("id" = i32, Path, description = "Review id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 404, description = "No such review", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[delete("/<id>")]
+async fn delete_review(
+    db: &State<DatabaseConnection>,
+    actor: Actor,
+    id: i32
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let rows_affected = remove_review(db, &actor, id).await?;
+
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
+}
+
+/// Deletes a review together with its rate and recomputes the book rating.
+pub(crate) async fn remove_review(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    let review = match Review::find_by_id(id).one(db).await {
+        Ok(Some(review)) => review,
+        Ok(None) => return Err(ApiError::new(Status::NotFound, format!("No review with id {}", id))),
+        Err(err) => return Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    };
+
+    let deleted_review = ActiveModel {
+        id: ActiveValue::set(id),
+        ..Default::default()
+    }.delete(db).await;
+
+    match deleted_review {
+        Ok(result) => {
+            // The review's rate lives in book_rate, so it goes away together with the review
+            let deleted_rate = book_rate::ActiveModel {
+                book_id: ActiveValue::set(review.book_id),
+                user_id: ActiveValue::set(review.user_id),
+                ..Default::default()
+            }.delete(db).await;
+
+            if let Err(err) = deleted_rate {
+                return Err(ApiError::new(Status::InternalServerError, err.to_string()));
+            }
+
+            audit::record(db, actor, audit::DELETE, "review", id, Some(audit::snapshot(&review)), None).await?;
+
+            match book_route::update_book_rating(db, review.book_id).await {
+                Ok(_) => Ok(result.rows_affected),
+                Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+            }
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    request_body = ReviewVoteData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 400, description = "The user votes for their own review", body = ApiError),
+        (status = 422, description = "Vote is not 1 or -1", body = ApiError),
+        (status = 404, description = "No such review", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/vote", data="<review_vote_data>", format="json")]
+async fn create_review_vote(
+    db: &State<DatabaseConnection>,
+    metrics: &State<Metrics>,
+    review_vote_data: Json<ReviewVoteData>,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    check_review_vote(db, &review_vote_data).await?;
+
+    let review_vote: Result<review_vote::Model, DbErr> = review_vote::ActiveModel {
+        review_id: ActiveValue::set(review_vote_data.review_id),
+        user_id: ActiveValue::set(review_vote_data.user_id),
+        vote: ActiveValue::set(review_vote_data.vote),
+    }.insert(db).await;
+
+    match review_vote {
+        Ok(_) => match update_review_votes(db, review_vote_data.review_id).await {
+            Ok(_) => {
+                metrics.vote(metrics::VOTE_REVIEW);
+
+                Ok(Json("Review vote was successfully created".to_string()))
+            },
+            Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    request_body = ReviewVoteData,
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 400, description = "The user votes for their own review", body = ApiError),
+        (status = 422, description = "Vote is not 1 or -1", body = ApiError),
+        (status = 404, description = "No such review", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/vote", data="<review_vote_data>", format="json")]
+async fn update_review_vote(
+    db: &State<DatabaseConnection>,
+    metrics: &State<Metrics>,
+    review_vote_data: Json<ReviewVoteData>,
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    check_review_vote(db, &review_vote_data).await?;
+
+    let review_vote: Result<review_vote::Model, DbErr> = review_vote::ActiveModel {
+        review_id: ActiveValue::set(review_vote_data.review_id),
+        user_id: ActiveValue::set(review_vote_data.user_id),
+        vote: ActiveValue::set(review_vote_data.vote),
+    }.update(db).await;
+
+    match review_vote {
+        Ok(_) => match update_review_votes(db, review_vote_data.review_id).await {
+            Ok(_) => {
+                metrics.vote(metrics::VOTE_REVIEW);
+
+                Ok(Json("Review vote was successfully updated".to_string()))
+            },
+            Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/review",
+    tag = "review",
+    params(
+        ("review_id" = i32, Path, description = "Review id"),
+        ("user_id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Deletion message", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[delete("/vote/<review_id>/<user_id>")]
+async fn delete_review_vote(
+    db: &State<DatabaseConnection>,
+    review_id: i32,
+    user_id: i32
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let deleted_review_vote = review_vote::ActiveModel {
+        review_id: ActiveValue::set(review_id),
+        user_id: ActiveValue::set(user_id),
+        ..Default::default()
+    }.delete(db).await;
+
+    match deleted_review_vote {
+        Ok(_) => match update_review_votes(db, review_id).await {
+            Ok(_) => Ok(Json("Review vote was successfully deleted".to_string())),
+            Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+/// Reviews whose book and author are not in the trash.
+fn visible_reviews() -> Select<Review> {
+    Review::find()
+        .inner_join(Book)
+        .inner_join(User)
+        .filter(book::Column::DeletedAt.is_null())
+        .filter(user::Column::DeletedAt.is_null())
+}
+
+/// Reviews flagged as spoilers are hidden as a whole, their raw `body` is left empty.
+fn with_markup(mut review: Model) -> ReviewWithMarkup {
+    let body_html = if review.spoiler {
+        markup::render_hidden(&std::mem::take(&mut review.body))
+    } else {
+        markup::render(&review.body)
+    };
+
+    ReviewWithMarkup {
+        review,
+        body_html
+    }
+}
+
+async fn check_review_vote(
+    db: &DatabaseConnection,
+    review_vote_data: &ReviewVoteData
+) -> Result<(), ApiError> {
+    if review_vote_data.vote != 1 && review_vote_data.vote != -1 {
+        return Err(ApiError::field("vote", "must be 1 or -1"))
+    }
+
+    match Review::find_by_id(review_vote_data.review_id).one(db).await {
+        Ok(Some(review)) if review.user_id == review_vote_data.user_id => Err(ApiError::new(
+            Status::BadRequest,
+            "Users can not vote for their own reviews".to_string()
+        )),
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(ApiError::new(
+            Status::NotFound,
+            format!("No review with id {}", review_vote_data.review_id)
+        )),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+/// Recounts the helpful/unhelpful votes stored on the review.
+pub async fn update_review_votes(db: &DatabaseConnection, review_id: i32) -> Result<(), DbErr> {
+    let votes = ReviewVote::find()
+        .filter(review_vote::Column::ReviewId.eq(review_id))
+        .all(db)
+        .await?;
+
+    ActiveModel {
+        id: ActiveValue::set(review_id),
+        helpful: ActiveValue::set(votes.iter().filter(|vote| vote.vote == 1).count() as i32),
+        unhelpful: ActiveValue::set(votes.iter().filter(|vote| vote.vote == -1).count() as i32),
+        ..Default::default()
+    }.update(db).await?;
+
+    Ok(())
+}
+
+/// Stores the review's rate as the user's `book_rate`, so `book.rating` counts
+/// reviewed and bare rates the same way.
+async fn save_book_rate(db: &DatabaseConnection, book_id: i32, user_id: i32, rate: i32) -> Result<(), DbErr> {
+    let book_rate = book_rate::ActiveModel {
+        book_id: ActiveValue::set(book_id),
+        user_id: ActiveValue::set(user_id),
+        rate: ActiveValue::set(rate),
+        ..Default::default()
+    };
+
+    let is_rate_exists = BookRate::find_by_id((book_id, user_id)).one(db).await?;
+
+    match is_rate_exists {
+        Some(_) => book_rate.update(db).await?,
+        None => book_rate.insert(db).await?
+    };
+
+    book_route::update_book_rating(db, book_id).await?;
+
+    Ok(())
+}
+
+/// Keeps the rate of an existing review in line with a bare `book_rate` change.
+pub(crate) async fn sync_review_rate(db: &DatabaseConnection, book_id: i32, user_id: i32, rate: i32) -> Result<(), DbErr> {
+    Review::update_many()
+        .col_expr(Column::Rate, Expr::value(rate))
+        .filter(Column::BookId.eq(book_id))
+        .filter(Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns whether the user's rate of the book belongs to a review.
+pub(crate) async fn has_review(db: &DatabaseConnection, book_id: i32, user_id: i32) -> Result<bool, DbErr> {
+    let review = Review::find()
+        .filter(Column::BookId.eq(book_id))
+        .filter(Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+
+    Ok(review.is_some())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_book_reviews,
+        get_review_by_id,
+        create_review,
+        update_review,
+        delete_review,
+        create_review_vote,
+        update_review_vote,
+        delete_review_vote
+    ),
+    components(schemas(ReviewData, ReviewVoteData, ReviewWithMarkup))
+)]
+pub struct ReviewApi;
+
+pub fn get_all_review_methods() -> Vec<rocket::Route> {
+    routes![
+        get_book_reviews,
+        get_review_by_id,
+        create_review,
+        update_review,
+        delete_review,
+        create_review_vote,
+        update_review_vote,
+        delete_review_vote
+    ]
+}
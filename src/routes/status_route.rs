@@ -0,0 +1,200 @@
+use rocket::serde::json::Json;
+use serde::Serialize;
+use utoipa::{OpenApi, ToSchema};
+
+use rocket::http::{ContentType, Status};
+use rocket::tokio::fs;
+
+use rocket::State;
+
+use crate::error::ApiError;
+use crate::metrics::Metrics;
+use crate::setup;
+
+use sea_orm::DatabaseConnection;
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SchemaStatus {
+    /// Last applied migration, absent on an empty database
+    #[schema(example = "m20240609_120000_add_role_to_user")]
+    schema_version: Option<String>,
+    /// Migrations known to this build that the database has not applied yet
+    pending_migrations: Vec<String>
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/status",
+    tag = "status",
+    responses(
+        (status = 200, description = "Schema version of the database", body = SchemaStatus),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/")]
+async fn get_status(
+    db: &State<DatabaseConnection>
+) -> Result<Json<SchemaStatus>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let schema_version = setup::schema_version(db).await?;
+    let pending_migrations = setup::pending_migrations(db).await?;
+
+    Ok(Json(SchemaStatus { schema_version, pending_migrations }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Health {
+    #[schema(example = "ok")]
+    status: &'static str
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Readiness {
+    ready: bool,
+    /// `ok` or the reason the database can not be reached
+    #[schema(example = "ok")]
+    database: String,
+    /// `ok` or the reason files can not be written to the storage directory
+    #[schema(example = "ok")]
+    storage: String,
+    /// `ok` or the pending migrations
+    #[schema(example = "ok")]
+    migrations: String
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Version {
+    #[schema(example = "0.1.0")]
+    version: &'static str,
+    /// Commit the server was built from, `unknown` outside a git checkout
+    #[schema(example = "de7288a")]
+    git_hash: &'static str,
+    /// Last applied migration, absent when the database is empty or unreachable
+    #[schema(example = "m20240609_120000_add_role_to_user")]
+    schema_version: Option<String>
+}
+
+const STORAGE_PROBE: &str = "storage/.ready";
+
+fn check(result: Result<(), String>) -> String {
+    match result {
+        Ok(()) => "ok".to_string(),
+        Err(err) => err
+    }
+}
+
+async fn check_storage() -> Result<(), String> {
+    fs::write(STORAGE_PROBE, b"").await.map_err(|err| err.to_string())?;
+    fs::remove_file(STORAGE_PROBE).await.map_err(|err| err.to_string())
+}
+
+async fn check_migrations(db: &DatabaseConnection) -> Result<(), String> {
+    match setup::pending_migrations(db).await {
+        Ok(pending) if pending.is_empty() => Ok(()),
+        Ok(pending) => Err(format!("pending: {}", pending.join(", "))),
+        Err(err) => Err(err.to_string())
+    }
+}
+
+#[utoipa::path(
+    tag = "status",
+    responses(
+        (status = 200, description = "The process is alive", body = Health)
+    ),
+)]
+#[get("/health")]
+fn get_health() -> Json<Health> {
+    Json(Health { status: "ok" })
+}
+
+#[utoipa::path(
+    tag = "status",
+    responses(
+        (status = 200, description = "The server can take requests", body = Readiness),
+        (status = 503, description = "A dependency is unavailable, see the failing check", body = Readiness)
+    ),
+)]
+#[get("/ready")]
+async fn get_ready(
+    db: &State<DatabaseConnection>
+) -> (Status, Json<Readiness>) {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let database = db.ping().await.map_err(|err| err.to_string());
+    let migrations = match database {
+        Ok(()) => check_migrations(db).await,
+        Err(_) => Err("database unavailable".to_string())
+    };
+    let storage = check_storage().await;
+
+    let ready = database.is_ok() && storage.is_ok() && migrations.is_ok();
+    let status = if ready { Status::Ok } else { Status::ServiceUnavailable };
+
+    (status, Json(Readiness {
+        ready,
+        database: check(database),
+        storage: check(storage),
+        migrations: check(migrations)
+    }))
+}
+
+#[utoipa::path(
+    tag = "status",
+    responses(
+        (status = 200, description = "Build and schema version", body = Version)
+    ),
+)]
+#[get("/version")]
+async fn get_version(
+    db: &State<DatabaseConnection>
+) -> Json<Version> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let schema_version = setup::schema_version(db).await.unwrap_or_default();
+
+    Json(Version {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("EGLINE_GIT_HASH").unwrap_or("unknown"),
+        schema_version
+    })
+}
+
+#[utoipa::path(
+    tag = "status",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain; version=0.0.4")
+    ),
+)]
+#[get("/metrics")]
+fn get_metrics(
+    db: &State<DatabaseConnection>,
+    metrics: &State<Metrics>
+) -> (ContentType, String) {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    (ContentType::new("text", "plain").with_params(("version", "0.0.4")), metrics.render(db))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_status,
+        get_health,
+        get_ready,
+        get_version,
+        get_metrics
+    ),
+    components(
+        schemas(SchemaStatus, Health, Readiness, Version)
+    )
+)]
+pub struct StatusApi;
+
+pub fn get_all_status_methods() -> Vec<rocket::Route> {
+    routes![get_status]
+}
+
+/// Probes for orchestrators and uptime monitors and the Prometheus scrape target, mounted at the root
+pub fn get_all_probe_methods() -> Vec<rocket::Route> {
+    routes![get_health, get_ready, get_version, get_metrics]
+}
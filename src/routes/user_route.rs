@@ -1,122 +1,365 @@
+use rocket::form::Form;
 use rocket::serde::json::{Json, serde_json};
 use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use validator::{Validate, ValidationError};
 
 use serde_json::json;
 use json_value_remove::Remove;
 
-use rocket::response::status;
 use rocket::http::Status;
 
 use rocket::State;
 
+use crate::audit::{self, Actor};
+use crate::error::ApiError;
+use crate::images::{self, ImageResponse, ImageSize, ImageUpload};
+use crate::auth::{self, AuthUser};
+use crate::metrics::{self, Metrics};
 use crate::entities::user::{Model, ActiveModel, Column};
-use crate::entities::prelude::{User, Book};
+use crate::entities::prelude::{User, Book, Chapter, ReadingProgress};
+use crate::entities::{book, reading_progress};
 
-use sea_orm::{prelude::DbErr, ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    prelude::{DateTime, DbErr}, ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter
+};
 
 use sha256::digest;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct UserAuthModel {
+const MIN_PASSWORD_LENGTH: usize = 6;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UserData {
+    #[validate(email(message = "must be a valid email"))]
+    #[schema(example = "reader@egline.com")]
+    email: String,
+    #[validate(length(min = 3, max = 32, message = "must be 3 to 32 characters long"))]
+    #[schema(example = "reader")]
+    display_name: String,
+    #[validate(length(min = 6, message = "must be at least 6 characters long"))]
+    password: String,
+    #[schema(value_type = Object)]
+    saved_books: serde_json::Value
+}
+
+impl UserData {
+    /// New user without saved books
+    pub fn new(email: String, display_name: String, password: String) -> UserData {
+        UserData {
+            email,
+            display_name,
+            password,
+            saved_books: json!({})
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+struct UserUpdateData {
+    #[validate(email(message = "must be a valid email"))]
+    #[schema(example = "reader@egline.com")]
     email: String,
+    #[validate(length(min = 3, max = 32, message = "must be 3 to 32 characters long"))]
+    #[schema(example = "reader")]
+    display_name: String,
+    /// New password, empty to keep the current one
+    #[validate(custom = "validate_new_password")]
+    password: String,
+    #[schema(value_type = Object)]
+    saved_books: serde_json::Value
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate, FromForm)]
+pub(crate) struct UserAuthModel {
+    #[validate(email(message = "must be a valid email"))]
+    #[schema(example = "reader@egline.com")]
+    pub(crate) email: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    #[schema(example = "secret")]
     password: String
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 struct UserEditModel {
+    #[validate(email(message = "must be a valid email"))]
+    #[schema(example = "reader@egline.com")]
     email: String,
+    #[validate(length(min = 3, max = 32, message = "must be 3 to 32 characters long"))]
+    #[schema(example = "reader")]
     display_name: String,
+    /// Current password, required to change it and checked whenever it is given
     password: String,
-    new_password: String,
-    avatar: Vec<i8>
+    /// New password, empty to keep the current one
+    #[validate(custom = "validate_new_password")]
+    new_password: String
+}
+
+/// Role change of the admin panel
+#[derive(Debug, Serialize, Deserialize, Validate, FromForm)]
+pub struct UserRoleData {
+    #[validate(custom = "validate_role")]
+    role: String
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct UserWithoutPassword {
+impl UserRoleData {
+    pub fn new(role: String) -> UserRoleData {
+        UserRoleData { role }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct UserWithoutPassword {
     id: i32,
     email: String,
     display_name: String,
-    avatar: Vec<u8>,
+    /// `reader` or `admin`
+    #[schema(example = "reader")]
+    role: String,
+    /// `/api/v1/user/<id>/avatar`, absent when the user has no avatar
+    avatar_url: Option<String>,
+    /// Tabs of saved book ids, e.g. `{"Reading": [1, 2]}`
+    #[schema(value_type = Object)]
     saved_books: serde_json::Value,
+    created_at: DateTime,
+    updated_at: DateTime,
+}
+
+impl From<Model> for UserWithoutPassword {
+    fn from(user: Model) -> UserWithoutPassword {
+        UserWithoutPassword {
+            avatar_url: images::image_url(format!("/api/v1/user/{}/avatar", user.id), &user.avatar),
+            id: user.id,
+            email: user.email,
+            display_name: user.display_name,
+            role: user.role,
+            saved_books: user.saved_books,
+            created_at: user.created_at,
+            updated_at: user.updated_at
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct UserWithToken {
+    #[serde(flatten)]
+    user: UserWithoutPassword,
+    token: String
 }
 
 #[utoipa::path(
-    context_path = "/user",
+    context_path = "/api/v1/user",
+    tag = "user",
     responses(
-        (status = 200, description = "All users", body = Vec<Model>),
-        (status = 500, description = "No users", body = String)
+        (status = 200, description = "All users", body = Vec<UserWithoutPassword>),
+        (status = 500, description = "Database error", body = ApiError)
     ),
 )]
 #[get("/")]
 async fn get_all_users(
     db: &State<DatabaseConnection>
-) -> Result<Json<Vec<Model>>, status::Custom<String>> {
+) -> Result<Json<Vec<UserWithoutPassword>>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let users = User::find().all(db).await;
+    let users = User::find().filter(Column::DeletedAt.is_null()).all(db).await;
 
     match users {
-        Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => Ok(Json(result.into_iter().map(UserWithoutPassword::from).collect())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "User with the id, or an empty user with id -1", body = UserWithoutPassword),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[get("/<id>")]
 async fn get_user_by_id(
     db: &State<DatabaseConnection>,
     id: i32
-) -> Result<Json<Model>, status::Custom<String>> {
+) -> Result<Json<UserWithoutPassword>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let user = User::find_by_id(id).one(db).await;
+    let user = User::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await;
 
     match user {
-        Ok(Some(user)) => Ok(Json(user)),
+        Ok(Some(user)) => Ok(Json(user.into())),
         Ok(None) => {
-            let empty_user = Model {
+            let empty_user = UserWithoutPassword {
                 id: -1,
                 display_name: String::new(),
                 email: String::new(),
-                password: String::new(),
-                avatar: Vec::new(),
+                role: String::new(),
+                avatar_url: None,
                 saved_books: json!(""),
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
             };
             Ok(Json(empty_user))
         }
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("size" = Option<String>, Query, description = "One of original (default), small, medium, large")
+    ),
+    responses(
+        (status = 200, description = "Avatar image", content_type = "image/*"),
+        (status = 304, description = "The avatar matches If-None-Match"),
+        (status = 400, description = "Unknown size", body = ApiError),
+        (status = 404, description = "No such user or the user has no avatar", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+// Ranked below `/progress/<user_id>`, which would otherwise collide with `/progress/avatar`
+#[get("/<id>/avatar?<size>", rank = 2)]
+async fn get_user_avatar(
+    db: &State<DatabaseConnection>,
+    id: i32,
+    size: Option<&str>
+) -> Result<ImageResponse, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+    let size = ImageSize::parse(size)?;
+
+    match User::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await {
+        Ok(Some(user)) => images::serve("user", id, user.avatar, size).await,
+        Ok(None) => Err(ApiError::new(Status::NotFound, format!("No user with id {}", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Update message", body = String),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 403, description = "The avatar of another user", body = ApiError),
+        (status = 404, description = "No such user", body = ApiError),
+        (status = 413, description = "The image is larger than 5 MiB", body = ApiError),
+        (status = 422, description = "The file is not a supported image", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/<id>/avatar", data = "<upload>")]
+async fn upload_user_avatar(
+    db: &State<DatabaseConnection>,
+    user: AuthUser,
+    id: i32,
+    upload: Form<ImageUpload<'_>>
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    if user.id != id {
+        return Err(ApiError::new(Status::Forbidden, "Users can only change their own avatar".to_string()));
+    }
+
+    if User::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await?.is_none() {
+        return Err(ApiError::new(Status::NotFound, format!("No user with id {}", id)));
+    }
+
+    let image = images::normalize(&upload, images::AVATAR_SIDE).await?;
+
+    let updated_user = ActiveModel {
+        id: ActiveValue::set(id),
+        avatar: ActiveValue::set(image),
+        ..Default::default()
+    }.update(db).await;
+
+    match updated_user {
+        Ok(_) => Ok(Json(format!("Avatar of user {} was updated", id))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    request_body = UserData,
+    responses(
+        (status = 200, description = "Creation message", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/", data="<user_data>", format="json")]
 async fn create_user(
     db: &State<DatabaseConnection>,
-    user_data: Json<Model>,
-) -> Result<Json<String>, status::Custom<String>> {
+    user_data: Json<UserData>,
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    insert_user(db, &user_data, auth::ROLE_READER).await?;
+
+    Ok(Json(format!("User {} was successfully created", user_data.display_name.clone())))
+}
+
+/// Registers a user with the role, shared by the JSON API and `egline-admin`.
+pub async fn insert_user(db: &DatabaseConnection, user_data: &UserData, role: &str) -> Result<Model, ApiError> {
+    user_data.validate()?;
+    check_unique_user(db, &user_data.email, &user_data.display_name, None).await?;
+
     let hashed_password: String = digest(user_data.password.clone());
 
     let user:Result<Model, DbErr> = ActiveModel {
         display_name: ActiveValue::set(user_data.display_name.clone()),
         email: ActiveValue::set(user_data.email.clone().to_lowercase()),
         password: ActiveValue::set(hashed_password),
-        avatar: ActiveValue::set(user_data.avatar.clone()),
+        avatar: ActiveValue::set(Vec::new()),
         saved_books: ActiveValue::set(json!(user_data.saved_books.clone())),
+        role: ActiveValue::set(role.to_string()),
         ..Default::default()
     }.insert(db).await;
 
-    match user {
-        Ok(_) => Ok(Json(format!("User {} was successfully created", user_data.display_name.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
-    }
+    user.map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = UserUpdateData,
+    responses(
+        (status = 200, description = "Updated user", body = UserWithoutPassword),
+        (status = 404, description = "No such user", body = ApiError),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[put("/<id>", data="<user_data>", format="json")]
 async fn update_user(
     db: &State<DatabaseConnection>,
-    user_data: Json<Model>,
+    user_data: Json<UserUpdateData>,
     id: i32,
-) -> Result<Json<Model>, status::Custom<String>> {
+) -> Result<Json<UserWithoutPassword>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
-    let user = User::find_by_id(id).one(db).await.unwrap().expect(format!("No such user with id {}", id).as_str());
+
+    user_data.validate()?;
+    check_unique_user(db, &user_data.email, &user_data.display_name, Some(id)).await?;
+
+    let user = match User::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await? {
+        Some(user) => user,
+        None => return Err(ApiError::new(Status::NotFound, format!("No user with id {}", id)))
+    };
 
     let hashed_password = if user_data.password.is_empty() {
         user.password
@@ -129,64 +372,166 @@ async fn update_user(
         display_name: ActiveValue::set(user_data.display_name.clone()),
         email: ActiveValue::set(user_data.email.clone().to_lowercase()),
         password: ActiveValue::set(hashed_password),
-        avatar: ActiveValue::set(
-            if user_data.avatar.clone().len() == 0 {
-                user.avatar.clone()
-            } else {
-                user_data.avatar.clone()
-            }
-        ),
         saved_books: ActiveValue::set(json!(user_data.saved_books.clone())),
         ..Default::default()
     }.update(db).await;
 
     match updated_user {
-        Ok(result) => Ok(Json(result)),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Ok(result) => Ok(Json(result.into())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Number of deleted entries", body = String),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[delete("/<id>")]
 async fn delete_user(
     db: &State<DatabaseConnection>,
+    actor: Actor,
     id: i32
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let deleted_user = ActiveModel {
+    let rows_affected = remove_user(db, &actor, id).await?;
+
+    Ok(Json(format!("Number of deleted entries: {}", rows_affected)))
+}
+
+/// Moves a user to the trash and ends their sessions. Their email and display name stay taken
+/// until the retention purge removes them for good.
+pub(crate) async fn remove_user(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    let rows_affected = set_user_deleted_at(db, actor, id, Some(Utc::now().naive_utc())).await?;
+
+    auth::delete_user_sessions(db, id).await?;
+
+    Ok(rows_affected)
+}
+
+/// Takes a user out of the trash, they sign in again with their old password.
+pub(crate) async fn restore_user(db: &DatabaseConnection, actor: &Actor, id: i32) -> Result<u64, ApiError> {
+    set_user_deleted_at(db, actor, id, None).await
+}
+
+async fn set_user_deleted_at(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: i32,
+    deleted_at: Option<DateTime>
+) -> Result<u64, ApiError> {
+    let updated_users = User::update_many()
+        .col_expr(Column::DeletedAt, Expr::value(deleted_at))
+        .filter(Column::Id.eq(id))
+        .filter(match deleted_at {
+            Some(_) => Column::DeletedAt.is_null(),
+            None => Column::DeletedAt.is_not_null()
+        })
+        .exec(db)
+        .await;
+
+    match updated_users {
+        Ok(result) => {
+            if result.rows_affected > 0 {
+                let user = User::find_by_id(id).one(db).await?;
+                audit::record_trash(db, actor, "user", id, deleted_at.is_some(), user).await?;
+            }
+
+            Ok(result.rows_affected)
+        },
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+pub async fn set_user_role(
+    db: &DatabaseConnection,
+    actor: &Actor,
+    id: i32,
+    role_data: &UserRoleData
+) -> Result<Model, ApiError> {
+    role_data.validate()?;
+
+    let before = User::find_by_id(id).one(db).await?;
+
+    let updated_user = ActiveModel {
         id: ActiveValue::set(id),
+        role: ActiveValue::set(role_data.role.clone()),
         ..Default::default()
-    }.delete(db).await;
+    }.update(db).await;
+
+    let user = updated_user.map_err(|err| ApiError::new(Status::InternalServerError, err.to_string()))?;
+
+    let before = before.as_ref().map(audit::snapshot);
+    audit::record(db, actor, audit::UPDATE, "user", id, before, Some(audit::snapshot(&user))).await?;
 
-    match deleted_user {
-        Ok(result) => Ok(Json(format!("Number of deleted entries: {}", result.rows_affected))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+    Ok(user)
+}
+
+/// Sets a new password and signs the user out everywhere.
+pub async fn reset_password(db: &DatabaseConnection, id: i32, password: &str) -> Result<Model, ApiError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ApiError::field("password", "must be at least 6 characters long"));
     }
+
+    let updated_user = ActiveModel {
+        id: ActiveValue::set(id),
+        password: ActiveValue::set(digest(password)),
+        ..Default::default()
+    }.update(db).await?;
+
+    auth::delete_user_sessions(db, id).await?;
+
+    Ok(updated_user)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 struct SaveBook {
     user_id: i32,
     book_id: i32,
+    #[validate(length(min = 1, max = 64, message = "must be 1 to 64 characters long"))]
+    #[schema(example = "Reading")]
     tab_name: String
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    request_body = SaveBook,
+    responses(
+        (status = 200, description = "Tab was updated", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "No such user or book, or database error", body = ApiError)
+    ),
+)]
 #[post("/save-book", data="<save_book_data>", format="json")]
 async fn add_book_to_tab(
     db: &State<DatabaseConnection>,
     save_book_data: Json<SaveBook>,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let user = User::find_by_id(save_book_data.user_id).all(db).await.unwrap();
-    let book = Book::find_by_id(save_book_data.book_id).all(db).await.unwrap();
+    save_book_data.validate()?;
+
+    let user = User::find_by_id(save_book_data.user_id).filter(Column::DeletedAt.is_null()).all(db).await.unwrap();
+    let book = Book::find_by_id(save_book_data.book_id)
+        .filter(book::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .unwrap();
 
     if user.len() == 0 {
-        return Err(status::Custom(Status::InternalServerError, format!("No user with id {}", save_book_data.user_id)))
+        return Err(ApiError::new(Status::InternalServerError, format!("No user with id {}", save_book_data.user_id)))
     }
 
     if book.len() == 0 {
-        return Err(status::Custom(Status::InternalServerError, format!("No book with id {}", save_book_data.book_id)))
+        return Err(ApiError::new(Status::InternalServerError, format!("No book with id {}", save_book_data.book_id)))
     }
 
     let tab_name = save_book_data.tab_name.clone();
@@ -213,26 +558,42 @@ async fn add_book_to_tab(
 
     match updated_user {
         Ok(_) => Ok(Json(format!("Tab {} was updated", tab_name.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    request_body = SaveBook,
+    responses(
+        (status = 200, description = "Tab was updated", body = String),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 500, description = "No such user, book or tab, or database error", body = ApiError)
+    ),
+)]
 #[delete("/delete-book", data="<save_book_data>", format="json")]
 async fn delete_book_from_tab(
     db: &State<DatabaseConnection>,
     save_book_data: Json<SaveBook>,
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let user = User::find_by_id(save_book_data.user_id).all(db).await.unwrap();
-    let book = Book::find_by_id(save_book_data.book_id).all(db).await.unwrap();
+    save_book_data.validate()?;
+
+    let user = User::find_by_id(save_book_data.user_id).filter(Column::DeletedAt.is_null()).all(db).await.unwrap();
+    let book = Book::find_by_id(save_book_data.book_id)
+        .filter(book::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .unwrap();
 
     if user.len() == 0 {
-        return Err(status::Custom(Status::InternalServerError, format!("No user with id {}", save_book_data.user_id)))
+        return Err(ApiError::new(Status::InternalServerError, format!("No user with id {}", save_book_data.user_id)))
     }
 
     if book.len() == 0 {
-        return Err(status::Custom(Status::InternalServerError, format!("No book with id {}", save_book_data.book_id)))
+        return Err(ApiError::new(Status::InternalServerError, format!("No book with id {}", save_book_data.book_id)))
     }
 
     let tab_name = save_book_data.tab_name.clone();
@@ -248,7 +609,7 @@ async fn delete_book_from_tab(
             }
         }
         None => {
-            return Err(status::Custom(
+            return Err(ApiError::new(
                 Status::InternalServerError,
                 format!("User with {} id does not have tab {}", save_book_data.user_id, save_book_data.tab_name)
             ));
@@ -263,22 +624,34 @@ async fn delete_book_from_tab(
 
     match updated_user {
         Ok(_) => Ok(Json(format!("Tab {} was updated", tab_name.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("user_id" = i32, Path, description = "User id"),
+        ("tab_name" = String, Path, description = "Name of the new tab")
+    ),
+    responses(
+        (status = 200, description = "Tab was added", body = String),
+        (status = 500, description = "No such user, the tab already exists or database error", body = ApiError)
+    ),
+)]
 #[post("/tab/<user_id>/<tab_name>")]
 async fn add_tab_to_user(
     db: &State<DatabaseConnection>,
     user_id: i32,
     tab_name: String
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let user = User::find_by_id(user_id.clone()).all(db).await.unwrap();
+    let user = User::find_by_id(user_id).filter(Column::DeletedAt.is_null()).all(db).await.unwrap();
 
     if user.len() == 0 {
-        return Err(status::Custom(Status::InternalServerError, format!("No user with id {}", user_id)))
+        return Err(ApiError::new(Status::InternalServerError, format!("No user with id {}", user_id)))
     }
 
     let mut saved_books = user[0].saved_books.clone();
@@ -286,7 +659,7 @@ async fn add_tab_to_user(
 
     match tab_array {
         Some(_) => {
-            return Err(status::Custom(
+            return Err(ApiError::new(
                 Status::InternalServerError,
                 format!("User with {} id already has {} tab", user_id, tab_name)
             ));
@@ -304,22 +677,34 @@ async fn add_tab_to_user(
 
     match updated_user {
         Ok(_) => Ok(Json(format!("Tab {} was successfully added to user with {} id", tab_name.clone(), user_id.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("user_id" = i32, Path, description = "User id"),
+        ("tab_name" = String, Path, description = "Name of the tab")
+    ),
+    responses(
+        (status = 200, description = "Tab was deleted", body = String),
+        (status = 500, description = "No such user or tab, or database error", body = ApiError)
+    ),
+)]
 #[delete("/tab/<user_id>/<tab_name>")]
 async fn delete_user_tab(
     db: &State<DatabaseConnection>,
     user_id: i32,
     tab_name: String
-) -> Result<Json<String>, status::Custom<String>> {
+) -> Result<Json<String>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let user = User::find_by_id(user_id.clone()).all(db).await.unwrap();
+    let user = User::find_by_id(user_id).filter(Column::DeletedAt.is_null()).all(db).await.unwrap();
 
     if user.len() == 0 {
-        return Err(status::Custom(Status::InternalServerError, format!("No user with id {}", user_id)))
+        return Err(ApiError::new(Status::InternalServerError, format!("No user with id {}", user_id)))
     }
 
     let mut saved_books = user[0].saved_books.clone();
@@ -330,7 +715,7 @@ async fn delete_user_tab(
             let _ = saved_books.remove(format!("/{}", tab_name.clone()).as_str());
         }
         None => {
-            return Err(status::Custom(
+            return Err(ApiError::new(
                 Status::InternalServerError,
                 format!("User with id {} doesn't have {} tab", user_id, tab_name)
             ));
@@ -345,66 +730,125 @@ async fn delete_user_tab(
 
     match updated_user {
         Ok(_) => Ok(Json(format!("Tab {} was successfully added to user with {} id", tab_name.clone(), user_id.clone()))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    request_body = UserAuthModel,
+    responses(
+        (status = 200, description = "Logged in user with a session token", body = UserWithToken),
+        (status = 422, description = "Invalid fields", body = ApiError),
+        (status = 400, description = "Email or password are not valid", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/login", data = "<user_auth_data>", format = "json")]
 async fn login_user(
     db: &State<DatabaseConnection>,
+    metrics: &State<Metrics>,
     user_auth_data: Json<UserAuthModel>
-) -> Result<Json<UserWithoutPassword>, status::Custom<String>> {
+) -> Result<Json<UserWithToken>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match find_by_credentials(db, &user_auth_data).await? {
+        Some(result) => {
+            let token = match auth::create_session(db, result.id).await {
+                Ok(token) => token,
+                Err(err) => return Err(ApiError::new(Status::InternalServerError, err.to_string()))
+            };
+
+            metrics.login(metrics::LOGIN_API);
+
+            Ok(Json(UserWithToken { user: result.into(), token }))
+        },
+        None => Err(ApiError::new(Status::BadRequest, "Email or passwrod are not valid".to_string()))
+    }
+}
+
+/// User with the email and password, shared by the JSON login and the admin panel login.
+pub(crate) async fn find_by_credentials(db: &DatabaseConnection, user_auth_data: &UserAuthModel) -> Result<Option<Model>, ApiError> {
+    user_auth_data.validate()?;
+
     let hashed_password = digest(user_auth_data.password.clone());
 
     let user = User::find()
         .filter(Column::Email.eq(user_auth_data.email.clone()))
         .filter(Column::Password.eq(hashed_password))
+        .filter(Column::DeletedAt.is_null())
         .one(db)
-        .await;
+        .await?;
 
-    match user {
-        Ok(Some(result)) => {
-            let user_wo_password = UserWithoutPassword {
-                id: result.id,
-                email: result.email,
-                display_name: result.display_name,
-                avatar: result.avatar,
-                saved_books: result.saved_books
-            };
+    Ok(user)
+}
 
-            Ok(Json(user_wo_password))
-        },
-        Ok(None) => Err(status::Custom(Status::BadRequest, format!("Email or passwrod are not valid"))),
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    security(("session_token" = [])),
+    responses(
+        (status = 200, description = "Number of deleted sessions", body = String),
+        (status = 401, description = "Missing or invalid session token", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[post("/logout")]
+async fn logout_user(
+    db: &State<DatabaseConnection>,
+    user: AuthUser
+) -> Result<Json<String>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    match auth::delete_session(db, user.token).await {
+        Ok(rows_affected) => Ok(Json(format!("Number of deleted entries: {}", rows_affected))),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
     }
 }
 
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = UserEditModel,
+    responses(
+        (status = 200, description = "Edited user", body = UserWithoutPassword),
+        (status = 404, description = "No such user", body = ApiError),
+        (status = 422, description = "Invalid fields or wrong current password", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
 #[post("/edit/<id>", data = "<user_edit_data>", format = "json")]
 async fn edit_user(
     db: &State<DatabaseConnection>,
     id: i32,
     user_edit_data: Json<UserEditModel>
-) -> Result<Json<UserWithoutPassword>, status::Custom<String>> {
+) -> Result<Json<UserWithoutPassword>, ApiError> {
     let db: &DatabaseConnection = db as &DatabaseConnection;
 
-    let user = User::find_by_id(id).all(db).await.unwrap();
+    user_edit_data.validate()?;
+    check_unique_user(db, &user_edit_data.email, &user_edit_data.display_name, Some(id)).await?;
 
-    if user.len() == 0 {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            format!("No such user with id {}", id)
-        ))
-    }
+    let user = match User::find_by_id(id).filter(Column::DeletedAt.is_null()).one(db).await? {
+        Some(user) => user,
+        None => return Err(ApiError::new(Status::NotFound, format!("No user with id {}", id)))
+    };
 
     let hashed_old_password = digest(user_edit_data.password.clone());
     let hashed_new_password = digest(user_edit_data.new_password.clone());
 
-    if user[0].password.clone() != hashed_old_password.clone() && user_edit_data.password.clone().len() != 0 {
-        return Err(status::Custom(
-            Status::InternalServerError,
-            format!("Passwords missmatching")
-        ))
+    let changes_password = !user_edit_data.new_password.is_empty();
+    if (changes_password || !user_edit_data.password.is_empty()) && user.password != hashed_old_password {
+        let message = if user_edit_data.password.is_empty() {
+            "is required to change the password"
+        } else {
+            "does not match the current password"
+        };
+
+        return Err(ApiError::field("password", message))
     }
 
     let updated_user = ActiveModel {
@@ -413,51 +857,200 @@ async fn edit_user(
         display_name: ActiveValue::set(user_edit_data.display_name.clone()),
         password: ActiveValue::set(
         if user_edit_data.new_password.clone().len() == 0 {
-            user[0].password.clone()
+            user.password.clone()
         } else {
             hashed_new_password.clone()
         }),
-        avatar: ActiveValue::set(
-            if user_edit_data.avatar.clone().len() == 0 {
-                user[0].avatar.clone()
-            } else {
-                vec_i8_into_u8(user_edit_data.avatar.clone())
-            }
-        ),
         ..Default::default()
     }.update(db).await;
 
     match updated_user {
-        Ok(result) => {
-            let user_wo_password = UserWithoutPassword {
-                id: result.id,
-                email: result.email,
-                display_name: result.display_name,
-                avatar: result.avatar,
-                saved_books: result.saved_books
-            };
+        Ok(result) => Ok(Json(result.into())),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
 
-            Ok(Json(user_wo_password))
-        },
-        Err(err) => Err(status::Custom(Status::InternalServerError, err.to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ReadingProgressData {
+    user_id: i32,
+    chapter_id: i32
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    params(
+        ("user_id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Last read chapter of every book", body = Vec<ReadingProgress>),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[get("/progress/<user_id>")]
+async fn get_reading_progress(
+    db: &State<DatabaseConnection>,
+    user_id: i32
+) -> Result<Json<Vec<reading_progress::Model>>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let progress = ReadingProgress::find()
+        .filter(reading_progress::Column::UserId.eq(user_id))
+        .all(db)
+        .await;
+
+    match progress {
+        Ok(result) => Ok(Json(result)),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    tag = "user",
+    request_body = ReadingProgressData,
+    responses(
+        (status = 200, description = "Saved reading progress", body = ReadingProgress),
+        (status = 404, description = "No such chapter", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+)]
+#[put("/progress", data = "<progress_data>", format = "json")]
+async fn update_reading_progress(
+    db: &State<DatabaseConnection>,
+    progress_data: Json<ReadingProgressData>
+) -> Result<Json<reading_progress::Model>, ApiError> {
+    let db: &DatabaseConnection = db as &DatabaseConnection;
+
+    let chapter = match Chapter::find_by_id(progress_data.chapter_id).one(db).await {
+        Ok(Some(chapter)) => chapter,
+        Ok(None) => return Err(ApiError::new(
+            Status::NotFound,
+            format!("No chapter with id {}", progress_data.chapter_id)
+        )),
+        Err(err) => return Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    };
+
+    let current_progress = ReadingProgress::find_by_id((progress_data.user_id, chapter.book_id))
+        .one(db)
+        .await;
+
+    let progress = reading_progress::ActiveModel {
+        user_id: ActiveValue::set(progress_data.user_id),
+        book_id: ActiveValue::set(chapter.book_id),
+        chapter_number: ActiveValue::set(chapter.number),
+    };
+
+    let saved_progress = match current_progress {
+        // Going back to an earlier chapter does not reduce the progress
+        Ok(Some(current)) if current.chapter_number >= chapter.number => Ok(current),
+        Ok(Some(_)) => progress.update(db).await,
+        Ok(None) => progress.insert(db).await,
+        Err(err) => Err(err)
+    };
+
+    match saved_progress {
+        Ok(result) => Ok(Json(result)),
+        Err(err) => Err(ApiError::new(Status::InternalServerError, err.to_string()))
+    }
+}
+
+/// Reports `email` and `display_name` already used by another user as field errors.
+async fn check_unique_user(
+    db: &DatabaseConnection,
+    email: &str,
+    display_name: &str,
+    id: Option<i32>
+) -> Result<(), ApiError> {
+    let email = email.to_lowercase();
+
+    let users = User::find()
+        .filter(
+            Condition::any()
+                .add(Column::Email.eq(email.clone()))
+                .add(Column::DisplayName.eq(display_name))
+        )
+        .all(db)
+        .await?;
+
+    let mut error = ApiError::validation_failed();
+
+    for user in users.iter().filter(|user| Some(user.id) != id) {
+        if user.email == email {
+            error.add_field("email", "is already taken");
+        }
+        if user.display_name == display_name {
+            error.add_field("display_name", "is already taken");
+        }
     }
 
+    if error.has_field_errors() { Err(error) } else { Ok(()) }
 }
 
-fn vec_i8_into_u8(v: Vec<i8>) -> Vec<u8> {
-    let mut v = std::mem::ManuallyDrop::new(v);
+fn validate_new_password(password: &str) -> Result<(), ValidationError> {
+    if password.is_empty() || password.chars().count() >= MIN_PASSWORD_LENGTH {
+        return Ok(());
+    }
 
-    let p = v.as_mut_ptr();
-    let len = v.len();
-    let cap = v.capacity();
-    
-    unsafe { Vec::from_raw_parts(p as *mut u8, len, cap) }
+    let mut error = ValidationError::new("length");
+    error.message = Some("must be empty or at least 6 characters long".into());
+
+    Err(error)
 }
 
+fn validate_role(role: &str) -> Result<(), ValidationError> {
+    if auth::ROLES.contains(&role) {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("role");
+    error.message = Some(format!("must be one of {}", auth::ROLES.join(", ")).into());
+
+    Err(error)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_all_users,
+        get_user_by_id,
+        get_user_avatar,
+        upload_user_avatar,
+        create_user,
+        update_user,
+        delete_user,
+        add_book_to_tab,
+        delete_book_from_tab,
+        add_tab_to_user,
+        delete_user_tab,
+        login_user,
+        logout_user,
+        edit_user,
+        get_reading_progress,
+        update_reading_progress
+    ),
+    components(
+        schemas(
+            UserData,
+            UserUpdateData,
+            UserAuthModel,
+            UserEditModel,
+            UserWithoutPassword,
+            UserWithToken,
+            SaveBook,
+            ReadingProgressData
+        )
+    )
+)]
+pub struct UserApi;
+
 pub fn get_all_methods() -> Vec<rocket::Route> {
     routes![
         get_all_users,
         get_user_by_id,
+        get_user_avatar,
+        upload_user_avatar,
         create_user,
         update_user,
         delete_user,
@@ -466,6 +1059,9 @@ pub fn get_all_methods() -> Vec<rocket::Route> {
         add_book_to_tab,
         delete_book_from_tab,
         login_user,
-        edit_user
+        logout_user,
+        edit_user,
+        get_reading_progress,
+        update_reading_progress
     ]
 }
\ No newline at end of file
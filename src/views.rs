@@ -0,0 +1,195 @@
+//! Counting of book views. Opening a book or one of its chapters counts once per viewer and book within
+//! the view window, and the counts are kept in memory and added to `book.views` by a periodic flush.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::figment::Figment;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::tokio::{self, time};
+use rocket::{Orbit, Rocket};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use crate::audit::Actor;
+use crate::entities::book;
+use crate::entities::prelude::Book;
+
+pub const DEFAULT_WINDOW_MINUTES: u64 = 30;
+pub const DEFAULT_FLUSH_SECONDS: u64 = 60;
+
+/// Who opens a book: the signed in user, or a fingerprint of the client address and user agent
+/// for anonymous requests.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Viewer {
+    User(i32),
+    Anonymous(u64)
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Viewer {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user_id = match request.guard::<Actor>().await {
+            Outcome::Success(actor) => actor.user_id,
+            _ => None
+        };
+
+        match user_id {
+            Some(user_id) => Outcome::Success(Viewer::User(user_id)),
+            None => {
+                let mut hasher = DefaultHasher::new();
+                request.client_ip().hash(&mut hasher);
+                request.headers().get_one("User-Agent").hash(&mut hasher);
+
+                Outcome::Success(Viewer::Anonymous(hasher.finish()))
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Views {
+    // When each viewer last counted for a book
+    seen: HashMap<(Viewer, i32), Instant>,
+    // Views per book not yet added to the database
+    pending: HashMap<i32, i32>
+}
+
+/// Views counted since the last flush. `view_window_minutes` and `view_flush_seconds` in Rocket.toml,
+/// or `ROCKET_VIEW_WINDOW_MINUTES` and `ROCKET_VIEW_FLUSH_SECONDS`, set the window and the flush interval.
+/// Clones share the same counts, so the flush task can own one.
+#[derive(Clone)]
+pub struct ViewCounter {
+    window: Duration,
+    flush_interval: Duration,
+    views: Arc<Mutex<Views>>
+}
+
+impl ViewCounter {
+    pub fn new(window: Duration, flush_interval: Duration) -> ViewCounter {
+        ViewCounter {
+            window,
+            flush_interval,
+            views: Arc::new(Mutex::new(Views::default()))
+        }
+    }
+
+    pub fn from_figment(figment: &Figment) -> ViewCounter {
+        let window_minutes: u64 = figment.extract_inner("view_window_minutes").unwrap_or(DEFAULT_WINDOW_MINUTES);
+        let flush_seconds: u64 = figment.extract_inner("view_flush_seconds").unwrap_or(DEFAULT_FLUSH_SECONDS);
+
+        ViewCounter::new(Duration::from_secs(window_minutes * 60), Duration::from_secs(flush_seconds))
+    }
+
+    /// Counts a view of the book unless the viewer already counted for it within the window.
+    pub fn view(&self, viewer: Viewer, book_id: i32) -> bool {
+        let now = Instant::now();
+        let mut views = self.views.lock().unwrap();
+
+        match views.seen.get(&(viewer.clone(), book_id)) {
+            Some(at) if now.duration_since(*at) < self.window => false,
+            _ => {
+                views.seen.insert((viewer, book_id), now);
+                *views.pending.entry(book_id).or_insert(0) += 1;
+                true
+            }
+        }
+    }
+
+    /// Adds the pending views to the books and forgets viewers whose window is over. Views that
+    /// could not be written stay pending for the next flush.
+    pub async fn flush(&self, db: &DatabaseConnection) -> Result<i32, DbErr> {
+        let pending = {
+            let mut views = self.views.lock().unwrap();
+            let now = Instant::now();
+            let window = self.window;
+            views.seen.retain(|_, at| now.duration_since(*at) < window);
+
+            std::mem::take(&mut views.pending)
+        };
+
+        let mut flushed = 0;
+        let mut books = pending.into_iter();
+
+        while let Some((book_id, count)) = books.next() {
+            // Incremented in place, so updates of the book in between are kept
+            let result = Book::update_many()
+                .col_expr(book::Column::Views, Expr::col(book::Column::Views).add(count))
+                .filter(book::Column::Id.eq(book_id))
+                .exec(db)
+                .await;
+
+            if let Err(err) = result {
+                let mut views = self.views.lock().unwrap();
+                for (book_id, count) in std::iter::once((book_id, count)).chain(books) {
+                    *views.pending.entry(book_id).or_insert(0) += count;
+                }
+
+                return Err(err);
+            }
+
+            flushed += count;
+        }
+
+        Ok(flushed)
+    }
+}
+
+/// Flushes the view counts every flush interval once the server is up, and a last time on shutdown.
+pub struct ViewFlush;
+
+#[rocket::async_trait]
+impl Fairing for ViewFlush {
+    fn info(&self) -> Info {
+        Info {
+            name: "View flush",
+            kind: Kind::Liftoff | Kind::Shutdown
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (Some(db), Some(counter)) = (
+            rocket.state::<DatabaseConnection>().cloned(),
+            rocket.state::<ViewCounter>().cloned()
+        ) else {
+            return;
+        };
+        let mut shutdown = rocket.shutdown();
+
+        tokio::spawn(async move {
+            // The first tick of an interval is immediate, there is nothing to flush yet
+            let start = time::Instant::now() + counter.flush_interval;
+            let mut interval = time::interval_at(start, counter.flush_interval);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = &mut shutdown => break
+                }
+
+                flush(&db, &counter).await;
+            }
+        });
+    }
+
+    async fn on_shutdown(&self, rocket: &Rocket<Orbit>) {
+        if let (Some(db), Some(counter)) = (rocket.state::<DatabaseConnection>(), rocket.state::<ViewCounter>()) {
+            flush(db, counter).await;
+        }
+    }
+}
+
+async fn flush(db: &DatabaseConnection, counter: &ViewCounter) {
+    match counter.flush(db).await {
+        Ok(0) => {},
+        Ok(views) => tracing::debug!(views, "flushed book views"),
+        Err(err) => tracing::error!(error = %err, "view flush failed")
+    }
+}
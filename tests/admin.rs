@@ -0,0 +1,358 @@
+mod common;
+
+use rocket::http::{ContentType, Cookie, Status};
+use rocket::local::asynchronous::LocalResponse;
+use rocket::serde::json::serde_json::json;
+
+use common::{image_upload, json, png, TestApp, PASSWORD};
+
+async fn log_in<'c>(app: &'c TestApp, email: &str, password: &str) -> LocalResponse<'c> {
+    app.client.post("/admin/login")
+        .header(ContentType::Form)
+        .body(format!("email={}&password={}", email, password))
+        .dispatch()
+        .await
+}
+
+/// Signs in as the fixture admin, the client keeps the session cookie for the next requests.
+async fn log_in_as_admin(app: &TestApp) {
+    let response = log_in(app, &app.fixtures.admin.email, PASSWORD).await;
+    assert_eq!(redirect(response), "/admin");
+}
+
+async fn page(app: &TestApp, uri: String) -> String {
+    let response = app.client.get(uri).dispatch().await;
+    let status = response.status();
+    let body = response.into_string().await.unwrap_or_default();
+    assert_eq!(status, Status::Ok, "unexpected status with page {}", body);
+
+    body
+}
+
+async fn post_form<'c>(app: &'c TestApp, uri: String, form: &str) -> LocalResponse<'c> {
+    app.client.post(uri)
+        .header(ContentType::Form)
+        .body(form)
+        .dispatch()
+        .await
+}
+
+/// Target of a redirect after a successful form submission.
+fn redirect(response: LocalResponse<'_>) -> String {
+    assert_eq!(response.status(), Status::SeeOther);
+
+    response.headers().get_one("Location").expect("Location header").to_string()
+}
+
+#[rocket::async_test]
+async fn signs_in_only_admins() {
+    let app = TestApp::new().await;
+
+    // Pages ask for the login form first
+    let response = app.client.get("/admin").dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert!(response.into_string().await.unwrap().contains("action=\"/admin/login\""));
+
+    let response = log_in(&app, &app.fixtures.reader.email, PASSWORD).await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert!(response.into_string().await.unwrap().contains("This account does not have the admin role"));
+
+    let response = log_in(&app, &app.fixtures.admin.email, "wrong-password").await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert!(response.into_string().await.unwrap().contains("Email or password are not valid"));
+
+    let response = log_in(&app, "not-an-email", PASSWORD).await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    log_in_as_admin(&app).await;
+
+    let dashboard = page(&app, "/admin".to_string()).await;
+    assert!(dashboard.contains("<h1>Dashboard</h1>"));
+
+    let response = app.client.post("/admin/logout").dispatch().await;
+    assert_eq!(redirect(response), "/admin/login");
+
+    let response = app.client.get("/admin").dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn uploads_covers_and_avatars_with_the_panel_session() {
+    let app = TestApp::new().await;
+
+    let response = log_in(&app, &app.fixtures.admin.email, PASSWORD).await;
+    let cookie = response.cookies().get("admin_session").expect("session cookie").clone();
+    assert_eq!(cookie.path(), Some("/"));
+    assert_eq!(redirect(response), "/admin");
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(format!("/api/v1/book/{}/cover", app.fixtures.book_id))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Cover of book {} was updated", app.fixtures.book_id));
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(format!("/api/v1/author/{}/avatar", app.fixtures.author_id))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Avatar of author {} was updated", app.fixtures.author_id));
+}
+
+#[rocket::async_test]
+async fn forbids_sessions_of_other_roles() {
+    let app = TestApp::new().await;
+
+    let response = app.client.get("/admin/books")
+        .cookie(Cookie::new("admin_session", app.fixtures.reader.token.clone()))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Forbidden);
+    assert!(response.into_string().await.unwrap().contains("Only users with the admin role"));
+
+    let response = app.client.get("/admin/books")
+        .cookie(Cookie::new("admin_session", "expired-token"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn lists_and_searches_every_table() {
+    let app = TestApp::new().await;
+    log_in_as_admin(&app).await;
+
+    for (uri, expected) in [
+        ("/admin/books", "The Left Hand of Darkness"),
+        ("/admin/books?q=Left&page=1", "The Left Hand of Darkness"),
+        ("/admin/authors?q=Guin", "Le Guin"),
+        ("/admin/genres", "Fantasy"),
+        ("/admin/users?q=READER", "reader@egline.com"),
+        ("/admin/comments?q=hero", "the hero survives"),
+        ("/admin/reviews?q=slow", "A slow start that pays off")
+    ] {
+        assert!(page(&app, uri.to_string()).await.contains(expected), "{} lists {}", uri, expected);
+    }
+
+    for (uri, unexpected) in [
+        ("/admin/books?q=Dispossessed", "The Left Hand of Darkness"),
+        ("/admin/authors?q=Butler", "Le Guin"),
+        ("/admin/comments?q=villain", "the hero survives"),
+        ("/admin/reviews?q=boring", "A slow start that pays off")
+    ] {
+        assert!(!page(&app, uri.to_string()).await.contains(unexpected), "{} filters out {}", uri, unexpected);
+    }
+}
+
+#[rocket::async_test]
+async fn edits_books_and_their_chapters() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let [first_chapter, second_chapter] = app.fixtures.chapter_ids;
+    log_in_as_admin(&app).await;
+
+    assert!(page(&app, "/admin/books/new".to_string()).await.contains("New book"));
+
+    let form = page(&app, format!("/admin/books/{}", book_id)).await;
+    assert!(form.contains("A Parade in Erhenrang"));
+
+    // A completed book can only become ongoing again
+    assert!(form.contains("<option value=\"ongoing\" >ongoing</option>"));
+    assert!(!form.contains("<option value=\"hiatus\""));
+
+    let response = app.client.get("/admin/books/999").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = post_form(&app, "/admin/books".to_string(), "title=The+Dispossessed&description=&year=1974&status=completed").await;
+    let location = redirect(response);
+    assert!(page(&app, location).await.contains("The Dispossessed"));
+
+    let response = post_form(&app, format!("/admin/books/{}", book_id), "title=&description=&year=1969&status=finished").await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let form = response.into_string().await.unwrap();
+    assert!(form.contains("must be 1 to 200 characters long"));
+
+    let response = post_form(&app, format!("/admin/books/{}", book_id), "title=Left+Hand&description=Gethen&year=1969&status=completed").await;
+    assert_eq!(redirect(response), format!("/admin/books/{}", book_id));
+
+    let response = post_form(&app, format!("/admin/chapters/{}", first_chapter), "title=The+Parade").await;
+    assert_eq!(redirect(response), format!("/admin/books/{}", book_id));
+
+    let response = app.client.post(format!("/admin/chapters/{}/delete", second_chapter)).dispatch().await;
+    assert_eq!(redirect(response), format!("/admin/books/{}", book_id));
+
+    let form = page(&app, format!("/admin/books/{}", book_id)).await;
+    assert!(form.contains("Left Hand") && form.contains("The Parade"));
+    assert!(!form.contains("The Place Inside the Blizzard"));
+
+    let response = app.client.post("/admin/chapters/999/delete").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[rocket::async_test]
+async fn edits_authors_and_genres() {
+    let app = TestApp::new().await;
+    let author_id = app.fixtures.author_id;
+    let genre_id = app.fixtures.genre_id;
+    log_in_as_admin(&app).await;
+
+    assert!(page(&app, "/admin/authors/new".to_string()).await.contains("New author"));
+    assert!(page(&app, format!("/admin/authors/{}", author_id)).await.contains("Le Guin"));
+
+    let response = app.client.get("/admin/authors/999").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = post_form(&app, "/admin/authors".to_string(), "first_name=Octavia&last_name=Butler&biography=").await;
+    let location = redirect(response);
+    assert!(page(&app, location.clone()).await.contains("Butler"));
+
+    let response = post_form(&app, format!("/admin/authors/{}", author_id), "first_name=&last_name=Le+Guin&biography=").await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = post_form(&app, format!("/admin/authors/{}", author_id), "first_name=Ursula+K.&last_name=Le+Guin&biography=").await;
+    assert_eq!(redirect(response), format!("/admin/authors/{}", author_id));
+
+    let response = app.client.post(format!("{}/delete", location)).dispatch().await;
+    assert_eq!(redirect(response), "/admin/authors");
+    assert!(!page(&app, "/admin/authors".to_string()).await.contains("Butler"));
+
+    let response = post_form(&app, "/admin/genres".to_string(), "title=Science+fiction").await;
+    assert_eq!(redirect(response), "/admin/genres");
+
+    let response = post_form(&app, format!("/admin/genres/{}", genre_id), "title=").await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = post_form(&app, format!("/admin/genres/{}", genre_id), "title=High+fantasy").await;
+    assert_eq!(redirect(response), "/admin/genres");
+
+    let response = app.client.post(format!("/admin/genres/{}/delete", genre_id)).dispatch().await;
+    assert_eq!(redirect(response), "/admin/genres");
+
+    let genres = page(&app, "/admin/genres".to_string()).await;
+    assert!(genres.contains("Science fiction") && !genres.contains("High fantasy"));
+}
+
+#[rocket::async_test]
+async fn manages_users_but_not_the_signed_in_admin() {
+    let app = TestApp::new().await;
+    let admin_id = app.fixtures.admin.id;
+    let reader_id = app.fixtures.reader.id;
+    log_in_as_admin(&app).await;
+
+    let response = post_form(&app, format!("/admin/users/{}/role", admin_id), "role=reader").await;
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(response.into_string().await.unwrap().contains("Admins can not change their own role"));
+
+    let response = app.client.post(format!("/admin/users/{}/delete", admin_id)).dispatch().await;
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = post_form(&app, format!("/admin/users/{}/role", reader_id), "role=emperor").await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let response = post_form(&app, format!("/admin/users/{}/role", reader_id), "role=admin").await;
+    assert_eq!(redirect(response), "/admin/users");
+
+    // The promoted reader can sign in to the panel now
+    let response = log_in(&app, &app.fixtures.reader.email, PASSWORD).await;
+    assert_eq!(redirect(response), "/admin");
+
+    let response = app.client.post(format!("/admin/users/{}/delete", app.fixtures.writer.id)).dispatch().await;
+    assert_eq!(redirect(response), "/admin/users");
+    assert!(!page(&app, "/admin/users".to_string()).await.contains(&app.fixtures.writer.email));
+}
+
+#[rocket::async_test]
+async fn moderates_comments_and_reviews() {
+    let app = TestApp::new().await;
+    log_in_as_admin(&app).await;
+
+    let response = app.client.post(format!("/admin/comments/{}/delete", app.fixtures.comment_id)).dispatch().await;
+    assert_eq!(redirect(response), "/admin/comments");
+    assert!(!page(&app, "/admin/comments".to_string()).await.contains("the hero survives"));
+
+    let response = app.client.post(format!("/admin/reviews/{}/delete", app.fixtures.review_id)).dispatch().await;
+    assert_eq!(redirect(response), "/admin/reviews");
+    assert!(!page(&app, "/admin/reviews".to_string()).await.contains("A slow start that pays off"));
+
+    let dashboard = page(&app, "/admin".to_string()).await;
+    assert!(dashboard.contains("<th><a href=\"/admin/reviews\">Reviews</a></th><td>0</td>"));
+}
+
+#[rocket::async_test]
+async fn restores_rows_from_the_trash() {
+    let app = TestApp::new().await;
+    let writer = &app.fixtures.writer;
+    log_in_as_admin(&app).await;
+
+    let trashed = [
+        ("books", app.fixtures.book_id),
+        ("authors", app.fixtures.author_id),
+        ("users", writer.id),
+        ("comments", app.fixtures.comment_id)
+    ];
+
+    for (kind, id) in trashed {
+        let response = app.client.post(format!("/admin/{}/{}/delete", kind, id)).dispatch().await;
+        assert_eq!(redirect(response), format!("/admin/{}", kind));
+    }
+
+    assert!(!page(&app, "/admin/books".to_string()).await.contains("The Left Hand of Darkness"));
+    let dashboard = page(&app, "/admin".to_string()).await;
+    assert!(dashboard.contains("<th><a href=\"/admin/trash\">Trash</a></th><td>4</td>"));
+
+    let trash = page(&app, "/admin/trash".to_string()).await;
+    assert!(trash.contains("kept for 30 days"));
+    for (kind, id) in trashed {
+        assert!(trash.contains(&format!("/admin/{}/{}/restore", kind, id)));
+    }
+
+    for (kind, id) in trashed {
+        let response = app.client.post(format!("/admin/{}/{}/restore", kind, id)).dispatch().await;
+        assert_eq!(redirect(response), "/admin/trash");
+
+        // Only rows in the trash can be restored
+        let response = app.client.post(format!("/admin/{}/{}/restore", kind, id)).dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    assert!(page(&app, "/admin/trash".to_string()).await.contains("The trash is empty"));
+    assert!(page(&app, "/admin/books".to_string()).await.contains("The Left Hand of Darkness"));
+    assert!(page(&app, "/admin/comments".to_string()).await.contains("the hero survives"));
+
+    // The restored user signs in with their old password
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": writer.email, "password": PASSWORD}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn logs_moderation_in_the_audit_log() {
+    let app = TestApp::new().await;
+    let admin_id = app.fixtures.admin.id;
+    let reader_id = app.fixtures.reader.id;
+    log_in_as_admin(&app).await;
+
+    let response = app.client.post(format!("/admin/comments/{}/delete", app.fixtures.comment_id)).dispatch().await;
+    assert_eq!(redirect(response), "/admin/comments");
+
+    let response = post_form(&app, format!("/admin/users/{}/role", reader_id), "role=admin").await;
+    assert_eq!(redirect(response), "/admin/users");
+
+    let audit = page(&app, format!("/admin/audit?actor_id={}", admin_id)).await;
+    assert!(audit.contains(&format!("<a href=\"/admin/audit?entity_type=comment&entity_id={0}\">comment {0}</a>", app.fixtures.comment_id)));
+    assert!(audit.contains(&format!("<a href=\"/admin/audit?entity_type=user&entity_id={0}\">user {0}</a>", reader_id)));
+    assert!(audit.contains("2 in total"));
+
+    let audit = page(&app, format!("/admin/audit?entity_type=user&entity_id={}&action=update", reader_id)).await;
+    assert!(audit.contains("&quot;role&quot;:&quot;admin&quot;"));
+    assert!(audit.contains("1 in total"));
+
+    let audit = page(&app, "/admin/audit?action=restore".to_string()).await;
+    assert!(audit.contains("No entries"));
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::serde_json::json;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder};
+
+use egline_server::audit;
+use egline_server::entities::audit_log;
+use egline_server::entities::prelude::AuditLog;
+
+use common::{bearer, json, TestApp};
+
+async fn entries(app: &TestApp, entity_type: &str, entity_id: i32) -> Vec<audit_log::Model> {
+    AuditLog::find()
+        .filter(audit_log::Column::EntityType.eq(entity_type))
+        .filter(audit_log::Column::EntityId.eq(entity_id))
+        .order_by_asc(audit_log::Column::Id)
+        .all(&app.db)
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+async fn logs_only_changed_fields_with_the_actor() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .header(bearer(&app.fixtures.writer.token))
+        .header(Header::new("X-Request-Id", "audit-update-1"))
+        .body(json!({"title": "The Left Hand of Darkness", "description": "Gethen", "year": 1969, "status": "completed"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let log = entries(&app, "book", book_id).await;
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].action, audit::UPDATE);
+    assert_eq!(log[0].actor_id, Some(app.fixtures.writer.id));
+    assert_eq!(log[0].request_id.as_deref(), Some("audit-update-1"));
+    assert_eq!(log[0].before, Some(json!({"description": "An envoy on the planet Gethen"})));
+    assert_eq!(log[0].after, Some(json!({"description": "Gethen"})));
+
+    // Saving the same fields again changes nothing and is not logged
+    app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "The Left Hand of Darkness", "description": "Gethen", "year": 1969, "status": "completed"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(entries(&app, "book", book_id).await.len(), 1);
+}
+
+#[rocket::async_test]
+async fn logs_creates_deletes_and_links() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let genre_id = app.fixtures.genre_id;
+
+    let response = app.client.post("/api/v1/genre")
+        .header(ContentType::JSON)
+        .body(json!({"title": "Horror"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let created = AuditLog::find()
+        .filter(audit_log::Column::EntityType.eq("genre"))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(created.action, audit::CREATE);
+    assert_eq!(created.actor_id, None);
+    assert_eq!(created.before, None);
+    assert_eq!(created.after.as_ref().unwrap()["title"], "Horror");
+
+    let response = app.client.delete(format!("/api/v1/book/genre/{}/{}", book_id, genre_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.delete(format!("/api/v1/genre/{}", genre_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let log = entries(&app, "book", book_id).await;
+    assert_eq!(log[0].before, Some(json!({"genre_id": genre_id})));
+    assert_eq!(log[0].after, Some(json!({})));
+
+    let log = entries(&app, "genre", genre_id).await;
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].action, audit::DELETE);
+    assert_eq!(log[0].before.as_ref().unwrap()["title"], "Fantasy");
+    assert_eq!(log[0].after, None);
+
+    // Trashed rows are logged as they were, without the image bytes
+    let response = app.client.delete(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let log = entries(&app, "book", book_id).await;
+    let deleted = log.last().unwrap();
+    assert_eq!(deleted.action, audit::DELETE);
+    assert_eq!(deleted.before.as_ref().unwrap()["title"], "The Left Hand of Darkness");
+    assert!(deleted.before.as_ref().unwrap().get("cover").is_none());
+}
+
+#[rocket::async_test]
+async fn keeps_entries_unchanged() {
+    let app = TestApp::new().await;
+
+    app.client.delete(format!("/api/v1/genre/{}", app.fixtures.genre_id)).dispatch().await;
+    let entry = AuditLog::find().one(&app.db).await.unwrap().unwrap();
+
+    let mut changed = entry.clone().into_active_model();
+    changed.action = ActiveValue::set(audit::CREATE.to_string());
+    assert!(changed.update(&app.db).await.is_err());
+    assert!(entry.clone().into_active_model().delete(&app.db).await.is_err());
+
+    assert_eq!(AuditLog::find().one(&app.db).await.unwrap(), Some(entry));
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::json;
+
+use common::{admin_session, error, image_upload, json, png, TestApp};
+
+#[rocket::async_test]
+async fn lists_and_finds_authors() {
+    let app = TestApp::new().await;
+    let author_id = app.fixtures.author_id;
+
+    let authors = json(app.client.get("/api/v1/author").dispatch().await).await;
+    assert_eq!(authors.as_array().unwrap().len(), 1);
+    assert_eq!(authors[0]["last_name"], "Le Guin");
+
+    // The fixture review's rate counts towards the author of the book
+    let author = json(app.client.get(format!("/api/v1/author/{}", author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 4.0);
+    assert_eq!(author["avatar_url"], json!(null));
+
+    let missing = json(app.client.get("/api/v1/author/999").dispatch().await).await;
+    assert_eq!(missing["id"], -1);
+}
+
+#[rocket::async_test]
+async fn creates_updates_and_deletes_authors() {
+    let app = TestApp::new().await;
+    let author_id = app.fixtures.author_id;
+
+    let response = app.client.post("/api/v1/author")
+        .header(ContentType::JSON)
+        .body(json!({"first_name": "Octavia", "last_name": "Butler", "biography": ""}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Author Octavia Butler was successfully created");
+
+    let response = app.client.put(format!("/api/v1/author/{}", author_id))
+        .header(ContentType::JSON)
+        .body(json!({"first_name": "Ursula K.", "last_name": "Le Guin", "biography": "Hainish Cycle"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Author Ursula K. Le Guin was successfully updated");
+
+    // Updates keep the rating
+    let author = json(app.client.get(format!("/api/v1/author/{}", author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 4.0);
+
+    let response = app.client.delete(format!("/api/v1/author/{}", author_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let authors = json(app.client.get("/api/v1/author").dispatch().await).await;
+    assert_eq!(authors.as_array().unwrap().len(), 1);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_authors() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/author")
+        .header(ContentType::JSON)
+        .body(json!({"first_name": "", "last_name": "Butler", "biography": ""}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"first_name": ["must be 1 to 100 characters long"]}));
+
+    let response = app.client.put(format!("/api/v1/author/{}", app.fixtures.author_id))
+        .header(ContentType::JSON)
+        .body(json!({"first_name": "Ursula", "last_name": "", "biography": ""}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"last_name": ["must be 1 to 100 characters long"]}));
+}
+
+#[rocket::async_test]
+async fn uploads_and_serves_avatars() {
+    let app = TestApp::new().await;
+    let author_id = app.fixtures.author_id;
+
+    let response = app.client.get(format!("/api/v1/author/{}/avatar", author_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let (content_type, body) = image_upload(&png(40, 20));
+    let response = app.client.put(format!("/api/v1/author/{}/avatar", author_id))
+        .cookie(admin_session(&app.fixtures.admin.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Avatar of author {} was updated", author_id));
+
+    let response = app.client.get(format!("/api/v1/author/{}/avatar", author_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::PNG));
+
+    let response = app.client.get(format!("/api/v1/author/{}/avatar?size=tiny", author_id)).dispatch().await;
+    error(response, Status::BadRequest).await;
+
+    let response = app.client.get("/api/v1/author/999/avatar").dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put("/api/v1/author/999/avatar")
+        .cookie(admin_session(&app.fixtures.admin.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn requires_an_admin_for_avatars() {
+    let app = TestApp::new().await;
+    let uri = format!("/api/v1/author/{}/avatar", app.fixtures.author_id);
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(uri.clone())
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Unauthorized).await;
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(uri)
+        .cookie(admin_session(&app.fixtures.reader.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Forbidden).await;
+}
+
+#[rocket::async_test]
+async fn reports_statistics() {
+    let app = TestApp::new().await;
+    let author_id = app.fixtures.author_id;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/follow/author")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": app.fixtures.reader.id, "author_id": author_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let statistics = json(app.client.get(format!("/api/v1/author/{}/statistics", author_id)).dispatch().await).await;
+    assert_eq!(statistics, json!({
+        "author_id": author_id,
+        "rating": 4.0,
+        "books": 1,
+        "chapters": 2,
+        "views": 0,
+        "followers": 1,
+        "rating_trend": [{
+            "book_id": book_id,
+            "title": "The Left Hand of Darkness",
+            "year": 1969,
+            "rating": 4.0,
+            "rates": 1
+        }]
+    }));
+
+    let response = app.client.get("/api/v1/author/999/statistics").dispatch().await;
+    error(response, Status::NotFound).await;
+}
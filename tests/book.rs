@@ -0,0 +1,668 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use migration::{Migrator, MigratorTrait};
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::serde_json::{json, Value};
+use sea_orm::{ActiveModelTrait, ActiveValue, ConnectionTrait};
+
+use egline_server::entities::{author, book_rate};
+use egline_server::retention;
+use egline_server::views::{ViewCounter, Viewer};
+
+use common::{admin_session, bearer, error, image_upload, json, png, without_timestamps, TestApp};
+
+#[rocket::async_test]
+async fn lists_and_finds_books() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let books = json(app.client.get("/api/v1/book").dispatch().await).await;
+    assert_eq!(without_timestamps(books.clone()), json!([{
+        "id": book_id,
+        "title": "The Left Hand of Darkness",
+        "description": "An envoy on the planet Gethen",
+        "rating": 4.0,
+        "year": 1969,
+        "views": 0,
+        "status": "completed",
+        "cover_url": null,
+        "genres": ["Fantasy"],
+        "rates": 1
+    }]));
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book, books[0]);
+
+    let response = app.client.get("/api/v1/book/999").dispatch().await;
+    let body = error(response, Status::InternalServerError).await;
+    assert_eq!(body["message"], "No such book");
+}
+
+#[rocket::async_test]
+async fn lists_ids_by_rating() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/book")
+        .header(ContentType::JSON)
+        .body(json!({"title": "Kindred", "description": "", "year": 1979, "status": "completed"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let ids = json(app.client.get("/api/v1/book/get-ids").dispatch().await).await;
+    assert_eq!(ids, json!([app.fixtures.book_id + 1, app.fixtures.book_id]));
+}
+
+#[rocket::async_test]
+async fn creates_and_updates_books() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/book")
+        .header(ContentType::JSON)
+        .body(json!({"title": "Kindred", "description": "", "year": 1979, "status": "completed"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Book Kindred was successfully created");
+
+    let response = app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "The Left Hand of Darkness", "description": "Gethen", "year": 1969, "status": "ongoing"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Book The Left Hand of Darkness was successfully updated");
+
+    // Rating and views are kept
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["status"], "ongoing");
+    assert_eq!(book["rating"], 4.0);
+
+    let response = app.client.put("/api/v1/book/999")
+        .header(ContentType::JSON)
+        .body(json!({"title": "Dawn", "description": "", "year": 1987, "status": "ongoing"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::NotFound).await;
+    assert_eq!(body["message"], "No book with id 999");
+}
+
+#[rocket::async_test]
+async fn counts_views_once_per_viewer() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let views = app.client.rocket().state::<ViewCounter>().unwrap();
+
+    // The book and its chapters count once for the same anonymous client
+    app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    app.client.get(format!("/api/v1/chapter/text/{}", app.fixtures.chapter_ids[0])).dispatch().await;
+
+    // Views wait in memory until the flush
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["views"], 0);
+    assert_eq!(views.flush(&app.db).await.unwrap(), 1);
+
+    app.client.get(format!("/api/v1/chapter/audio/{}", app.fixtures.chapter_ids[1]))
+        .header(bearer(&app.fixtures.reader.token))
+        .dispatch()
+        .await;
+    app.client.get(format!("/api/v1/book/{}", book_id))
+        .header(Header::new("User-Agent", "Another reader"))
+        .dispatch()
+        .await;
+    assert_eq!(views.flush(&app.db).await.unwrap(), 2);
+    assert_eq!(views.flush(&app.db).await.unwrap(), 0);
+
+    // Updates of the book keep the counter
+    app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "The Left Hand of Darkness", "description": "Gethen", "year": 1969, "status": "completed"}).to_string())
+        .dispatch()
+        .await;
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["views"], 3);
+
+    // Once the window is over the same viewer counts again
+    let views = ViewCounter::new(Duration::ZERO, Duration::from_secs(60));
+    assert!(views.view(Viewer::User(app.fixtures.reader.id), book_id));
+    assert!(views.view(Viewer::User(app.fixtures.reader.id), book_id));
+    assert_eq!(views.flush(&app.db).await.unwrap(), 2);
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["views"], 5);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_books() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/book")
+        .header(ContentType::JSON)
+        .body(json!({"title": "", "description": "", "year": -1, "status": "abandoned"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    let errors = body["errors"].as_object().unwrap();
+    assert_eq!(errors["title"], json!(["must be 1 to 200 characters long"]));
+    assert!(errors["year"][0].as_str().unwrap().starts_with("must be between 0 and "));
+    assert_eq!(errors["status"], json!(["must be one of ongoing, completed, hiatus, dropped, announced"]));
+}
+
+#[rocket::async_test]
+async fn moves_statuses_along_allowed_transitions() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let update = |status: &str| json!({"title": "The Left Hand of Darkness", "description": "", "year": 1969, "status": status}).to_string();
+
+    // Completed books can only be picked up again
+    let response = app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .body(update("hiatus"))
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"status": ["can not change from completed to hiatus"]}));
+
+    for status in ["ongoing", "hiatus", "hiatus"] {
+        let response = app.client.put(format!("/api/v1/book/{}", book_id))
+            .header(ContentType::JSON)
+            .header(bearer(&app.fixtures.writer.token))
+            .body(update(status))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let response = app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .body(update("announced"))
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"status": ["can not change from hiatus to announced"]}));
+
+    // Keeping the status adds nothing to the history
+    let history = json(app.client.get(format!("/api/v1/book/{}/status-history", book_id)).dispatch().await).await;
+    let changes = history.as_array().unwrap()
+        .iter()
+        .map(|change| (change["from_status"].clone(), change["to_status"].clone(), change["user_id"].clone()))
+        .collect::<Vec<_>>();
+    let writer_id = json!(app.fixtures.writer.id);
+    assert_eq!(changes, vec![
+        (json!("completed"), json!("ongoing"), writer_id.clone()),
+        (json!("ongoing"), json!("hiatus"), writer_id)
+    ]);
+
+    // New books start their history with the status they are created with
+    let response = app.client.post("/api/v1/book")
+        .header(ContentType::JSON)
+        .body(json!({"title": "Kindred", "description": "", "year": 1979, "status": "announced"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let history = json(app.client.get(format!("/api/v1/book/{}/status-history", book_id + 1)).dispatch().await).await;
+    assert_eq!(history[0]["from_status"], json!(null));
+    assert_eq!(history[0]["to_status"], "announced");
+}
+
+#[rocket::async_test]
+async fn filters_and_sorts_books_by_status() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    for (title, status) in [("Kindred", "announced"), ("Dawn", "ongoing")] {
+        let response = app.client.post("/api/v1/book")
+            .header(ContentType::JSON)
+            .body(json!({"title": title, "description": "", "year": 2024, "status": status}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let titles = |books: Value| books.as_array().unwrap()
+        .iter()
+        .map(|book| book["title"].as_str().unwrap().to_string())
+        .collect::<Vec<String>>();
+
+    let books = json(app.client.get("/api/v1/book?status=ongoing,announced").dispatch().await).await;
+    assert_eq!(titles(books), ["Kindred", "Dawn"]);
+
+    let books = json(app.client.get("/api/v1/book?sort=status").dispatch().await).await;
+    assert_eq!(titles(books), ["Dawn", "The Left Hand of Darkness", "Kindred"]);
+
+    let books = json(app.client.get("/api/v1/book?sort=title").dispatch().await).await;
+    assert_eq!(titles(books), ["Dawn", "Kindred", "The Left Hand of Darkness"]);
+
+    let ids = json(app.client.get("/api/v1/book/get-ids?status=completed").dispatch().await).await;
+    assert_eq!(ids, json!([book_id]));
+
+    let response = app.client.get("/api/v1/book?status=abandoned").dispatch().await;
+    let body = error(response, Status::BadRequest).await;
+    assert_eq!(body["message"], "Unknown status abandoned, expected one of ongoing, completed, hiatus, dropped, announced");
+
+    let response = app.client.get("/api/v1/book?sort=popular").dispatch().await;
+    error(response, Status::BadRequest).await;
+}
+
+#[rocket::async_test]
+async fn refuses_to_migrate_rates_out_of_range() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    // Back to before the rate checks
+    Migrator::down(&app.db, Some(2)).await.unwrap();
+    app.db.execute_unprepared(&format!("UPDATE book_rate SET rate = 7 WHERE book_id = {}", book_id)).await.unwrap();
+
+    let err = Migrator::up(&app.db, None).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Migration Error: Rows of book_rate with a rate not between 1 and 5: book_id {} by user {} (7). Fix or remove them first",
+            book_id, app.fixtures.writer.id
+        )
+    );
+
+    app.db.execute_unprepared(&format!("UPDATE book_rate SET rate = 5 WHERE book_id = {}", book_id)).await.unwrap();
+    Migrator::up(&app.db, None).await.unwrap();
+}
+
+#[rocket::async_test]
+async fn refuses_to_migrate_unknown_statuses() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    Migrator::down(&app.db, Some(1)).await.unwrap();
+    app.db.execute_unprepared(&format!("UPDATE book SET status = 'paused' WHERE id = {}", book_id)).await.unwrap();
+
+    let err = Migrator::up(&app.db, None).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Migration Error: Books with unknown statuses: {} (paused). Set them to one of ongoing, completed, hiatus, dropped, announced first", book_id)
+    );
+
+    app.db.execute_unprepared(&format!("UPDATE book SET status = 'hiatus' WHERE id = {}", book_id)).await.unwrap();
+    Migrator::up(&app.db, None).await.unwrap();
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["status"], "hiatus");
+}
+
+#[rocket::async_test]
+async fn deletes_books_and_recomputes_author_ratings() {
+    let app = TestApp::new().await;
+    let author_id = app.fixtures.author_id;
+
+    let response = app.client.post("/api/v1/book")
+        .header(ContentType::JSON)
+        .body(json!({"title": "The Dispossessed", "description": "", "year": 1974, "status": "completed"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let book_id = app.fixtures.book_id + 1;
+
+    let response = app.client.post("/api/v1/book/author")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "author_id": author_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post("/api/v1/book/rate")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "user_id": app.fixtures.reader.id, "rate": 2}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let author = json(app.client.get(format!("/api/v1/author/{}", author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 3.0);
+
+    let response = app.client.delete(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let author = json(app.client.get(format!("/api/v1/author/{}", author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 4.0);
+
+    let response = app.client.delete(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 0");
+}
+
+#[rocket::async_test]
+async fn trashes_and_purges_books() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.delete(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+    let response = app.client.delete(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 0");
+
+    let response = app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await;
+    assert_eq!(error(response, Status::InternalServerError).await["message"], "No such book");
+    assert_eq!(json(app.client.get("/api/v1/book").dispatch().await).await, json!([]));
+    assert_eq!(json(app.client.get("/api/v1/book/get-ids").dispatch().await).await, json!([]));
+
+    let response = app.client.put(format!("/api/v1/book/{}", book_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "The Left Hand of Darkness", "description": "", "year": 1969, "status": "ongoing"}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+
+    // Trashed books leave their authors' ratings
+    let author = json(app.client.get(format!("/api/v1/author/{}", app.fixtures.author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 0.0);
+
+    // Chapters, reviews and comments stay until the purge, but are not served
+    assert!(Path::new(&format!("storage/{}/1", book_id)).is_dir());
+
+    let chapter_id = app.fixtures.chapter_ids[0];
+    assert_eq!(json(app.client.get(format!("/api/v1/chapter/book-chapters/{}", book_id)).dispatch().await).await, json!([]));
+    assert_eq!(json(app.client.get("/api/v1/chapter").dispatch().await).await, json!([]));
+    let chapter = json(app.client.get(format!("/api/v1/chapter/{}", chapter_id)).dispatch().await).await;
+    assert_eq!(chapter["id"], -1);
+    let response = app.client.get(format!("/api/v1/chapter/text/{}", chapter_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+    let response = app.client.get(format!("/api/v1/chapter/audio/{}", chapter_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    assert_eq!(json(app.client.get(format!("/api/v1/review/book/{}", book_id)).dispatch().await).await, json!([]));
+    let response = app.client.get(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    // The purge recomputes the ratings of the books' authors
+    author::ActiveModel {
+        id: ActiveValue::set(app.fixtures.author_id),
+        rating: ActiveValue::set(4.0),
+        ..Default::default()
+    }.update(&app.db).await.unwrap();
+
+    let purged = retention::purge(&app.db, Utc::now().naive_utc()).await.unwrap();
+    assert_eq!(purged.books, 1);
+    assert!(!Path::new(&format!("storage/{}", book_id)).exists());
+
+    let author = json(app.client.get(format!("/api/v1/author/{}", app.fixtures.author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 0.0);
+
+    // Missing chapters come back as the placeholder with id -1
+    let chapter = json(app.client.get(format!("/api/v1/chapter/{}", chapter_id)).dispatch().await).await;
+    assert_eq!(chapter["id"], -1);
+}
+
+#[rocket::async_test]
+async fn uploads_and_serves_covers() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.get(format!("/api/v1/book/{}/cover", book_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let (content_type, body) = image_upload(&png(2048, 1024));
+    let response = app.client.put(format!("/api/v1/book/{}/cover", book_id))
+        .cookie(admin_session(&app.fixtures.admin.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Cover of book {} was updated", book_id));
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["cover_url"], format!("/api/v1/book/{}/cover", book_id));
+
+    let response = app.client.get(format!("/api/v1/book/{}/cover", book_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let cover = image::load_from_memory(&response.into_bytes().await.unwrap()).unwrap();
+    assert_eq!((cover.width(), cover.height()), (1024, 512));
+
+    let response = app.client.get(format!("/api/v1/book/{}/cover?size=medium", book_id)).dispatch().await;
+    let cover = image::load_from_memory(&response.into_bytes().await.unwrap()).unwrap();
+    assert_eq!((cover.width(), cover.height()), (256, 128));
+}
+
+#[rocket::async_test]
+async fn requires_an_admin_for_covers() {
+    let app = TestApp::new().await;
+    let uri = format!("/api/v1/book/{}/cover", app.fixtures.book_id);
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(uri.clone())
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Unauthorized).await;
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(uri.clone())
+        .cookie(admin_session(&app.fixtures.reader.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Forbidden).await;
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(uri.clone())
+        .header(bearer(&app.fixtures.reader.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Forbidden).await;
+
+    let response = app.client.get(format!("/api/v1/book/{}/cover", app.fixtures.book_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    // API clients send the admin's session token
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(uri)
+        .header(bearer(&app.fixtures.admin.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Cover of book {} was updated", app.fixtures.book_id));
+}
+
+#[rocket::async_test]
+async fn serves_covers_from_storage() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    fs::write(format!("storage/{}/cover.png", book_id), png(10, 10)).unwrap();
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["cover_url"], format!("/api/v1/book/{}/cover", book_id));
+
+    let response = app.client.get(format!("/api/v1/book/{}/cover", book_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_covers() {
+    let app = TestApp::new().await;
+
+    let (content_type, body) = image_upload(b"GIF89a but not really");
+    let response = app.client.put(format!("/api/v1/book/{}/cover", app.fixtures.book_id))
+        .cookie(admin_session(&app.fixtures.admin.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::UnprocessableEntity).await;
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put("/api/v1/book/999/cover")
+        .cookie(admin_session(&app.fixtures.admin.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+
+    let response = app.client.get("/api/v1/book/999/cover").dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let response = app.client.get(format!("/api/v1/book/{}/cover?size=xl", app.fixtures.book_id)).dispatch().await;
+    error(response, Status::BadRequest).await;
+}
+
+#[rocket::async_test]
+async fn links_and_unlinks_genres() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let genre_id = app.fixtures.genre_id;
+
+    let response = app.client.delete(format!("/api/v1/book/genre/{}/{}", book_id, genre_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let response = app.client.post("/api/v1/book/genre")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "genre_id": genre_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Book genre was successfully created");
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["genres"], json!(["Fantasy"]));
+
+    // Linking twice breaks the primary key
+    let response = app.client.post("/api/v1/book/genre")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "genre_id": genre_id}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+}
+
+#[rocket::async_test]
+async fn links_and_unlinks_authors() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let author_id = app.fixtures.author_id;
+
+    let response = app.client.delete(format!("/api/v1/book/author/{}/{}", book_id, author_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let author = json(app.client.get(format!("/api/v1/author/{}", author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 0.0);
+
+    let response = app.client.post("/api/v1/book/author")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "author_id": author_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Book author was successfully created");
+
+    let author = json(app.client.get(format!("/api/v1/author/{}", author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 4.0);
+
+    let response = app.client.post("/api/v1/book/author")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": 999, "author_id": author_id}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+}
+
+#[rocket::async_test]
+async fn rates_books() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let reader_id = app.fixtures.reader.id;
+
+    let response = app.client.post("/api/v1/book/rate")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "user_id": reader_id, "rate": 2}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Book rate was successfully created");
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!((book["rating"].as_f64(), book["rates"].as_u64()), (Some(3.0), Some(2)));
+
+    let response = app.client.put("/api/v1/book/rate")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "user_id": reader_id, "rate": 5}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let author = json(app.client.get(format!("/api/v1/author/{}", app.fixtures.author_id)).dispatch().await).await;
+    assert_eq!(author["rating"], 4.5);
+
+    let response = app.client.delete(format!("/api/v1/book/rate/{}/{}", book_id, reader_id)).dispatch().await;
+    assert_eq!(json(response).await, "Book rate was successfully deleted");
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["rating"], 4.0);
+}
+
+#[rocket::async_test]
+async fn keeps_review_rates_in_sync() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let writer_id = app.fixtures.writer.id;
+
+    let response = app.client.put("/api/v1/book/rate")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "user_id": writer_id, "rate": 1}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let review = json(app.client.get(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await).await;
+    assert_eq!(review["rate"], 1);
+
+    let response = app.client.delete(format!("/api/v1/book/rate/{}/{}", book_id, writer_id)).dispatch().await;
+    error(response, Status::Conflict).await;
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_rates() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let reader_id = app.fixtures.reader.id;
+
+    for rate in [0, 6] {
+        let response = app.client.post("/api/v1/book/rate")
+            .header(ContentType::JSON)
+            .body(json!({"book_id": book_id, "user_id": reader_id, "rate": rate}).to_string())
+            .dispatch()
+            .await;
+        let body = error(response, Status::UnprocessableEntity).await;
+        assert_eq!(body["errors"], json!({"rate": ["must be between 1 and 5"]}));
+
+        let response = app.client.put("/api/v1/book/rate")
+            .header(ContentType::JSON)
+            .body(json!({"book_id": book_id, "user_id": reader_id, "rate": rate}).to_string())
+            .dispatch()
+            .await;
+        error(response, Status::UnprocessableEntity).await;
+    }
+
+    // The writer has already rated the book through the review
+    let response = app.client.post("/api/v1/book/rate")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "user_id": app.fixtures.writer.id, "rate": 3}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+
+    // The database refuses them as well
+    let book_rate = book_rate::ActiveModel {
+        book_id: ActiveValue::set(book_id),
+        user_id: ActiveValue::set(reader_id),
+        rate: ActiveValue::set(6),
+        ..Default::default()
+    };
+    assert!(book_rate.insert(&app.db).await.is_err());
+}
@@ -0,0 +1,223 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::json;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, SqlErr};
+
+use egline_server::entities::{audit_log, chapter};
+use egline_server::entities::prelude::{AuditLog, Book, Chapter, Comment};
+
+use common::{chapter_audio, chapter_text, error, json, TestApp};
+
+#[rocket::async_test]
+async fn lists_and_finds_chapters() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let [first_chapter, _] = app.fixtures.chapter_ids;
+
+    let chapters = json(app.client.get("/api/v1/chapter").dispatch().await).await;
+    assert_eq!(chapters.as_array().unwrap().len(), 2);
+
+    let chapters = json(app.client.get(format!("/api/v1/chapter/book-chapters/{}", book_id)).dispatch().await).await;
+    assert_eq!(chapters.as_array().unwrap().len(), 2);
+
+    let chapters = json(app.client.get("/api/v1/chapter/book-chapters/999").dispatch().await).await;
+    assert_eq!(chapters, json!([]));
+
+    let chapter = json(app.client.get(format!("/api/v1/chapter/{}", first_chapter)).dispatch().await).await;
+    assert_eq!(chapter, json!({
+        "id": first_chapter,
+        "book_id": book_id,
+        "title": "A Parade in Erhenrang",
+        "filepath": format!("/{}/1/", book_id),
+        "number": 1,
+        "date": "2024-01-01"
+    }));
+
+    let missing = json(app.client.get("/api/v1/chapter/999").dispatch().await).await;
+    assert_eq!(missing["id"], -1);
+}
+
+#[rocket::async_test]
+async fn creates_chapters_with_a_storage_directory() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "title": "The Mad King", "number": 3, "date": "2024-01-03"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Chapter The Mad King was successfully created");
+
+    assert!(Path::new(&format!("storage/{}/3", book_id)).is_dir());
+
+    let chapters = json(app.client.get(format!("/api/v1/chapter/book-chapters/{}", book_id)).dispatch().await).await;
+    assert_eq!(chapters.as_array().unwrap().len(), 3);
+
+    // A directory left behind does not stop the chapter
+    fs::create_dir(format!("storage/{}/4", book_id)).unwrap();
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "title": "Estraven the Traitor", "number": 4, "date": "2024-01-04"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Chapter Estraven the Traitor was successfully created");
+
+    // Chapters that can not be saved leave no directory
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": 999, "title": "Nowhere", "number": 1, "date": "2024-01-04"}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+    assert!(!Path::new("storage/999").exists());
+}
+
+#[rocket::async_test]
+async fn rejects_duplicate_chapter_numbers() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "title": "Another first chapter", "number": 1, "date": "2024-01-03"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::Conflict).await;
+    assert_eq!(body["message"], format!("Book {} has chapter with number 1", book_id));
+
+    let chapters = json(app.client.get(format!("/api/v1/chapter/book-chapters/{}", book_id)).dispatch().await).await;
+    assert_eq!(chapters.as_array().unwrap().len(), 2);
+
+    // The database keeps numbers unique even when the check above is raced
+    let duplicate = chapter::ActiveModel {
+        book_id: ActiveValue::set(book_id),
+        title: ActiveValue::set("Another first chapter".to_string()),
+        filepath: ActiveValue::set(format!("/{}/1/", book_id)),
+        number: ActiveValue::set(1),
+        date: ActiveValue::set(NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()),
+        ..Default::default()
+    };
+    let err = duplicate.insert(&app.db).await.unwrap_err();
+    assert!(matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))));
+}
+
+#[rocket::async_test]
+async fn deletes_chapters_with_their_book() {
+    let app = TestApp::new().await;
+
+    let deleted = Book::delete_by_id(app.fixtures.book_id).exec(&app.db).await.unwrap();
+    assert_eq!(deleted.rows_affected, 1);
+
+    assert_eq!(Chapter::find().count(&app.db).await.unwrap(), 0);
+    assert_eq!(Comment::find().count(&app.db).await.unwrap(), 0);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_chapters() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": app.fixtures.book_id, "title": "", "number": 0, "date": "2024-01-03"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({
+        "title": ["must be 1 to 200 characters long"],
+        "number": ["must be positive"]
+    }));
+
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": app.fixtures.book_id, "title": "Undated", "number": 3, "date": "soon"}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::UnprocessableEntity).await;
+}
+
+#[rocket::async_test]
+async fn updates_chapters_but_not_their_numbers() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let [first_chapter, _] = app.fixtures.chapter_ids;
+
+    let response = app.client.put(format!("/api/v1/chapter/{}", first_chapter))
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "title": "A Parade", "number": 1, "date": "2024-02-01"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Chapter A Parade was successfully updated");
+
+    let chapter = json(app.client.get(format!("/api/v1/chapter/{}", first_chapter)).dispatch().await).await;
+    assert_eq!((chapter["title"].as_str(), chapter["date"].as_str()), (Some("A Parade"), Some("2024-02-01")));
+
+    let response = app.client.put(format!("/api/v1/chapter/{}", first_chapter))
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "title": "A Parade", "number": 2, "date": "2024-02-01"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::InternalServerError).await;
+    assert_eq!(body["message"], format!("Book {} has chapter with number 2", book_id));
+
+    let response = app.client.put("/api/v1/chapter/999")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": book_id, "title": "Nowhere", "number": 1, "date": "2024-02-01"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::NotFound).await;
+    assert_eq!(body["message"], "No chapter with id 999");
+
+    let entries = AuditLog::find().filter(audit_log::Column::EntityId.eq(999)).count(&app.db).await.unwrap();
+    assert_eq!(entries, 0);
+}
+
+#[rocket::async_test]
+async fn deletes_chapters_with_their_files() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let [first_chapter, _] = app.fixtures.chapter_ids;
+
+    let response = app.client.delete(format!("/api/v1/chapter/{}", first_chapter)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    assert!(!Path::new(&format!("storage/{}/1", book_id)).exists());
+
+    let response = app.client.delete(format!("/api/v1/chapter/{}", first_chapter)).dispatch().await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn serves_chapter_text_and_audio() {
+    let app = TestApp::new().await;
+    let [first_chapter, second_chapter] = app.fixtures.chapter_ids;
+
+    let text = json(app.client.get(format!("/api/v1/chapter/text/{}", first_chapter)).dispatch().await).await;
+    assert_eq!(text, chapter_text(1));
+
+    let audio = json(app.client.get(format!("/api/v1/chapter/audio/{}", second_chapter)).dispatch().await).await;
+    assert_eq!(audio, chapter_audio(2));
+
+    let response = app.client.get("/api/v1/chapter/text/999").dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let response = app.client.get("/api/v1/chapter/audio/999").dispatch().await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn reports_missing_chapter_files() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+    let [first_chapter, _] = app.fixtures.chapter_ids;
+
+    std::fs::remove_file(format!("storage/{}/1/audio.txt", book_id)).unwrap();
+
+    let response = app.client.get(format!("/api/v1/chapter/audio/{}", first_chapter)).dispatch().await;
+    error(response, Status::InternalServerError).await;
+}
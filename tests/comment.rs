@@ -0,0 +1,148 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::json;
+
+use common::{bearer, error, json, without_timestamps, TestApp};
+
+const HTML: &str = "Loved it, <span class=\"spoiler\">the hero survives</span>";
+
+#[rocket::async_test]
+async fn lists_and_finds_comments_with_markup() {
+    let app = TestApp::new().await;
+    let comment_id = app.fixtures.comment_id;
+
+    let comments = json(app.client.get("/api/v1/comment").dispatch().await).await;
+    assert_eq!(without_timestamps(comments.clone()), json!([{
+        "id": comment_id,
+        "book_id": app.fixtures.book_id,
+        "user_id": app.fixtures.writer.id,
+        "chapter_id": app.fixtures.chapter_ids[1],
+        "text": "Loved it, ||the hero survives||",
+        "upvotes": 0,
+        "downvotes": 0,
+        "html": HTML,
+        "spoiler_hidden": false
+    }]));
+
+    let comment = json(app.client.get(format!("/api/v1/comment/{}", comment_id)).dispatch().await).await;
+    assert_eq!(comment, comments[0]);
+
+    let missing = json(app.client.get("/api/v1/comment/999").dispatch().await).await;
+    assert_eq!(missing["id"], -1);
+}
+
+#[rocket::async_test]
+async fn hides_comments_on_unread_chapters() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+    let book_uri = format!("/api/v1/comment/book/{}", app.fixtures.book_id);
+    let chapter_uri = format!("/api/v1/comment/chapter/{}", app.fixtures.chapter_ids[1]);
+
+    for uri in [&book_uri, &chapter_uri] {
+        let comments = json(app.client.get(uri).header(bearer(&app.fixtures.reader.token)).dispatch().await).await;
+        assert_eq!(comments[0]["spoiler_hidden"], true);
+        assert_eq!(comments[0]["html"], format!("<span class=\"spoiler\">{}</span>", HTML));
+        assert_eq!(comments[0]["text"], "");
+    }
+
+    let response = app.client.put("/api/v1/user/progress")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "chapter_id": app.fixtures.chapter_ids[1]}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    for uri in [&book_uri, &chapter_uri] {
+        let comments = json(app.client.get(uri).header(bearer(&app.fixtures.reader.token)).dispatch().await).await;
+        assert_eq!(comments[0]["spoiler_hidden"], false);
+    }
+
+    // Without a reader nothing is hidden
+    let comments = json(app.client.get(format!("/api/v1/comment/book/{}", app.fixtures.book_id)).dispatch().await).await;
+    assert_eq!(comments[0]["spoiler_hidden"], false);
+
+    let comments = json(app.client.get(format!("/api/v1/comment/chapter/{}", app.fixtures.chapter_ids[0])).dispatch().await).await;
+    assert_eq!(comments, json!([]));
+}
+
+#[rocket::async_test]
+async fn creates_updates_and_deletes_comments() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/comment")
+        .header(ContentType::JSON)
+        .body(json!({
+            "book_id": app.fixtures.book_id,
+            "user_id": app.fixtures.reader.id,
+            "chapter_id": app.fixtures.chapter_ids[0],
+            "text": "<b>First!</b>"
+        }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Comment was successfully created");
+
+    let comments = json(app.client.get(format!("/api/v1/comment/chapter/{}", app.fixtures.chapter_ids[0])).dispatch().await).await;
+    assert_eq!(comments[0]["html"], "&lt;b&gt;First!&lt;/b&gt;");
+    let comment_id = comments[0]["id"].as_i64().unwrap();
+
+    let response = app.client.put(format!("/api/v1/comment/{}", comment_id))
+        .header(ContentType::JSON)
+        .body(json!({"text": "Second thoughts"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Comment {} was successfully updated", comment_id));
+
+    let comment = json(app.client.get(format!("/api/v1/comment/{}", comment_id)).dispatch().await).await;
+    assert_eq!(comment["text"], "Second thoughts");
+
+    let response = app.client.delete(format!("/api/v1/comment/{}", comment_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let response = app.client.delete(format!("/api/v1/comment/{}", comment_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 0");
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_comments() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/comment")
+        .header(ContentType::JSON)
+        .body(json!({
+            "book_id": app.fixtures.book_id,
+            "user_id": app.fixtures.reader.id,
+            "chapter_id": app.fixtures.chapter_ids[0],
+            "text": ""
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"text": ["must be 1 to 2000 characters long"]}));
+
+    let response = app.client.put(format!("/api/v1/comment/{}", app.fixtures.comment_id))
+        .header(ContentType::JSON)
+        .body(json!({"text": "x".repeat(2001)}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::UnprocessableEntity).await;
+
+    let response = app.client.post("/api/v1/comment")
+        .header(ContentType::JSON)
+        .body(json!({
+            "book_id": app.fixtures.book_id,
+            "user_id": 999,
+            "chapter_id": app.fixtures.chapter_ids[0],
+            "text": "Who am I?"
+        }).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+
+    let response = app.client.put("/api/v1/comment/999")
+        .header(ContentType::JSON)
+        .body(json!({"text": "Nothing to edit"}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+}
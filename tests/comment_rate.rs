@@ -0,0 +1,111 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::json;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+
+use egline_server::entities::comment_rate;
+
+use common::{error, json, TestApp};
+
+#[rocket::async_test]
+async fn counts_votes_on_comments() {
+    let app = TestApp::new().await;
+    let comment_id = app.fixtures.comment_id;
+    let reader_id = app.fixtures.reader.id;
+    let admin_id = app.fixtures.admin.id;
+
+    for (user_id, rate) in [(reader_id, 1), (admin_id, -1)] {
+        let response = app.client.post("/api/v1/comment-rate")
+            .header(ContentType::JSON)
+            .body(json!({"comment_id": comment_id, "user_id": user_id, "rate": rate}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(json(response).await, "Comment rate was successfully created");
+    }
+
+    let rates = json(app.client.get("/api/v1/comment-rate").dispatch().await).await;
+    assert_eq!(rates.as_array().unwrap().len(), 2);
+
+    let comment = json(app.client.get(format!("/api/v1/comment/{}", comment_id)).dispatch().await).await;
+    assert_eq!((comment["upvotes"].as_i64(), comment["downvotes"].as_i64()), (Some(1), Some(1)));
+
+    let response = app.client.put("/api/v1/comment-rate")
+        .header(ContentType::JSON)
+        .body(json!({"comment_id": comment_id, "user_id": admin_id, "rate": 1}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Comment rate was successfully updated");
+
+    let comment = json(app.client.get(format!("/api/v1/comment/{}", comment_id)).dispatch().await).await;
+    assert_eq!((comment["upvotes"].as_i64(), comment["downvotes"].as_i64()), (Some(2), Some(0)));
+
+    let response = app.client.delete(format!("/api/v1/comment-rate/{}/{}", comment_id, reader_id)).dispatch().await;
+    assert_eq!(json(response).await, "Comment rate was successfully deleted");
+
+    let comment = json(app.client.get(format!("/api/v1/comment/{}", comment_id)).dispatch().await).await;
+    assert_eq!(comment["upvotes"], 1);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_comment_rates() {
+    let app = TestApp::new().await;
+    let comment_id = app.fixtures.comment_id;
+    let reader_id = app.fixtures.reader.id;
+
+    for rate in [0, 2, -5] {
+        let response = app.client.post("/api/v1/comment-rate")
+            .header(ContentType::JSON)
+            .body(json!({"comment_id": comment_id, "user_id": reader_id, "rate": rate}).to_string())
+            .dispatch()
+            .await;
+        let body = error(response, Status::UnprocessableEntity).await;
+        assert_eq!(body["errors"], json!({"rate": ["must be 1 or -1"]}));
+
+        let response = app.client.put("/api/v1/comment-rate")
+            .header(ContentType::JSON)
+            .body(json!({"comment_id": comment_id, "user_id": reader_id, "rate": rate}).to_string())
+            .dispatch()
+            .await;
+        let body = error(response, Status::UnprocessableEntity).await;
+        assert_eq!(body["errors"], json!({"rate": ["must be 1 or -1"]}));
+    }
+
+    let rates = json(app.client.get("/api/v1/comment-rate").dispatch().await).await;
+    assert_eq!(rates, json!([]));
+
+    // The database refuses them as well
+    let comment_rate = comment_rate::ActiveModel {
+        comment_id: ActiveValue::set(comment_id),
+        user_id: ActiveValue::set(reader_id),
+        rate: ActiveValue::set(2),
+        ..Default::default()
+    };
+    assert!(comment_rate.insert(&app.db).await.is_err());
+}
+
+#[rocket::async_test]
+async fn rejects_votes_twice_and_on_missing_comments() {
+    let app = TestApp::new().await;
+    let vote = json!({"comment_id": app.fixtures.comment_id, "user_id": app.fixtures.reader.id, "rate": 1}).to_string();
+
+    let response = app.client.post("/api/v1/comment-rate").header(ContentType::JSON).body(vote.clone()).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post("/api/v1/comment-rate").header(ContentType::JSON).body(vote).dispatch().await;
+    error(response, Status::InternalServerError).await;
+
+    let response = app.client.post("/api/v1/comment-rate")
+        .header(ContentType::JSON)
+        .body(json!({"comment_id": 999, "user_id": app.fixtures.reader.id, "rate": 1}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+
+    let response = app.client.put("/api/v1/comment-rate")
+        .header(ContentType::JSON)
+        .body(json!({"comment_id": app.fixtures.comment_id, "user_id": app.fixtures.admin.id, "rate": 1}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+}
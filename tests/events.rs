@@ -0,0 +1,129 @@
+mod common;
+
+use std::time::Duration;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::LocalResponse;
+use rocket::serde::json::serde_json::{self, json, Value};
+use rocket::tokio::io::AsyncReadExt;
+use rocket::tokio::time::timeout;
+
+use common::{bearer, error, without_timestamps, TestApp};
+
+struct PushEvent {
+    id: u64,
+    kind: String,
+    data: Value
+}
+
+/// Reads the stream until `count` events arrived or it stays silent for a second.
+/// The stream never ends on its own, so it can not be read to the end.
+async fn read_events(response: &mut LocalResponse<'_>, count: usize) -> Vec<PushEvent> {
+    let mut text = String::new();
+    let mut chunk = [0; 1024];
+
+    while text.matches("\n\n").count() < count {
+        match timeout(Duration::from_secs(1), response.read(&mut chunk)).await {
+            Ok(Ok(read)) if read > 0 => text.push_str(&String::from_utf8_lossy(&chunk[..read])),
+            _ => break
+        }
+    }
+
+    text.split("\n\n")
+        .filter(|event| event.contains("data:"))
+        .map(|event| {
+            let field = |name: &str| event.lines()
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+
+            PushEvent {
+                id: field("id:").parse().unwrap(),
+                kind: field("event:"),
+                data: serde_json::from_str(&field("data:")).unwrap()
+            }
+        })
+        .collect()
+}
+
+#[rocket::async_test]
+async fn requires_a_session() {
+    let app = TestApp::new().await;
+
+    let response = app.client.get("/api/v1/events").dispatch().await;
+    error(response, Status::Unauthorized).await;
+
+    let response = app.client.get("/api/v1/events").header(bearer("expired-token")).dispatch().await;
+    error(response, Status::Unauthorized).await;
+
+    let response = app.client.get("/api/v1/events?token=expired-token").dispatch().await;
+    error(response, Status::Unauthorized).await;
+}
+
+#[rocket::async_test]
+async fn pushes_new_chapter_notifications() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/follow/book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": app.fixtures.reader.id, "book_id": app.fixtures.book_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut stream = app.client.get("/api/v1/events").header(bearer(&app.fixtures.reader.token)).dispatch().await;
+    assert_eq!(stream.status(), Status::Ok);
+    assert_eq!(stream.content_type(), Some(ContentType::EventStream));
+
+    let response = app.client.post("/api/v1/chapter")
+        .header(ContentType::JSON)
+        .body(json!({"book_id": app.fixtures.book_id, "title": "The Mad King", "number": 3, "date": "2024-01-03"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let events = read_events(&mut stream, 1).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "notification");
+    assert_eq!(events[0].data["user_id"], app.fixtures.reader.id);
+    assert_eq!(events[0].data["kind"], "new_chapter");
+}
+
+#[rocket::async_test]
+async fn replays_missed_events_after_the_last_event_id() {
+    let app = TestApp::new().await;
+    let comment_id = app.fixtures.comment_id;
+
+    // The reader and the admin vote on the writer's comment while the writer is away
+    for (user_id, rate) in [(app.fixtures.reader.id, 1), (app.fixtures.admin.id, -1)] {
+        let response = app.client.post("/api/v1/comment-rate")
+            .header(ContentType::JSON)
+            .body(json!({"comment_id": comment_id, "user_id": user_id, "rate": rate}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    // Without `Last-Event-ID` only new events are sent
+    let mut stream = app.client.get(format!("/api/v1/events?token={}", app.fixtures.writer.token)).dispatch().await;
+    assert!(read_events(&mut stream, 1).await.is_empty());
+
+    let mut stream = app.client.get(format!("/api/v1/events?token={}", app.fixtures.writer.token))
+        .header(Header::new("Last-Event-ID", "0"))
+        .dispatch()
+        .await;
+    let events = read_events(&mut stream, 2).await;
+    assert_eq!(events.iter().map(|event| event.kind.as_str()).collect::<Vec<_>>(), ["comment_vote", "comment_vote"]);
+    assert_eq!(without_timestamps(events[0].data.clone()), json!({"comment_id": comment_id, "user_id": app.fixtures.reader.id, "rate": 1}));
+    assert!(events[0].id < events[1].id);
+
+    let mut stream = app.client.get("/api/v1/events")
+        .header(bearer(&app.fixtures.writer.token))
+        .header(Header::new("Last-Event-ID", events[0].id.to_string()))
+        .dispatch()
+        .await;
+    let missed = read_events(&mut stream, 2).await;
+    assert_eq!(missed.len(), 1);
+    assert_eq!(missed[0].data["user_id"], app.fixtures.admin.id);
+}
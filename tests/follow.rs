@@ -0,0 +1,65 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::json;
+
+use common::{error, json, TestApp};
+
+#[rocket::async_test]
+async fn follows_and_unfollows_books_and_authors() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+    let book_id = app.fixtures.book_id;
+    let author_id = app.fixtures.author_id;
+
+    let follows = json(app.client.get(format!("/api/v1/follow/{}", reader_id)).dispatch().await).await;
+    assert_eq!(follows, json!({"books": [], "authors": []}));
+
+    let response = app.client.post("/api/v1/follow/book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "book_id": book_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("User {} now follows book {}", reader_id, book_id));
+
+    let response = app.client.post("/api/v1/follow/author")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "author_id": author_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("User {} now follows author {}", reader_id, author_id));
+
+    let follows = json(app.client.get(format!("/api/v1/follow/{}", reader_id)).dispatch().await).await;
+    assert_eq!(follows, json!({"books": [book_id], "authors": [author_id]}));
+
+    let response = app.client.delete(format!("/api/v1/follow/book/{}/{}", reader_id, book_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let response = app.client.delete(format!("/api/v1/follow/author/{}/{}", reader_id, author_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let response = app.client.delete(format!("/api/v1/follow/author/{}/{}", reader_id, author_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 0");
+
+    let follows = json(app.client.get(format!("/api/v1/follow/{}", reader_id)).dispatch().await).await;
+    assert_eq!(follows, json!({"books": [], "authors": []}));
+}
+
+#[rocket::async_test]
+async fn rejects_duplicate_and_dangling_follows() {
+    let app = TestApp::new().await;
+    let follow = json!({"user_id": app.fixtures.reader.id, "book_id": app.fixtures.book_id}).to_string();
+
+    let response = app.client.post("/api/v1/follow/book").header(ContentType::JSON).body(follow.clone()).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post("/api/v1/follow/book").header(ContentType::JSON).body(follow).dispatch().await;
+    error(response, Status::InternalServerError).await;
+
+    let response = app.client.post("/api/v1/follow/author")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": app.fixtures.reader.id, "author_id": 999}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::InternalServerError).await;
+}
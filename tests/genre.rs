@@ -0,0 +1,126 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::json;
+
+use common::{error, json, timestamp, without_timestamps, TestApp};
+
+#[rocket::async_test]
+async fn lists_and_finds_genres() {
+    let app = TestApp::new().await;
+    let genre_id = app.fixtures.genre_id;
+
+    let genres = json(app.client.get("/api/v1/genre").dispatch().await).await;
+    assert_eq!(without_timestamps(genres), json!([{"id": genre_id, "title": "Fantasy"}]));
+
+    let genre = json(app.client.get(format!("/api/v1/genre/{}", genre_id)).dispatch().await).await;
+    assert_eq!(genre["title"], "Fantasy");
+
+    let missing = json(app.client.get("/api/v1/genre/999").dispatch().await).await;
+    assert_eq!(without_timestamps(missing), json!({"id": -1, "title": ""}));
+}
+
+#[rocket::async_test]
+async fn creates_genres_with_capitalized_titles() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/genre")
+        .header(ContentType::JSON)
+        .body(json!({"title": "sCIENCE fiction"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Genre sCIENCE fiction was successfully created");
+
+    let genres = json(app.client.get("/api/v1/genre").dispatch().await).await;
+    assert!(genres.as_array().unwrap().iter().any(|genre| genre["title"] == "Science fiction"));
+}
+
+#[rocket::async_test]
+async fn rejects_taken_and_empty_titles() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/genre")
+        .header(ContentType::JSON)
+        .body(json!({"title": "fantasy"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"title": ["is already taken"]}));
+
+    let response = app.client.post("/api/v1/genre")
+        .header(ContentType::JSON)
+        .body(json!({"title": ""}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"title": ["must be 1 to 50 characters long"]}));
+}
+
+#[rocket::async_test]
+async fn rejects_unparsable_bodies() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/genre")
+        .header(ContentType::JSON)
+        .body("{\"title\":")
+        .dispatch()
+        .await;
+    error(response, Status::BadRequest).await;
+}
+
+#[rocket::async_test]
+async fn renames_genres() {
+    let app = TestApp::new().await;
+    let genre_id = app.fixtures.genre_id;
+
+    let response = app.client.put(format!("/api/v1/genre/{}", genre_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "high fantasy"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Genre High fantasy was successfully updated");
+
+    // Keeping its own title is not a conflict
+    let response = app.client.put(format!("/api/v1/genre/{}", genre_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "High fantasy"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn stamps_creation_and_updates() {
+    let app = TestApp::new().await;
+    let genre_id = app.fixtures.genre_id;
+
+    let genre = json(app.client.get(format!("/api/v1/genre/{}", genre_id)).dispatch().await).await;
+    let created_at = timestamp(&genre["created_at"]).unwrap();
+    assert_eq!(timestamp(&genre["updated_at"]), Some(created_at));
+
+    let response = app.client.put(format!("/api/v1/genre/{}", genre_id))
+        .header(ContentType::JSON)
+        .body(json!({"title": "High fantasy"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let genre = json(app.client.get(format!("/api/v1/genre/{}", genre_id)).dispatch().await).await;
+    assert_eq!(timestamp(&genre["created_at"]), Some(created_at));
+    assert!(timestamp(&genre["updated_at"]).unwrap() > created_at);
+}
+
+#[rocket::async_test]
+async fn deletes_genres() {
+    let app = TestApp::new().await;
+    let genre_id = app.fixtures.genre_id;
+
+    let response = app.client.delete(format!("/api/v1/genre/{}", genre_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let response = app.client.delete(format!("/api/v1/genre/{}", genre_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 0");
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", app.fixtures.book_id)).dispatch().await).await;
+    assert_eq!(book["genres"], json!([]));
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::{json, Value};
+
+use common::{bearer, error, json, TestApp, TestUser};
+
+/// Makes the reader follow the book and the admin follow its author, then publishes chapters
+/// 3 to `last_number` so both of them get a notification per chapter.
+async fn publish_followed_chapters(app: &TestApp, last_number: i32) {
+    let response = app.client.post("/api/v1/follow/book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": app.fixtures.reader.id, "book_id": app.fixtures.book_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post("/api/v1/follow/author")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": app.fixtures.admin.id, "author_id": app.fixtures.author_id}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    for number in 3..=last_number {
+        let response = app.client.post("/api/v1/chapter")
+            .header(ContentType::JSON)
+            .body(json!({
+                "book_id": app.fixtures.book_id,
+                "title": format!("Chapter {}", number),
+                "number": number,
+                "date": "2024-02-01"
+            }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
+
+/// Requests a notification route with the user's session.
+async fn get(app: &TestApp, user: &TestUser, uri: &str) -> Value {
+    json(app.client.get(format!("/api/v1/notification{}", uri)).header(bearer(&user.token)).dispatch().await).await
+}
+
+fn ids(page: &Value) -> Vec<i64> {
+    page["items"].as_array().unwrap().iter().map(|item| item["id"].as_i64().unwrap()).collect()
+}
+
+#[rocket::async_test]
+async fn notifies_followers_of_new_chapters() {
+    let app = TestApp::new().await;
+    publish_followed_chapters(&app, 3).await;
+
+    for user in [&app.fixtures.reader, &app.fixtures.admin] {
+        let page = get(&app, user, "").await;
+        assert_eq!((page["total"].as_u64(), page["unread"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(page["items"][0]["kind"], "new_chapter");
+        assert_eq!(page["items"][0]["book_id"], app.fixtures.book_id);
+        assert_eq!(page["items"][0]["message"], "The Left Hand of Darkness: new chapter 3 \"Chapter 3\"");
+        assert_eq!(page["items"][0]["is_read"], false);
+    }
+
+    // The writer follows nothing
+    let page = get(&app, &app.fixtures.writer, "").await;
+    assert_eq!(page["items"], json!([]));
+
+    let response = app.client.get("/api/v1/notification").dispatch().await;
+    error(response, Status::Unauthorized).await;
+}
+
+#[rocket::async_test]
+async fn pages_notifications_newest_first() {
+    let app = TestApp::new().await;
+    let reader = &app.fixtures.reader;
+    publish_followed_chapters(&app, 5).await;
+
+    let all = get(&app, reader, "").await;
+    assert_eq!((all["page"].as_u64(), all["per_page"].as_u64(), all["total"].as_u64()), (Some(1), Some(20), Some(3)));
+    assert_eq!(all["items"][0]["message"], "The Left Hand of Darkness: new chapter 5 \"Chapter 5\"");
+
+    let all_ids = ids(&all);
+    let second_page = get(&app, reader, "?page=2&per_page=2").await;
+    assert_eq!(ids(&second_page), all_ids[2..]);
+
+    // Out of range values are clamped
+    let page = get(&app, reader, "?page=0&per_page=1000").await;
+    assert_eq!((page["page"].as_u64(), page["per_page"].as_u64()), (Some(1), Some(100)));
+}
+
+#[rocket::async_test]
+async fn marks_notifications_as_read() {
+    let app = TestApp::new().await;
+    let reader = &app.fixtures.reader;
+    publish_followed_chapters(&app, 5).await;
+
+    let page = get(&app, reader, "").await;
+    let [newest, _, oldest] = ids(&page)[..] else { panic!("three notifications expected") };
+
+    let response = app.client.put(format!("/api/v1/notification/read/{}", newest))
+        .header(bearer(&reader.token))
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Notification {} was marked as read", newest));
+
+    assert_eq!(get(&app, reader, "/unread-count").await, json!({"unread": 2}));
+
+    let page = get(&app, reader, "?unread_only=true").await;
+    assert_eq!((page["total"].as_u64(), page["unread"].as_u64()), (Some(2), Some(2)));
+    assert!(!ids(&page).contains(&newest));
+
+    let response = app.client.put("/api/v1/notification/read-all").header(bearer(&reader.token)).dispatch().await;
+    assert_eq!(json(response).await, "Number of updated entries: 2");
+
+    assert_eq!(get(&app, reader, "/unread-count").await, json!({"unread": 0}));
+
+    // Other users keep their unread notifications
+    assert_eq!(get(&app, &app.fixtures.admin, "/unread-count").await, json!({"unread": 3}));
+
+    let response = app.client.delete(format!("/api/v1/notification/{}", oldest))
+        .header(bearer(&reader.token))
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let page = get(&app, reader, "").await;
+    assert_eq!(page["total"], 2);
+}
+
+#[rocket::async_test]
+async fn keeps_notifications_to_their_user() {
+    let app = TestApp::new().await;
+    publish_followed_chapters(&app, 3).await;
+
+    let page = get(&app, &app.fixtures.reader, "").await;
+    let id = ids(&page)[0];
+    let writer = bearer(&app.fixtures.writer.token);
+
+    let response = app.client.put(format!("/api/v1/notification/read/{}", id)).header(writer.clone()).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let response = app.client.delete(format!("/api/v1/notification/{}", id)).header(writer.clone()).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let response = app.client.put("/api/v1/notification/read-all").header(writer).dispatch().await;
+    assert_eq!(json(response).await, "Number of updated entries: 0");
+
+    let page = get(&app, &app.fixtures.reader, "").await;
+    assert_eq!((page["total"].as_u64(), page["unread"].as_u64()), (Some(1), Some(1)));
+
+    for response in [
+        app.client.get("/api/v1/notification/unread-count").dispatch().await,
+        app.client.put(format!("/api/v1/notification/read/{}", id)).dispatch().await,
+        app.client.put("/api/v1/notification/read-all").dispatch().await,
+        app.client.delete(format!("/api/v1/notification/{}", id)).dispatch().await
+    ] {
+        error(response, Status::Unauthorized).await;
+    }
+}
+
+#[rocket::async_test]
+async fn reports_missing_notifications() {
+    let app = TestApp::new().await;
+    let reader = bearer(&app.fixtures.reader.token);
+
+    let response = app.client.put("/api/v1/notification/read/999").header(reader.clone()).dispatch().await;
+    let body = error(response, Status::NotFound).await;
+    assert_eq!(body["message"], "No notification with id 999");
+
+    let response = app.client.delete("/api/v1/notification/999").header(reader.clone()).dispatch().await;
+    let body = error(response, Status::NotFound).await;
+    assert_eq!(body["message"], "No notification with id 999");
+
+    let response = app.client.put("/api/v1/notification/read-all").header(reader).dispatch().await;
+    assert_eq!(json(response).await, "Number of updated entries: 0");
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::{json, Value};
+
+use common::{error, json, TestApp};
+
+fn review_data(user_id: i32, book_id: i32, rate: i32, spoiler: bool) -> String {
+    json!({
+        "user_id": user_id,
+        "book_id": book_id,
+        "rate": rate,
+        "title": "Cold and brilliant",
+        "body": "Genly Ai ||survives the ice||",
+        "spoiler": spoiler
+    }).to_string()
+}
+
+fn rates(reviews: &Value) -> Vec<i64> {
+    reviews.as_array().unwrap().iter().map(|review| review["rate"].as_i64().unwrap()).collect()
+}
+
+#[rocket::async_test]
+async fn lists_and_finds_reviews() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/review")
+        .header(ContentType::JSON)
+        .body(review_data(app.fixtures.reader.id, book_id, 2, true))
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Review Cold and brilliant was successfully created");
+
+    let reviews = json(app.client.get(format!("/api/v1/review/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(rates(&reviews), [2, 4]);
+    // Spoiler reviews are hidden as a whole
+    assert_eq!(
+        reviews[0]["body_html"],
+        "<span class=\"spoiler\">Genly Ai <span class=\"spoiler\">survives the ice</span></span>"
+    );
+    assert_eq!(reviews[0]["body"], "");
+    assert_ne!(reviews[1]["body"], "");
+
+    for (sort, expected) in [("oldest", [4, 2]), ("rate_high", [4, 2]), ("rate_low", [2, 4]), ("newest", [2, 4])] {
+        let reviews = json(app.client.get(format!("/api/v1/review/book/{}?sort={}", book_id, sort)).dispatch().await).await;
+        assert_eq!(rates(&reviews), expected, "sorted by {}", sort);
+    }
+
+    let response = app.client.get(format!("/api/v1/review/book/{}?sort=random", book_id)).dispatch().await;
+    error(response, Status::BadRequest).await;
+
+    let review = json(app.client.get(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await).await;
+    assert_eq!(review["title"], "A slow start that pays off");
+    assert_eq!(review["edited_at"], json!(null));
+
+    let response = app.client.get("/api/v1/review/999").dispatch().await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn counts_review_rates_towards_the_book() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/review")
+        .header(ContentType::JSON)
+        .body(review_data(app.fixtures.reader.id, book_id, 2, false))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!((book["rating"].as_f64(), book["rates"].as_u64()), (Some(3.0), Some(2)));
+
+    let response = app.client.put(format!("/api/v1/review/{}", app.fixtures.review_id))
+        .header(ContentType::JSON)
+        .body(review_data(app.fixtures.writer.id, book_id, 5, false))
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Review Cold and brilliant was successfully updated");
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!(book["rating"], 3.5);
+
+    let review = json(app.client.get(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await).await;
+    assert!(review["edited_at"].is_string());
+
+    let response = app.client.delete(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let book = json(app.client.get(format!("/api/v1/book/{}", book_id)).dispatch().await).await;
+    assert_eq!((book["rating"].as_f64(), book["rates"].as_u64()), (Some(2.0), Some(1)));
+
+    let response = app.client.delete(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn rejects_second_and_invalid_reviews() {
+    let app = TestApp::new().await;
+    let book_id = app.fixtures.book_id;
+
+    let response = app.client.post("/api/v1/review")
+        .header(ContentType::JSON)
+        .body(review_data(app.fixtures.writer.id, book_id, 3, false))
+        .dispatch()
+        .await;
+    let body = error(response, Status::Conflict).await;
+    assert_eq!(body["message"], format!("User {} has already reviewed book {}", app.fixtures.writer.id, book_id));
+
+    let response = app.client.post("/api/v1/review")
+        .header(ContentType::JSON)
+        .body(json!({
+            "user_id": app.fixtures.reader.id,
+            "book_id": book_id,
+            "rate": 6,
+            "title": "",
+            "body": "",
+            "spoiler": false
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({
+        "rate": ["must be between 1 and 5"],
+        "title": ["must be 1 to 200 characters long"],
+        "body": ["must be 1 to 20000 characters long"]
+    }));
+
+    let response = app.client.put(format!("/api/v1/review/{}", app.fixtures.review_id))
+        .header(ContentType::JSON)
+        .body(review_data(app.fixtures.writer.id, book_id, 0, false))
+        .dispatch()
+        .await;
+    error(response, Status::UnprocessableEntity).await;
+}
+
+#[rocket::async_test]
+async fn counts_helpfulness_votes() {
+    let app = TestApp::new().await;
+    let review_id = app.fixtures.review_id;
+    let reader_id = app.fixtures.reader.id;
+    let admin_id = app.fixtures.admin.id;
+
+    for (user_id, vote) in [(reader_id, 1), (admin_id, -1)] {
+        let response = app.client.post("/api/v1/review/vote")
+            .header(ContentType::JSON)
+            .body(json!({"review_id": review_id, "user_id": user_id, "vote": vote}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(json(response).await, "Review vote was successfully created");
+    }
+
+    let review = json(app.client.get(format!("/api/v1/review/{}", review_id)).dispatch().await).await;
+    assert_eq!((review["helpful"].as_i64(), review["unhelpful"].as_i64()), (Some(1), Some(1)));
+
+    let response = app.client.put("/api/v1/review/vote")
+        .header(ContentType::JSON)
+        .body(json!({"review_id": review_id, "user_id": admin_id, "vote": 1}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Review vote was successfully updated");
+
+    let response = app.client.delete(format!("/api/v1/review/vote/{}/{}", review_id, reader_id)).dispatch().await;
+    assert_eq!(json(response).await, "Review vote was successfully deleted");
+
+    let review = json(app.client.get(format!("/api/v1/review/{}", review_id)).dispatch().await).await;
+    assert_eq!((review["helpful"].as_i64(), review["unhelpful"].as_i64()), (Some(1), Some(0)));
+
+    // Sorting by helpfulness puts the voted review first
+    let response = app.client.post("/api/v1/review")
+        .header(ContentType::JSON)
+        .body(review_data(reader_id, app.fixtures.book_id, 2, false))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let reviews = json(app.client.get(format!("/api/v1/review/book/{}?sort=helpful", app.fixtures.book_id)).dispatch().await).await;
+    assert_eq!(rates(&reviews), [4, 2]);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_votes() {
+    let app = TestApp::new().await;
+    let review_id = app.fixtures.review_id;
+
+    let response = app.client.post("/api/v1/review/vote")
+        .header(ContentType::JSON)
+        .body(json!({"review_id": review_id, "user_id": app.fixtures.writer.id, "vote": 1}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::BadRequest).await;
+    assert_eq!(body["message"], "Users can not vote for their own reviews");
+
+    let response = app.client.post("/api/v1/review/vote")
+        .header(ContentType::JSON)
+        .body(json!({"review_id": review_id, "user_id": app.fixtures.reader.id, "vote": 2}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"vote": ["must be 1 or -1"]}));
+
+    let response = app.client.put("/api/v1/review/vote")
+        .header(ContentType::JSON)
+        .body(json!({"review_id": 999, "user_id": app.fixtures.reader.id, "vote": 1}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+}
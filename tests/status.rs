@@ -0,0 +1,71 @@
+mod common;
+
+use migration::{Migrator, MigratorTrait};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::serde_json::{json, Value};
+
+use common::{json, TestApp};
+
+fn last_migration() -> String {
+    Migrator::migrations().last().unwrap().name().to_string()
+}
+
+#[rocket::async_test]
+async fn reports_health_and_versions() {
+    let app = TestApp::new().await;
+
+    let health = json(app.client.get("/health").dispatch().await).await;
+    assert_eq!(health, json!({"status": "ok"}));
+
+    let version = json(app.client.get("/version").dispatch().await).await;
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert!(version["git_hash"].is_string());
+    assert_eq!(version["schema_version"], last_migration());
+
+    let status = json(app.client.get("/api/v1/status").dispatch().await).await;
+    assert_eq!(status, json!({"schema_version": last_migration(), "pending_migrations": []}));
+}
+
+#[rocket::async_test]
+async fn reports_readiness_of_every_dependency() {
+    let app = TestApp::new().await;
+
+    let ready = json(app.client.get("/ready").dispatch().await).await;
+    assert_eq!(ready, json!({"ready": true, "database": "ok", "storage": "ok", "migrations": "ok"}));
+
+    Migrator::down(&app.db, Some(1)).await.unwrap();
+    std::fs::remove_dir_all("storage").unwrap();
+
+    let response = app.client.get("/ready").dispatch().await;
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+
+    let ready = response.into_json::<Value>().await.unwrap();
+    assert_eq!((&ready["ready"], &ready["database"]), (&json!(false), &json!("ok")));
+    assert_ne!(ready["storage"], "ok");
+    assert_eq!(ready["migrations"], format!("pending: {}", last_migration()));
+
+    let status = json(app.client.get("/api/v1/status").dispatch().await).await;
+    assert_eq!(status["pending_migrations"], json!([last_migration()]));
+}
+
+#[rocket::async_test]
+async fn exposes_prometheus_metrics() {
+    let app = TestApp::new().await;
+
+    let response = app.client.get("/api/v1/genre").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.get("/metrics").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::new("text", "plain").with_params(("version", "0.0.4"))));
+
+    let metrics = response.into_string().await.unwrap();
+    let genre_requests = metrics.lines()
+        .find(|line| line.starts_with("egline_http_requests_total") && line.contains("route=\"/api/v1/genre\""))
+        .expect("the genre request is counted");
+    assert!(genre_requests.contains("status=\"200\""));
+    assert!(genre_requests.ends_with(" 1"));
+
+    assert!(metrics.contains("egline_db_query_duration_seconds_count"));
+    assert!(metrics.contains("egline_db_pool_connections{state=\"idle\"}"));
+}
@@ -0,0 +1,491 @@
+mod common;
+
+use chrono::Utc;
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::serde_json::json;
+
+use egline_server::retention;
+
+use common::{bearer, error, image_upload, json, png, TestApp, PASSWORD};
+
+#[rocket::async_test]
+async fn lists_and_finds_users_without_passwords() {
+    let app = TestApp::new().await;
+    let reader = &app.fixtures.reader;
+
+    let users = json(app.client.get("/api/v1/user").dispatch().await).await;
+    assert_eq!(users.as_array().unwrap().len(), 3);
+    assert!(users.as_array().unwrap().iter().all(|user| user.get("password").is_none()));
+
+    let user = json(app.client.get(format!("/api/v1/user/{}", reader.id)).dispatch().await).await;
+    assert_eq!(user["email"], "reader@egline.com");
+    assert_eq!(user["role"], "reader");
+    assert_eq!(user["avatar_url"], json!(null));
+
+    let missing = json(app.client.get("/api/v1/user/999").dispatch().await).await;
+    assert_eq!(missing["id"], -1);
+}
+
+#[rocket::async_test]
+async fn registers_users() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/user")
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "New@Egline.com",
+            "display_name": "newcomer",
+            "password": "another-secret",
+            "saved_books": {}
+        }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "User newcomer was successfully created");
+
+    // Emails are stored lowercased
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "new@egline.com", "password": "another-secret"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await["display_name"], "newcomer");
+}
+
+#[rocket::async_test]
+async fn rejects_taken_and_invalid_registrations() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/user")
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "READER@egline.com",
+            "display_name": "writer",
+            "password": "another-secret",
+            "saved_books": {}
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"email": ["is already taken"], "display_name": ["is already taken"]}));
+
+    let response = app.client.post("/api/v1/user")
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "not-an-email",
+            "display_name": "ab",
+            "password": "short",
+            "saved_books": {}
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({
+        "email": ["must be a valid email"],
+        "display_name": ["must be 3 to 32 characters long"],
+        "password": ["must be at least 6 characters long"]
+    }));
+}
+
+#[rocket::async_test]
+async fn updates_users() {
+    let app = TestApp::new().await;
+    let reader = &app.fixtures.reader;
+
+    let response = app.client.put(format!("/api/v1/user/{}", reader.id))
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "reader@egline.com",
+            "display_name": "avid reader",
+            "password": "",
+            "saved_books": {"Reading": [app.fixtures.book_id]}
+        }).to_string())
+        .dispatch()
+        .await;
+    let user = json(response).await;
+    assert_eq!(user["display_name"], "avid reader");
+    assert_eq!(user["saved_books"], json!({"Reading": [app.fixtures.book_id]}));
+
+    // An empty password keeps the current one
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "reader@egline.com", "password": PASSWORD}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.put(format!("/api/v1/user/{}", reader.id))
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "writer@egline.com",
+            "display_name": "avid reader",
+            "password": "",
+            "saved_books": {}
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"email": ["is already taken"]}));
+}
+
+#[rocket::async_test]
+async fn deletes_users() {
+    let app = TestApp::new().await;
+    let writer_id = app.fixtures.writer.id;
+
+    let response = app.client.delete(format!("/api/v1/user/{}", writer_id)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    // Missing users come back as the placeholder with id -1
+    let user = json(app.client.get(format!("/api/v1/user/{}", writer_id)).dispatch().await).await;
+    assert_eq!(user["id"], -1);
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": app.fixtures.writer.email, "password": PASSWORD}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::BadRequest).await;
+
+    // Trashed accounts can not be changed either
+    let response = app.client.put(format!("/api/v1/user/{}", writer_id))
+        .header(ContentType::JSON)
+        .body(json!({"email": "writer@egline.com", "display_name": "writer", "password": "", "saved_books": {}}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+
+    let response = app.client.post(format!("/api/v1/user/edit/{}", writer_id))
+        .header(ContentType::JSON)
+        .body(json!({"email": "writer@egline.com", "display_name": "writer", "password": "", "new_password": ""}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+
+    // Their comments and reviews stay until the purge removes them with the user, the reviews are not served
+    let comments = json(app.client.get("/api/v1/comment").dispatch().await).await;
+    assert_eq!(comments.as_array().unwrap().len(), 1);
+    assert_eq!(json(app.client.get(format!("/api/v1/review/book/{}", app.fixtures.book_id)).dispatch().await).await, json!([]));
+    let response = app.client.get(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let purged = retention::purge(&app.db, Utc::now().naive_utc()).await.unwrap();
+    assert_eq!(purged.users, 1);
+
+    let comments = json(app.client.get("/api/v1/comment").dispatch().await).await;
+    assert_eq!(comments, json!([]));
+    let response = app.client.get(format!("/api/v1/review/{}", app.fixtures.review_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn uploads_and_serves_avatars() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+
+    let response = app.client.get(format!("/api/v1/user/{}/avatar", reader_id)).dispatch().await;
+    error(response, Status::NotFound).await;
+
+    let (content_type, body) = image_upload(&png(1024, 512));
+    let response = app.client.put(format!("/api/v1/user/{}/avatar", reader_id))
+        .header(bearer(&app.fixtures.reader.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, format!("Avatar of user {} was updated", reader_id));
+
+    let user = json(app.client.get(format!("/api/v1/user/{}", reader_id)).dispatch().await).await;
+    assert_eq!(user["avatar_url"], format!("/api/v1/user/{}/avatar", reader_id));
+
+    let response = app.client.get(format!("/api/v1/user/{}/avatar?size=small", reader_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::PNG));
+    let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+    let avatar = image::load_from_memory(&response.into_bytes().await.unwrap()).unwrap();
+    assert_eq!((avatar.width(), avatar.height()), (64, 32));
+
+    let response = app.client.get(format!("/api/v1/user/{}/avatar?size=small", reader_id))
+        .header(Header::new("If-None-Match", etag))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::NotModified);
+}
+
+#[rocket::async_test]
+async fn rejects_invalid_avatars() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+
+    let (content_type, body) = image_upload(b"plain text");
+    let response = app.client.put(format!("/api/v1/user/{}/avatar", reader_id))
+        .header(bearer(&app.fixtures.reader.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"image": ["must be a PNG, JPEG, GIF or WebP image"]}));
+
+    // Only the signed in user can change their avatar
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(format!("/api/v1/user/{}/avatar", reader_id))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Unauthorized).await;
+
+    let (content_type, body) = image_upload(&png(8, 8));
+    let response = app.client.put(format!("/api/v1/user/{}/avatar", reader_id))
+        .header(bearer(&app.fixtures.writer.token))
+        .header(content_type)
+        .body(body)
+        .dispatch()
+        .await;
+    error(response, Status::Forbidden).await;
+
+    let response = app.client.get(format!("/api/v1/user/{}/avatar?size=huge", reader_id)).dispatch().await;
+    error(response, Status::BadRequest).await;
+
+    let response = app.client.get("/api/v1/user/999/avatar").dispatch().await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn saves_books_to_tabs() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+    let book_id = app.fixtures.book_id;
+    let save_book = json!({"user_id": reader_id, "book_id": book_id, "tab_name": "Reading"}).to_string();
+
+    for _ in 0..2 {
+        let response = app.client.post("/api/v1/user/save-book")
+            .header(ContentType::JSON)
+            .body(save_book.clone())
+            .dispatch()
+            .await;
+        assert_eq!(json(response).await, "Tab Reading was updated");
+    }
+
+    let user = json(app.client.get(format!("/api/v1/user/{}", reader_id)).dispatch().await).await;
+    assert_eq!(user["saved_books"], json!({"Reading": [book_id]}));
+
+    let response = app.client.delete("/api/v1/user/delete-book")
+        .header(ContentType::JSON)
+        .body(save_book)
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await, "Tab Reading was updated");
+
+    let user = json(app.client.get(format!("/api/v1/user/{}", reader_id)).dispatch().await).await;
+    assert_eq!(user["saved_books"], json!({"Reading": []}));
+}
+
+#[rocket::async_test]
+async fn rejects_saving_to_missing_books_and_tabs() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+
+    let response = app.client.post("/api/v1/user/save-book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "book_id": 999, "tab_name": "Reading"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::InternalServerError).await;
+    assert_eq!(body["message"], "No book with id 999");
+
+    let response = app.client.post("/api/v1/user/save-book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": 999, "book_id": app.fixtures.book_id, "tab_name": "Reading"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::InternalServerError).await;
+    assert_eq!(body["message"], "No user with id 999");
+
+    let response = app.client.post("/api/v1/user/save-book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "book_id": app.fixtures.book_id, "tab_name": ""}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::UnprocessableEntity).await;
+
+    let response = app.client.delete("/api/v1/user/delete-book")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "book_id": app.fixtures.book_id, "tab_name": "Finished"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::InternalServerError).await;
+    assert_eq!(body["message"], format!("User with {} id does not have tab Finished", reader_id));
+}
+
+#[rocket::async_test]
+async fn adds_and_deletes_tabs() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+
+    let response = app.client.post(format!("/api/v1/user/tab/{}/Finished", reader_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post(format!("/api/v1/user/tab/{}/Finished", reader_id)).dispatch().await;
+    let body = error(response, Status::InternalServerError).await;
+    assert_eq!(body["message"], format!("User with {} id already has Finished tab", reader_id));
+
+    let user = json(app.client.get(format!("/api/v1/user/{}", reader_id)).dispatch().await).await;
+    assert_eq!(user["saved_books"], json!({"Finished": []}));
+
+    let response = app.client.delete(format!("/api/v1/user/tab/{}/Finished", reader_id)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let user = json(app.client.get(format!("/api/v1/user/{}", reader_id)).dispatch().await).await;
+    assert_eq!(user["saved_books"], json!({}));
+
+    let response = app.client.delete(format!("/api/v1/user/tab/{}/Finished", reader_id)).dispatch().await;
+    error(response, Status::InternalServerError).await;
+
+    let response = app.client.post("/api/v1/user/tab/999/Finished").dispatch().await;
+    error(response, Status::InternalServerError).await;
+}
+
+#[rocket::async_test]
+async fn logs_in_and_out() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "reader@egline.com", "password": PASSWORD}).to_string())
+        .dispatch()
+        .await;
+    let user = json(response).await;
+    assert_eq!(user["id"], app.fixtures.reader.id);
+    let token = user["token"].as_str().unwrap().to_string();
+
+    let response = app.client.post("/api/v1/user/logout").header(bearer(&token)).dispatch().await;
+    assert_eq!(json(response).await, "Number of deleted entries: 1");
+
+    let response = app.client.post("/api/v1/user/logout").header(bearer(&token)).dispatch().await;
+    error(response, Status::Unauthorized).await;
+
+    let response = app.client.post("/api/v1/user/logout").dispatch().await;
+    error(response, Status::Unauthorized).await;
+}
+
+#[rocket::async_test]
+async fn rejects_wrong_credentials() {
+    let app = TestApp::new().await;
+
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "reader@egline.com", "password": "wrong-password"}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::BadRequest).await;
+    assert_eq!(body["message"], "Email or passwrod are not valid");
+
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "reader", "password": ""}).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"email": ["must be a valid email"], "password": ["must not be empty"]}));
+}
+
+#[rocket::async_test]
+async fn edits_profiles_with_the_current_password() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+
+    let response = app.client.post(format!("/api/v1/user/edit/{}", reader_id))
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "reader@egline.com",
+            "display_name": "reader",
+            "password": "wrong-password",
+            "new_password": "new-password"
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"password": ["does not match the current password"]}));
+
+    // Without the current password the new one is refused as well
+    let response = app.client.post(format!("/api/v1/user/edit/{}", reader_id))
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "reader@egline.com",
+            "display_name": "reader",
+            "password": "",
+            "new_password": "new-password"
+        }).to_string())
+        .dispatch()
+        .await;
+    let body = error(response, Status::UnprocessableEntity).await;
+    assert_eq!(body["errors"], json!({"password": ["is required to change the password"]}));
+
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "reader@egline.com", "password": PASSWORD}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post(format!("/api/v1/user/edit/{}", reader_id))
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "reader@egline.com",
+            "display_name": "renamed reader",
+            "password": PASSWORD,
+            "new_password": "new-password"
+        }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(json(response).await["display_name"], "renamed reader");
+
+    let response = app.client.post("/api/v1/user/login")
+        .header(ContentType::JSON)
+        .body(json!({"email": "reader@egline.com", "password": "new-password"}).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = app.client.post("/api/v1/user/edit/999")
+        .header(ContentType::JSON)
+        .body(json!({
+            "email": "nobody@egline.com",
+            "display_name": "nobody",
+            "password": "",
+            "new_password": ""
+        }).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+}
+
+#[rocket::async_test]
+async fn tracks_reading_progress() {
+    let app = TestApp::new().await;
+    let reader_id = app.fixtures.reader.id;
+    let [first_chapter, second_chapter] = app.fixtures.chapter_ids;
+
+    let progress = json(app.client.get(format!("/api/v1/user/progress/{}", reader_id)).dispatch().await).await;
+    assert_eq!(progress, json!([]));
+
+    for (chapter_id, expected_number) in [(second_chapter, 2), (first_chapter, 2)] {
+        let response = app.client.put("/api/v1/user/progress")
+            .header(ContentType::JSON)
+            .body(json!({"user_id": reader_id, "chapter_id": chapter_id}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(json(response).await["chapter_number"], expected_number);
+    }
+
+    let progress = json(app.client.get(format!("/api/v1/user/progress/{}", reader_id)).dispatch().await).await;
+    assert_eq!(progress, json!([{"user_id": reader_id, "book_id": app.fixtures.book_id, "chapter_number": 2}]));
+
+    let response = app.client.put("/api/v1/user/progress")
+        .header(ContentType::JSON)
+        .body(json!({"user_id": reader_id, "chapter_id": 999}).to_string())
+        .dispatch()
+        .await;
+    error(response, Status::NotFound).await;
+}